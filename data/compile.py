@@ -1,14 +1,11 @@
 import csv
 import logging
-from concurrent.futures import Future, ThreadPoolExecutor
-from datetime import datetime
 from multiprocessing import Process
 
 import polars as pl
 import processors.areatree.process as areatree
 import yaml
 from external.loaders.opening_hours import load_opening_hours
-from pipeline_types import Entry
 from processors import (
     aliases,
     coords,
@@ -21,7 +18,6 @@ from processors import (
     roomfinder,
     search,
     sections,
-    sitemap,
     structure,
     studierendenwerk,
     tumonline,
@@ -37,7 +33,6 @@ from processors.exports import ranking_factors as ranking_factors_export
 from processors.exports import sources as sources_export
 from processors.exports import urls as urls_export
 from processors.exports import usages as usages_export
-from processors.sitemap import SimplifiedSitemaps
 from utils import DEV_MODE, setup_logging
 
 _logger = logging.getLogger(__name__)
@@ -117,32 +112,10 @@ def main() -> None:
     resizer = Process(target=images.resize_and_crop)
     resizer.start()
 
-    # Kick off sitemap network IO in worker threads so it overlaps with the main pipeline.
-    # The threads release the GIL during socket reads, so the merge/lazy work runs unimpeded.
-    # try/finally guarantees the executor is shut down even on early failure.
-    sitemap_io = ThreadPoolExecutor(max_workers=3, thread_name_prefix="sitemap-io")
-    try:
-        fut_old_data = sitemap_io.submit(sitemap.fetch_old_data)
-        fut_old_sitemaps = sitemap_io.submit(sitemap.fetch_online_sitemaps)
-        fut_web_sitemap = sitemap_io.submit(sitemap.download_online_sitemap, sitemap.WEB_SITEMAP_URL)
-
-        _run_pipeline(
-            resizer=resizer,
-            fut_old_data=fut_old_data,
-            fut_old_sitemaps=fut_old_sitemaps,
-            fut_web_sitemap=fut_web_sitemap,
-        )
-    finally:
-        sitemap_io.shutdown(wait=True)
+    _run_pipeline(resizer=resizer)
 
 
-def _run_pipeline(
-    *,
-    resizer: Process,
-    fut_old_data: Future[list[Entry]],
-    fut_old_sitemaps: Future[SimplifiedSitemaps],
-    fut_web_sitemap: Future[dict[str, datetime]],
-) -> None:
+def _run_pipeline(*, resizer: Process) -> None:
     # --- Read base data ---
     _logger.info("-- 00 areatree")
     df = areatree.read_areatree()
@@ -292,7 +265,9 @@ def _run_pipeline(
     export.export_known_usages(df)
     export.export_tumonline_orgs_parquet()
     export.export_known_orgs()
+    export.export_semesters_parquet()
     export.export_events_parquet()
+    export.export_key_successors_parquet(df)
     ranking_factors_export.export_ranking_factors_parquet(df)
     operators_export.export_operators_de_parquet(df)
     operators_export.export_operators_en_parquet(df)
@@ -302,11 +277,6 @@ def _run_pipeline(
     urls_export.export_urls_en_parquet(df)
     parents_export.export_parents_parquet(df)
     location_images_export.export_location_images_parquet(df)
-    sitemap.generate_sitemap(
-        old_data=fut_old_data.result(),
-        old_sitemaps=fut_old_sitemaps.result(),
-        web_sitemap=fut_web_sitemap.result(),
-    )
 
     resizer.join(timeout=60 * 4)
     if resizer.exitcode != 0:
@@ -1,7 +1,7 @@
 import orjson
 import polars as pl
 from pipeline_types import Entry, FlatRow, Json
-from utils import TranslatableStr
+from utils import LANGUAGE_TAG_RE, TranslatableStr
 
 _DEFAULT_DTYPE: pl.DataType = pl.Utf8()
 
@@ -22,13 +22,16 @@ def ensure_columns(df: pl.DataFrame, columns: dict[str, pl.DataType]) -> pl.Data
 
 
 def translatable_to_columns(field: str, value: Json) -> dict[str, str | None]:
-    """Split a TranslatableStr or plain string into ``{field}_de`` / ``{field}_en`` columns."""
+    """
+    Split a TranslatableStr or plain string into ``{field}_de`` / ``{field}_en`` columns.
+
+    Further translations of a TranslatableStr are split into ``{field}_{lang}`` columns, e.g. ``name_zh``.
+    """
     if value is None:
         return {f"{field}_de": None, f"{field}_en": None}
-    if isinstance(value, TranslatableStr):
-        return {f"{field}_de": value["de"], f"{field}_en": value["en"]}
     if isinstance(value, dict) and "de" in value:
-        return {f"{field}_de": value.get("de"), f"{field}_en": value.get("en")}
+        further = {f"{field}_{lang}": text for lang, text in value.items() if LANGUAGE_TAG_RE.fullmatch(lang)}
+        return {**further, f"{field}_de": value.get("de"), f"{field}_en": value.get("en")}
     if isinstance(value, str):
         return {f"{field}_de": value, f"{field}_en": value}
     return {f"{field}_de": str(value), f"{field}_en": str(value)}
@@ -183,12 +186,24 @@ def flatten_entry(entry_id: str, entry: Entry) -> FlatRow:
     return row
 
 
+def further_translations(row: FlatRow, field: str) -> dict[str, str]:
+    """Collect the translations beyond ``de`` / ``en`` from the ``{field}_{lang}`` columns"""
+    prefix = f"{field}_"
+    translations: dict[str, str] = {}
+    for column, text in row.items():
+        lang = column.removeprefix(prefix)
+        if text and column.startswith(prefix) and lang not in ("de", "en") and LANGUAGE_TAG_RE.fullmatch(lang):
+            translations[lang] = text
+    return translations
+
+
 def unflatten_row(row: FlatRow) -> Entry:
     """Reconstruct the nested API dict from flat DataFrame columns."""
     name_de = row.get("name_de") or row.get("name")
     name_en = row.get("name_en")
-    if name_de and name_en and name_de != name_en:
-        name_val: Json = {"en": name_en, "de": name_de}
+    further_names = further_translations(row, "name")
+    if name_de and name_en and (name_de != name_en or further_names):
+        name_val: Json = {"en": name_en, "de": name_de, **further_names}
     else:
         name_val = name_de
 
@@ -258,7 +273,11 @@ def unflatten_row(row: FlatRow) -> Entry:
     if operator_code := row.get("props_operator_code"):
         props["operator"] = {
             "code": operator_code,
-            "name": {"en": row.get("props_operator_name_en"), "de": row.get("props_operator_name_de")},
+            "name": {
+                "en": row.get("props_operator_name_en"),
+                "de": row.get("props_operator_name_de"),
+                **further_translations(row, "props_operator_name"),
+            },
             "url": row.get("props_operator_url"),
             "id": row.get("props_operator_id"),
         }
@@ -276,7 +295,11 @@ def unflatten_row(row: FlatRow) -> Entry:
     if generic_json := row.get("props_generic_json"):
         props["generic"] = orjson.loads(generic_json)
     if comment_de := row.get("props_comment_de"):
-        props["comment"] = {"en": row.get("props_comment_en", ""), "de": comment_de}
+        props["comment"] = {
+            "en": row.get("props_comment_en", ""),
+            "de": comment_de,
+            **further_translations(row, "props_comment"),
+        }
     if iris_coverage_building_ids := row.get("iris_coverage_building_ids"):
         props["iris_coverage_building_ids"] = iris_coverage_building_ids
 
@@ -286,7 +309,7 @@ def unflatten_row(row: FlatRow) -> Entry:
     if row.get("usage_name_de") or row.get("usage_din_277"):
         usage: Entry = {}
         if name_de := row.get("usage_name_de"):
-            usage["name"] = {"en": row.get("usage_name_en"), "de": name_de}
+            usage["name"] = {"en": row.get("usage_name_en"), "de": name_de, **further_translations(row, "usage_name")}
         if din_277 := row.get("usage_din_277"):
             usage["din_277"] = din_277
         if din_277_desc := row.get("usage_din_277_desc"):
@@ -321,13 +344,14 @@ def unflatten_row(row: FlatRow) -> Entry:
     if row.get("type_common_name") or row.get("type_common_name_de"):
         tcn_de = row.get("type_common_name_de") or row.get("type_common_name")
         tcn_en = row.get("type_common_name_en") or tcn_de
+        tcn_further = further_translations(row, "type_common_name")
         # usage_name values are always TranslatableStr dicts; TYPE_COMMON_NAME_BY_TYPE plain
         # strings stay as strings, so we only collapse to a string for the known plain-string types.
         plain_string_types = {"Campus", "POI"}
-        if tcn_de in plain_string_types and tcn_de == tcn_en:
+        if tcn_de in plain_string_types and tcn_de == tcn_en and not tcn_further:
             result["type_common_name"] = tcn_de
         else:
-            result["type_common_name"] = {"en": tcn_en, "de": tcn_de}
+            result["type_common_name"] = {"en": tcn_en, "de": tcn_de, **tcn_further}
 
     if buildings_overview_json := row.get("sections_buildings_overview_json"):
         result.setdefault("sections", {})["buildings_overview"] = orjson.loads(buildings_overview_json)
@@ -11,6 +11,7 @@ import polars as pl
 import xxhash
 import yaml
 from external.loaders.events import load_events
+from external.loaders.semesters import load_semester
 from external.loaders.tumonline_orgs import load_tumonline_orgs
 from external.models.common import PydanticConfiguration
 from external.schemas.events import EventsSchema
@@ -142,8 +143,8 @@ _ALLOWED_VARIATION_RE = re.compile(rb"prandtl[ -]+str", re.IGNORECASE)
 
 
 def _de(value: Json) -> Json:
-    """Pick the German variant from a TranslatableStr-shaped dict; pass-through otherwise."""
-    if isinstance(value, dict) and value.keys() <= {"de", "en"}:
+    """Pick the German variant from a TranslatableStr-shaped dict (of any languages); pass-through otherwise."""
+    if isinstance(value, dict) and (value.keys() <= {"de", "en"} or TranslatableStr.is_translation(value)):
         return value.get("de", value.get("en", {}))
     return value
 
@@ -384,6 +385,24 @@ def export_tumonline_orgs_parquet() -> None:
     load_tumonline_orgs().write_parquet(OUTPUT_DIR_PATH / "tumonline_orgs.parquet")
 
 
+def export_semesters_parquet() -> None:
+    """
+    Read semesters.csv and write semesters.parquet.
+
+    The server assigns lectures to the semester they take place in.
+    Dates are written as ISO-8601 strings, like the datetimes in events.parquet.
+    """
+    (
+        load_semester()
+        .select(
+            pl.col("key"),
+            pl.col("start").dt.to_string("%Y-%m-%d"),
+            pl.col("end").dt.to_string("%Y-%m-%d"),
+        )
+        .write_parquet(OUTPUT_DIR_PATH / "semesters.parquet")
+    )
+
+
 def export_known_orgs() -> None:
     """Export the known TUMonline orgs as json"""
     # `org_id` is the value submitted as `events.organising_org_id`
@@ -405,3 +424,24 @@ def export_events_parquet() -> None:
     and `ends_at >= starts_at` (matching the DB CHECK constraint).
     """
     load_events().write_parquet(OUTPUT_DIR_PATH / "events.parquet")
+
+
+def export_key_successors_parquet(df: pl.DataFrame) -> None:
+    """
+    Read key_successors.csv and write key_successors.parquet.
+
+    The server redirects links to removed keys to their successor (e.g. after a room was
+    renumbered). Mappings whose key still exists or whose successor is gone are dropped,
+    so a redirect never points at a 404.
+    """
+    data_dir = Path(__file__).parent.parent
+    known_ids = df.get_column("id")
+    (
+        pl.read_csv(
+            data_dir / "sources" / "key_successors.csv",
+            schema={"id": pl.String, "successor": pl.String},
+        )
+        .filter(~pl.col("id").is_in(known_ids) & pl.col("successor").is_in(known_ids))
+        .select(pl.col("id").alias("key"), pl.col("successor"))
+        .write_parquet(OUTPUT_DIR_PATH / "key_successors.parquet")
+    )
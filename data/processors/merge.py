@@ -26,15 +26,16 @@ def load_yaml(path: Path) -> Json:
     """
 
     def add_translatable_str(value: str | list[Json] | dict[str, Json]) -> Json:
-        """Recursively change all {de: ..., en:...] to a TranslatableStr"""
+        """Recursively change all {de: ..., en: ..., <further languages>: ...} to a TranslatableStr"""
         if isinstance(value, bool | float | int | str) or value is None:
             return value
         if isinstance(value, list):
             return [add_translatable_str(v) for v in value]
         if isinstance(value, dict):
-            # We consider each dict that has only the keys "de" and "en" as translated string
-            if set(value.keys()) == {"de", "en"}:
-                return TranslatableStr(value["de"], value["en"])
+            # We consider each dict of strings that has the keys "de" and "en" and otherwise only language tags
+            # (e.g. "zh" or "es") as translated string
+            if TranslatableStr.is_translation(value):
+                return TranslatableStr.from_translations(value)
 
             return {k: add_translatable_str(v) for k, v in value.items()}
         raise ValueError(f"Unhandled type {type(value)}")
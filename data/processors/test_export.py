@@ -1,6 +1,7 @@
 from pipeline_types import Entry
 from utils import TranslatableStr
 
+from processors.df_utils import flatten_entry, unflatten_row
 from processors.export import extract_exported_item
 
 
@@ -46,3 +47,16 @@ def test_root_parent_type_is_synthesised_without_a_data_entry() -> None:
     result = extract_exported_item(data, data["garching"])
 
     assert result["parent_types"] == ["root"]
+
+
+def test_further_translations_survive_the_export() -> None:
+    """Languages beyond de/en (e.g. for the main buildings) have to reach the server through the flat columns."""
+    data = _data()
+    data["mi"]["name"] = TranslatableStr("Mathematik/Informatik", "Mathematics/Informatics", {"zh": "数学/信息学"})
+    data["mi"]["props"] = {"comment": TranslatableStr("Haupteingang", "Main entrance", {"es": "Entrada principal"})}
+
+    entry = unflatten_row(flatten_entry("mi", data["mi"]))
+    result = extract_exported_item(data, entry)
+
+    assert result["name"] == {"de": "Mathematik/Informatik", "en": "Mathematics/Informatics", "zh": "数学/信息学"}
+    assert result["props"]["comment"] == {"de": "Haupteingang", "en": "Main entrance", "es": "Entrada principal"}
@@ -1,5 +1,7 @@
 import logging
 import os
+import re
+from collections.abc import Mapping
 from math import acos, cos, radians, sin
 from pathlib import Path
 
@@ -16,6 +18,9 @@ with TRANSLATION_BUFFER_PATH.open(encoding="utf-8") as yaml_file:
 
 DEV_MODE = "GIT_COMMIT_SHA" not in os.environ
 
+# BCP 47 language tags as they appear in the data, e.g. `zh`, `es` or `zh-Hant`
+LANGUAGE_TAG_RE = re.compile(r"[a-z]{2,3}(-[A-Za-z0-9]{2,8})*")
+
 
 class TranslatableStr(dict[str, str]):
     """
@@ -30,22 +35,59 @@ class TranslatableStr(dict[str, str]):
     to turn a message into a translated string.
 
     Translatable strings will be exported as {"de": "<de string>", "en": "<en string>"}.
+    Further (partial) translations, e.g. {"zh": "<zh string>"}, are exported alongside.
+    They are given explicitly or in the translation buffer as {"en": ..., "zh": ...} instead of only the english string.
     """
 
-    def __init__(self, message: str, en_message: str | None = None) -> None:
+    def __init__(
+        self,
+        message: str,
+        en_message: str | None = None,
+        translations: Mapping[str, str] | None = None,
+    ) -> None:
         if not isinstance(message, str):
             raise TypeError("message must be a str")
         if not message.strip():
             raise ValueError("message must not be empty")
         if en_message is None:
             if message in TRANSLATION_BUFFER:
-                en_message = TRANSLATION_BUFFER[message]
+                buffered = TRANSLATION_BUFFER[message]
+                if isinstance(buffered, Mapping):
+                    en_message = buffered.get("en") or message
+                    translations = {**buffered, **(translations or {})}
+                else:
+                    en_message = buffered
             else:
                 en_message = message
                 TRANSLATION_BUFFER[message] = ""
                 with TRANSLATION_BUFFER_PATH.open("w", encoding="utf-8") as file:
                     yaml.dump(TRANSLATION_BUFFER, file)
-        super().__init__(en=en_message, de=message)
+        further = {lang: text for lang, text in (translations or {}).items() if lang not in ("de", "en") and text}
+        super().__init__(en=en_message, de=message, **further)
+
+    @staticmethod
+    def is_translation(value: Json) -> bool:
+        """Whether ``value`` is a dict of translations like {"de": ..., "en": ..., "zh": ...}"""
+        return (
+            isinstance(value, dict)
+            and {"de", "en"} <= value.keys()
+            and all(isinstance(k, str) and LANGUAGE_TAG_RE.fullmatch(k) for k in value)
+            and all(isinstance(v, str) for v in value.values())
+        )
+
+    @classmethod
+    def from_translations(cls, translations: Mapping[str, str]) -> TranslatableStr:
+        """Build a TranslatableStr from a dict of translations like {"de": ..., "en": ..., "zh": ...}"""
+        return cls(translations["de"], translations["en"], translations)
+
+    @property
+    def further_translations(self) -> dict[str, str]:
+        """The translations beyond german and english"""
+        return {lang: text for lang, text in self.items() if lang not in ("de", "en")}
+
+    def _languages_with(self, other: TranslatableStr) -> set[str]:
+        """The further languages of either string; a missing translation falls back to english."""
+        return self.further_translations.keys() | other.further_translations.keys()
 
     def __hash__(self) -> int:  # type: ignore[override]
         """Return a hash as if this was a string."""
@@ -61,22 +103,27 @@ class TranslatableStr(dict[str, str]):
 
     def __add__(self, other: str | TranslatableStr) -> TranslatableStr:
         """Concatenate two TranslatableStr or a TranslatableStr with a string ."""
-        if isinstance(other, str):
-            return TranslatableStr(self["de"] + other, self["en"] + other)
         if isinstance(other, TranslatableStr):
-            return TranslatableStr(self["de"] + other["de"], self["en"] + other["en"])
+            further = {
+                lang: self.get(lang, self["en"]) + other.get(lang, other["en"]) for lang in self._languages_with(other)
+            }
+            return TranslatableStr(self["de"] + other["de"], self["en"] + other["en"], further)
+        if isinstance(other, str):
+            further = {lang: text + other for lang, text in self.further_translations.items()}
+            return TranslatableStr(self["de"] + other, self["en"] + other, further)
         raise ValueError(f"{self} + {other} is not implmented")
 
     def __radd__(self, other: str) -> TranslatableStr:
         """Concatenate a TranslatableStr onto a string"""
         if isinstance(other, str):
-            return TranslatableStr(other + self["de"], other + self["en"])
+            further = {lang: other + text for lang, text in self.further_translations.items()}
+            return TranslatableStr(other + self["de"], other + self["en"], further)
         raise ValueError(f"{other} + {self} is not implmented")
 
     def format(self, *args: Json, **kwargs: Json) -> TranslatableStr:
         """Apply the format-method to the contained data, as if the class itsself was a string."""
-        self["de"] = self["de"].format(*args, **kwargs)
-        self["en"] = self["en"].format(*args, **kwargs)
+        for lang, text in self.items():
+            self[lang] = text.format(*args, **kwargs)
         return self
 
 
@@ -47,6 +47,23 @@ tables.rooms =
         { column = "geom",                 type = "geometry", not_null = true }
       }
     )
+-- Building footprints and roads are only the outdoor context of the locally rendered
+-- preview fallback of the server, the map itself uses the planetiler tiles for them.
+tables.buildings =
+    osm2pgsql.define_area_table(
+      "buildings",
+      {
+        { column = "geom", type = "geometry", not_null = true }
+      }
+    )
+tables.roads =
+    osm2pgsql.define_way_table(
+      "roads",
+      {
+        { column = "kind", type = "text",       not_null = true },
+        { column = "geom", type = "linestring", not_null = true }
+      }
+    )
 tables.pois =
     osm2pgsql.define_table(
       {
@@ -291,6 +308,18 @@ local function toilet_flags(tags)
   }
 end
 
+-- How prominently a highway=* is drawn, nil for highways which are not drawn at all
+local function road_kind(highway)
+  if highway == "motorway" or highway == "trunk" or highway == "primary" or highway == "secondary" or highway == "tertiary" then
+    return "major"
+  elseif highway == "unclassified" or highway == "residential" or highway == "living_street" or highway == "service" then
+    return "minor"
+  elseif highway == "pedestrian" or highway == "footway" or highway == "cycleway" or highway == "path" or highway == "steps" then
+    return "path"
+  end
+  return nil
+end
+
 -- Called for every node in the input. The `object` argument contains all the
 -- attributes of the node like `id`, `version`, etc. as well as all tags as a
 -- Lua table (`object.tags`).
@@ -406,6 +435,13 @@ end
 function osm2pgsql.process_way(object)
   --  Uncomment next line to look at the object data:
   --  print(inspect(object))
+  if object.tags.building ~= nil and object.tags.building ~= "no" and object.is_closed then
+    tables.buildings:insert({ geom = object:as_polygon() })
+  end
+  local kind = road_kind(object.tags.highway)
+  if kind ~= nil and object.tags.indoor == nil and object.tags.level == nil then
+    tables.roads:insert({ kind = kind, geom = object:as_linestring() })
+  end
   if object.tags.building ~= nil then
     object.tags.indoor = nil
     object.tags.level = nil
@@ -481,6 +517,9 @@ function osm2pgsql.process_relation(object)
   --  Uncomment next line to look at the object data:
   --  print(inspect(object))
 
+  if object.tags.type == "multipolygon" and object.tags.building ~= nil and object.tags.building ~= "no" then
+    tables.buildings:insert({ geom = object:as_multipolygon() })
+  end
   if clean_tags_indoor(object.tags) then
     return
   end
@@ -0,0 +1,21 @@
+-- Keys which vanish from the status file used to be hard-deleted, so bookmarks and
+-- printed QR codes of renumbered or demolished rooms turned into a bare 404.
+-- `tombstones` remembers what was removed (and what it was called), while
+-- `key_successors` holds the data-maintained "this room is now that room" mapping.
+-- Neither table references `de`: both describe keys which no longer exist there.
+CREATE TABLE tombstones
+(
+    key        TEXT PRIMARY KEY NOT NULL,
+    visible_id TEXT             NOT NULL,
+    type       TEXT             NOT NULL,
+    name       TEXT             NOT NULL,
+    removed_at TIMESTAMPTZ      NOT NULL DEFAULT NOW()
+);
+CREATE INDEX tombstones_visible_id_idx ON tombstones (visible_id);
+
+CREATE TABLE key_successors
+(
+    key       TEXT PRIMARY KEY NOT NULL,
+    successor TEXT             NOT NULL,
+    CHECK (key <> successor)
+);
@@ -0,0 +1,4 @@
+-- When the data of an entry last changed, used as `lastmod` in the sitemap.
+-- Entries which exist before this migration are treated as having changed now.
+ALTER TABLE de ADD COLUMN hash_updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+COMMENT ON COLUMN de.hash_updated_at IS 'the last time the hash (and thus the data) of this entry changed';
@@ -0,0 +1,23 @@
+-- `de` and `en` hold the complete data of every entry, and the derived tables (search,
+-- calendar, operators_*, urls_*, ...) are built from them.
+-- Translations into further languages (e.g. names of the main buildings in Chinese or Spanish)
+-- are partial, so `localised` only holds them for entries where at least one string is translated.
+-- Untranslated strings are filled in along the fallback chain `{lang} -> en -> de` while loading.
+CREATE TABLE localised
+(
+    key  TEXT  NOT NULL REFERENCES de (key) ON DELETE CASCADE,
+    lang TEXT  NOT NULL,
+    data JSONB NOT NULL,
+    PRIMARY KEY (key, lang)
+);
+
+-- The data of all entries in all languages, keyed by `(key, lang)`
+CREATE VIEW location_data AS
+SELECT key, 'de' AS lang, data
+FROM de
+UNION ALL
+SELECT key, 'en', data
+FROM en
+UNION ALL
+SELECT key, lang, data
+FROM localised;
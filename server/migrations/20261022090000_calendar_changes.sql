@@ -0,0 +1,27 @@
+-- `calendar` only holds the current state of each room, as every scrape replaces its events.
+-- To tell students about cancelled, moved or rescheduled lectures, the differences between
+-- two scrapes of a room are recorded here.
+-- Only events which had not ended when the change was detected are recorded, so events dropping
+-- out of the scraped time window are not mistaken for cancellations.
+CREATE TABLE calendar_changes
+(
+    id                 BIGSERIAL PRIMARY KEY NOT NULL,
+    event_id           INTEGER               NOT NULL,
+    kind               TEXT                  NOT NULL CHECK (kind IN ('added', 'removed', 'rescheduled', 'moved')),
+    detected_at        TIMESTAMPTZ           NOT NULL DEFAULT NOW(),
+    title_de           TEXT                  NOT NULL,
+    title_en           TEXT                  NOT NULL,
+    entry_type         TEXT                  NOT NULL,
+    -- where and when the event was before the change, NULL for `added`
+    previous_room_code TEXT,
+    previous_start_at  TIMESTAMPTZ,
+    previous_end_at    TIMESTAMPTZ,
+    -- where and when the event is after the change, NULL for `removed`
+    room_code          TEXT,
+    start_at           TIMESTAMPTZ,
+    end_at             TIMESTAMPTZ
+);
+CREATE INDEX calendar_changes_detected_at ON calendar_changes (detected_at);
+CREATE INDEX calendar_changes_event_id ON calendar_changes (event_id);
+CREATE INDEX calendar_changes_previous_room_code ON calendar_changes (previous_room_code);
+CREATE INDEX calendar_changes_room_code ON calendar_changes (room_code);
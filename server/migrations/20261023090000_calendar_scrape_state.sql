@@ -0,0 +1,18 @@
+-- Calendars used to be scraped whenever `last_calendar_scrape_at` was older than 60 minutes.
+-- Rooms which always fail (e.g. because TUMonline returns a body which cannot be decoded) were
+-- thus retried forever, and rooms whose calendar never changes were scraped as often as lecture halls.
+-- The scraper now schedules the next scrape of each room based on its history.
+CREATE TABLE calendar_scrape_state
+(
+    key                  TEXT        NOT NULL PRIMARY KEY REFERENCES de (key) ON DELETE CASCADE,
+    -- failed scrapes since the last successful one, for exponential backoff
+    consecutive_failures INTEGER     NOT NULL DEFAULT 0,
+    -- successful scrapes since the events of the room last changed
+    unchanged_scrapes    INTEGER     NOT NULL DEFAULT 0,
+    -- `success`, `fetch_error`, `decode_error` or `store_error`
+    last_result          TEXT        NOT NULL,
+    last_attempt_at      TIMESTAMPTZ NOT NULL,
+    last_change_at       TIMESTAMPTZ,
+    next_due_at          TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX calendar_scrape_state_next_due_at ON calendar_scrape_state (next_due_at);
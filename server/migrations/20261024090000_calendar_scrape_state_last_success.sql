@@ -0,0 +1,7 @@
+-- The data quality report lists rooms whose calendar was not scraped successfully for a while.
+-- `last_attempt_at` is not enough, as a room may have failed after an earlier success.
+ALTER TABLE calendar_scrape_state
+    ADD COLUMN last_success_at TIMESTAMPTZ;
+UPDATE calendar_scrape_state
+SET last_success_at = last_attempt_at
+WHERE last_result = 'success';
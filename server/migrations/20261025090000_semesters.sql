@@ -0,0 +1,8 @@
+-- Loaded from semesters.parquet, used to assign lectures to the semester they take place in
+CREATE TABLE semesters
+(
+    key        TEXT PRIMARY KEY NOT NULL,
+    start_date DATE             NOT NULL,
+    end_date   DATE             NOT NULL,
+    CHECK (end_date >= start_date)
+);
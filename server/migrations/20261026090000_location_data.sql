@@ -0,0 +1,173 @@
+-- `de`, `en` and `localised` (joined by the `location_data` view) are replaced by one table keyed by `(key, lang)`.
+-- `locations` holds what does not depend on the language and is what other tables reference.
+-- Every entry has complete `de` and `en` rows. Further languages (e.g. names of the main buildings in
+-- Chinese or Spanish) only exist for entries where at least one string is translated, with untranslated
+-- strings filled in along the fallback chain `{lang} -> en -> de` while loading.
+CREATE TABLE locations
+(
+    key                     TEXT PRIMARY KEY NOT NULL,
+    hash                    BIGINT           DEFAULT 0, -- the chance of an empty hash is astronomically slim
+    hash_updated_at         TIMESTAMPTZ      NOT NULL DEFAULT NOW(),
+    last_calendar_scrape_at TIMESTAMPTZ      DEFAULT NULL
+);
+COMMENT ON COLUMN locations.hash_updated_at IS 'the last time the hash (and thus the data) of this entry changed';
+COMMENT ON COLUMN locations.last_calendar_scrape_at IS 'the last time the calendar was scraped for this room';
+CREATE INDEX locations_hash_lut ON locations (key, hash);
+
+INSERT INTO locations(key, hash, hash_updated_at, last_calendar_scrape_at)
+SELECT key, hash, hash_updated_at, last_calendar_scrape_at
+FROM de;
+
+DROP VIEW location_data;
+CREATE TABLE location_data
+(
+    key                 TEXT    NOT NULL REFERENCES locations (key) ON UPDATE CASCADE ON DELETE CASCADE,
+    lang                TEXT    NOT NULL,
+    data                JSONB   NOT NULL,
+    lat                 FLOAT   NOT NULL GENERATED ALWAYS AS (CAST(data -> 'coords' ->> 'lat' AS FLOAT)) STORED,
+    lon                 FLOAT   NOT NULL GENERATED ALWAYS AS (CAST(data -> 'coords' ->> 'lon' AS FLOAT)) STORED,
+    name                TEXT    NOT NULL GENERATED ALWAYS AS (CAST(data ->> 'name' AS TEXT)) STORED,
+    type_common_name    TEXT    NOT NULL GENERATED ALWAYS AS (CAST(data ->> 'type_common_name' AS TEXT)) STORED,
+    type                TEXT    NOT NULL GENERATED ALWAYS AS (CAST(data ->> 'type' AS TEXT)) STORED,
+    calendar_url        TEXT GENERATED ALWAYS AS (CAST(data -> 'props' ->> 'calendar_url' AS TEXT)) STORED,
+    tumonline_room_nr   INTEGER GENERATED ALWAYS AS (CAST(data -> 'props' ->> 'tumonline_room_nr' AS INTEGER)) STORED,
+    coordinate_accuracy TEXT GENERATED ALWAYS AS (data -> 'coords' ->> 'accuracy') STORED,
+    coordinate_source   TEXT    NOT NULL GENERATED ALWAYS AS (data -> 'coords' ->> 'source') STORED,
+    comment             TEXT GENERATED ALWAYS AS (data -> 'props' ->> 'comment') STORED,
+    usage_id            INTEGER GENERATED ALWAYS AS (hashtext(data -> 'usage' ->> 'name')) STORED,
+    operator_id         INTEGER GENERATED ALWAYS AS ((data -> 'props' -> 'operator' ->> 'id')::integer) STORED,
+    PRIMARY KEY (key, lang)
+);
+
+INSERT INTO location_data(key, lang, data)
+SELECT key, 'de', data
+FROM de
+UNION ALL
+SELECT key, 'en', data
+FROM en
+UNION ALL
+SELECT key, lang, data
+FROM localised;
+
+-- the references to `de` and `en` now point to `locations`
+ALTER TABLE aliases DROP CONSTRAINT aliases_key_fkey;
+ALTER TABLE aliases ADD FOREIGN KEY (key) REFERENCES locations ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE calendar DROP CONSTRAINT calendar_room_code_fkey;
+ALTER TABLE calendar ADD FOREIGN KEY (room_code) REFERENCES locations ON UPDATE CASCADE ON DELETE CASCADE;
+ALTER TABLE calendar_scrape_state DROP CONSTRAINT calendar_scrape_state_key_fkey;
+ALTER TABLE calendar_scrape_state ADD FOREIGN KEY (key) REFERENCES locations ON DELETE CASCADE;
+ALTER TABLE location_images DROP CONSTRAINT location_images_key_fkey;
+ALTER TABLE location_images ADD FOREIGN KEY (key) REFERENCES locations ON DELETE CASCADE;
+ALTER TABLE parents DROP CONSTRAINT parents_key_fkey;
+ALTER TABLE parents ADD FOREIGN KEY (key) REFERENCES locations ON DELETE CASCADE;
+ALTER TABLE ranking_factors DROP CONSTRAINT ranking_factors_id_fkey;
+ALTER TABLE ranking_factors ADD FOREIGN KEY (id) REFERENCES locations ON DELETE CASCADE;
+ALTER TABLE sources DROP CONSTRAINT sources_key_fkey;
+ALTER TABLE sources ADD FOREIGN KEY (key) REFERENCES locations ON DELETE CASCADE;
+ALTER TABLE urls_de DROP CONSTRAINT urls_de_key_fkey;
+ALTER TABLE urls_de ADD FOREIGN KEY (key) REFERENCES locations ON DELETE CASCADE;
+ALTER TABLE urls_en DROP CONSTRAINT urls_en_key_fkey;
+ALTER TABLE urls_en ADD FOREIGN KEY (key) REFERENCES locations ON DELETE CASCADE;
+
+-- the materialized views built from `de` and `en` are rebuilt from `location_data`
+DROP MATERIALIZED VIEW floors_de;
+CREATE MATERIALIZED VIEW floors_de AS
+SELECT DISTINCT (data -> 'props' -> 'floors' ->> 'id')::integer as id,
+                data -> 'props' -> 'floors' ->> 'floor'         as floor,
+                data -> 'props' -> 'floors' ->> 'tumonline'     as tumonline,
+                data -> 'props' -> 'floors' ->> 'type'          as type,
+                data -> 'props' -> 'floors' ->> 'name'          as name
+from location_data
+where lang = 'de';
+
+DROP MATERIALIZED VIEW floors_en;
+CREATE MATERIALIZED VIEW floors_en AS
+SELECT DISTINCT (data -> 'props' -> 'floors' ->> 'id')::integer as id,
+                data -> 'props' -> 'floors' ->> 'floor'         as floor,
+                data -> 'props' -> 'floors' ->> 'tumonline'     as tumonline,
+                data -> 'props' -> 'floors' ->> 'type'          as type,
+                data -> 'props' -> 'floors' ->> 'name'          as name
+from location_data
+where lang = 'en';
+
+DROP MATERIALIZED VIEW computed_properties;
+CREATE MATERIALIZED VIEW computed_properties AS
+WITH facts(key, fact) AS (SELECT key,
+                                 jsonb_array_elements((data -> 'props') -> 'computed') AS fact
+                          FROM location_data
+                          WHERE lang = 'de'),
+     extracted_facts(key, name, value) AS (SELECT facts.key,
+                                                  facts.fact ->> 'name' AS name,
+                                                  facts.fact ->> 'text' AS value
+                                           FROM facts
+                                           where facts.fact ->> 'text' != '')
+SELECT DISTINCT f.key,
+                building_codes.value                                                                 AS building_codes,
+                split_part(address.value, ', ', 1)                                                   AS address,
+                split_part(split_part(address.value, ', ', 2), ' ', 1)::integer                      AS postcode,
+                split_part(split_part(address.value, ', ', 2), ' ', 2)                               AS city,
+                level.value                                                                          AS level,
+                arch_name.value                                                                      AS arch_name,
+                split_part(room_cnt.value, ' (', 1)::integer                                         AS room_cnt,
+                (case
+                     when room_cnt.value like '%(%'
+                         then (split_part(split_part(room_cnt.value, '(', 2), ' ', 1)::integer) end) AS room_cnt_without_corridors,
+                building_cnt.value::integer                                                          AS building_cnt
+FROM extracted_facts f
+         LEFT JOIN extracted_facts building_codes
+                   ON f.key = building_codes.key AND building_codes.name = 'Gebäudekennungen'
+         LEFT JOIN extracted_facts address ON f.key = address.key AND address.name = 'Adresse'
+         LEFT JOIN extracted_facts level ON f.key = level.key AND level.name = 'Stockwerk'
+         LEFT JOIN extracted_facts arch_name ON f.key = arch_name.key AND arch_name.name = 'Architekten-Name'
+         LEFT JOIN extracted_facts room_cnt ON f.key = room_cnt.key AND room_cnt.name = 'Anzahl Räume'
+         LEFT JOIN extracted_facts building_cnt
+                   ON f.key = building_cnt.key AND building_cnt.name = 'Anzahl Gebäude';
+CREATE INDEX IF NOT EXISTS computed_properties_id_idx ON computed_properties (key);
+
+DROP MATERIALIZED VIEW buildings_section;
+CREATE MATERIALIZED VIEW buildings_section AS
+WITH avaliable AS (SELECT key,
+                          jsonb_array_elements(data -> 'sections' -> 'buildings_overview' -> 'entries') AS entry,
+                          (data -> 'sections' -> 'buildings_overview' ->> 'n_visible')::integer       AS n_visible
+                   FROM location_data
+                   WHERE lang = 'de'),
+     data AS (SELECT key,
+                     entry ->> 'id'                                            AS id,
+                     entry ->> 'name'                                          AS name,
+                     entry ->> 'thumb'                                         AS thumb,
+                     entry ->> 'subtext'                                       AS subtext,
+                     substring(entry ->> 'subtext', '([0-9]+) Räume')::integer   AS room_cnt,
+                     substring(entry ->> 'subtext', '([0-9]+) Gebäude')::integer AS building_cnt,
+                     n_visible
+              FROM avaliable)
+SELECT key,
+       id,
+       name,
+       thumb,
+       subtext,
+       row_number() OVER (PARTITION BY key ORDER BY building_cnt DESC, room_cnt DESC) < n_visible AS visible
+FROM data
+ORDER BY key, building_cnt DESC, room_cnt DESC;
+CREATE INDEX IF NOT EXISTS buildings_section_id_idx ON buildings_section (key);
+
+DROP MATERIALIZED VIEW rooms_section;
+CREATE MATERIALIZED VIEW rooms_section AS
+WITH avaliable AS (SELECT key,
+                          jsonb_array_elements(data -> 'sections' -> 'rooms_overview' -> 'usages') AS usage
+                   FROM location_data
+                   WHERE lang = 'de'),
+     data AS (SELECT key,
+                     usage ->> 'name'                          AS name,
+                     jsonb_array_elements(usage -> 'children') AS child
+              FROM avaliable)
+SELECT key,
+       name             AS usage_name,
+       child ->> 'id'   AS location_id,
+       child ->> 'name' AS location_name
+FROM data
+ORDER BY key, name, child ->> 'id';
+CREATE INDEX IF NOT EXISTS rooms_section_id_idx ON rooms_section (key);
+
+DROP TABLE localised;
+DROP TABLE en;
+DROP TABLE de;
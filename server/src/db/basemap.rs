@@ -0,0 +1,106 @@
+use sqlx::PgPool;
+use tracing::debug;
+
+/// The bounding box in web mercator, from `$1..$4` in WGS84
+const BBOX: &str =
+    "WITH bbox AS (SELECT ST_Transform(ST_MakeEnvelope($1, $2, $3, $4, 4326), 3857) AS geom)";
+/// Building footprints, larger ones first
+const BUILDINGS: &str = r"
+    SELECT 'building',
+           NULL,
+           ST_AsGeoJSON(ST_Transform(ST_CurveToLine(g.geom), 4326))::jsonb,
+           0,
+           ST_Area(g.geom)
+    FROM buildings g, bbox b
+    WHERE g.geom && b.geom";
+/// Roads, the most prominent ones last so that they are drawn on top
+const ROADS: &str = r"
+    SELECT 'road',
+           r.kind,
+           ST_AsGeoJSON(ST_Transform(r.geom, 4326))::jsonb,
+           1,
+           CASE r.kind WHEN 'path' THEN 2 WHEN 'minor' THEN 1 ELSE 0 END
+    FROM roads r, bbox b
+    WHERE r.geom && b.geom";
+/// Rooms on the level `$5`, larger ones first so that smaller ones are drawn on top
+const ROOMS: &str = r"
+    SELECT 'room',
+           r.indoor,
+           ST_AsGeoJSON(ST_Transform(ST_CurveToLine(r.geom), 4326))::jsonb,
+           2,
+           ST_Area(r.geom)
+    FROM rooms r, bbox b
+    WHERE r.geom && b.geom AND r.level_min <= $5 AND r.level_max >= $5";
+/// Walls on the level `$5`
+const WALLS: &str = r"
+    SELECT 'wall',
+           NULL,
+           ST_AsGeoJSON(ST_Transform(w.geom, 4326))::jsonb,
+           3,
+           0
+    FROM indoor_ways w, bbox b
+    WHERE w.geom && b.geom AND w.level_min <= $5 AND w.level_max >= $5";
+
+/// A shape of the locally rendered basemap
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BasemapFeature {
+    /// What the shape is: a `building`, `road`, `room` or `wall`
+    pub layer: String,
+    /// `indoor=*` of a room (`room`, `corridor`, `area`, ...) or the kind of a road (`major`, `minor`, `path`)
+    pub class: Option<String>,
+    /// `GeoJSON` geometry, a `(Multi)Polygon` for buildings and rooms and a `LineString` for roads and walls
+    pub geometry: serde_json::Value,
+}
+
+impl BasemapFeature {
+    /// Fetches the buildings, roads and the indoor features on `level` within `[min_lon, min_lat, max_lon, max_lat]`.
+    ///
+    /// Features are ordered as they are drawn: buildings, roads, rooms and walls.
+    /// The tables are owned by osm2pgsql and absent in migration-only setups (or older imports),
+    /// so each is guarded on and the query is composed at runtime (see [`super::geojson::LocationFeature`]).
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_within(
+        pool: &PgPool,
+        [min_lon, min_lat, max_lon, max_lat]: [f64; 4],
+        level: f32,
+    ) -> sqlx::Result<Vec<Self>> {
+        let (buildings, roads, rooms, walls): (bool, bool, bool, bool) = sqlx::query_as(
+            "SELECT to_regclass('public.buildings') IS NOT NULL,
+                    to_regclass('public.roads') IS NOT NULL,
+                    to_regclass('public.rooms') IS NOT NULL,
+                    to_regclass('public.indoor_ways') IS NOT NULL",
+        )
+        .fetch_one(pool)
+        .await?;
+        let parts: Vec<&str> = [
+            (buildings, BUILDINGS),
+            (roads, ROADS),
+            (rooms, ROOMS),
+            (walls, WALLS),
+        ]
+        .into_iter()
+        .filter_map(|(exists, part)| exists.then_some(part))
+        .collect();
+        if parts.is_empty() {
+            debug!("basemap tables absent (osm2pgsql not loaded); nothing to render");
+            return Ok(Vec::new());
+        }
+        let query = format!(
+            "{BBOX}
+SELECT layer, class, geometry
+FROM ({}) features(layer, class, geometry, draw_order, area)
+ORDER BY draw_order, area DESC
+LIMIT 10000",
+            parts.join("\n    UNION ALL")
+        );
+        // only the constant parts above are composed, the bounds and the level are bound
+        sqlx::query_as::<_, Self>(sqlx::AssertSqlSafe(query))
+            .bind(min_lon)
+            .bind(min_lat)
+            .bind(max_lon)
+            .bind(max_lat)
+            .bind(level)
+            .fetch_all(pool)
+            .await
+    }
+}
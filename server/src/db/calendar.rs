@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::{self, Debug, Formatter};
 
+use crate::db::calendar_changes;
 use crate::external::connectum::ConnectumEvent;
 use crate::limited::hash_map::LimitedHashMap;
 use crate::limited::vec::LimitedVec;
@@ -25,12 +26,34 @@ impl CalendarLocation {
         ids: &[String],
     ) -> anyhow::Result<LimitedVec<Self>> {
         let res = sqlx::query_as!(
-        CalendarLocation,
-        "SELECT key,name,last_calendar_scrape_at,calendar_url,type,type_common_name FROM de WHERE key = ANY($1::text[])",
-        ids
-    )
-            .fetch_all(pool)
-            .await?;
+            CalendarLocation,
+            r#"SELECT d.key,d.name,l.last_calendar_scrape_at,d.calendar_url,d.type,d.type_common_name
+            FROM location_data d
+            JOIN locations l ON l.key = d.key
+            WHERE d.key = ANY($1::text[]) AND d.lang = 'de'"#,
+            ids
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(LimitedVec(res))
+    }
+    /// The rooms within `building` (or any other parent) which have a calendar
+    #[tracing::instrument(skip(pool))]
+    pub(crate) async fn get_rooms_in(
+        pool: &PgPool,
+        building: &str,
+    ) -> anyhow::Result<LimitedVec<Self>> {
+        let res = sqlx::query_as!(
+            CalendarLocation,
+            r#"SELECT d.key,d.name,l.last_calendar_scrape_at,d.calendar_url,d.type,d.type_common_name
+            FROM location_data d
+            JOIN locations l ON l.key = d.key
+            WHERE d.key IN (SELECT key FROM parents WHERE id = $1) AND d.calendar_url IS NOT NULL AND d.lang = 'de'
+            ORDER BY d.key"#,
+            building
+        )
+        .fetch_all(pool)
+        .await?;
         Ok(LimitedVec(res))
     }
 }
@@ -61,26 +84,30 @@ impl LocationEvents {
         start_after: &DateTime<Utc>,
         end_before: &DateTime<Utc>,
     ) -> anyhow::Result<LimitedHashMap<String, Self>> {
-        let mut located_events: HashMap<String, Self> = HashMap::new();
-        for location in locations {
-            let events = sqlx::query_as!(
+        let keys = locations.iter().map(|l| l.key.clone()).collect::<Vec<_>>();
+        let events = sqlx::query_as!(
             Event,
             r#"SELECT id,room_code,start_at,end_at,title_de,title_en,stp_type,entry_type,detailed_entry_type
             FROM calendar
-            WHERE room_code = $1 AND start_at >= $2 AND end_at <= $3"#,
-            location.key,
+            WHERE room_code = ANY($1::text[]) AND start_at >= $2 AND end_at <= $3
+            ORDER BY start_at, id"#,
+            &keys,
             start_after,
             end_before
         )
-                .fetch_all(pool)
-                .await?;
-            located_events.insert(
-                location.key.clone(),
-                Self {
-                    location,
-                    events: events.into(),
-                },
-            );
+        .fetch_all(pool)
+        .await?;
+        let mut located_events: HashMap<String, Self> = locations
+            .into_iter()
+            .map(|location| {
+                let events = LimitedVec(Vec::new());
+                (location.key.clone(), Self { events, location })
+            })
+            .collect();
+        for event in events {
+            if let Some(located) = located_events.get_mut(&event.room_code) {
+                located.events.0.push(event);
+            }
         }
         Ok(LimitedHashMap(located_events))
     }
@@ -98,14 +125,63 @@ pub struct Event {
     pub detailed_entry_type: String,
 }
 impl Event {
+    /// Up to `limit` events of the `rooms` in the window, ordered by `start_at` (then `id`).
+    ///
+    /// Only events after `after` (a `(start_at, id)` of the previous page) are returned.
+    #[tracing::instrument(skip(pool))]
+    pub(crate) async fn fetch_page(
+        pool: &PgPool,
+        rooms: &[String],
+        start_after: &DateTime<Utc>,
+        end_before: &DateTime<Utc>,
+        after: Option<(DateTime<Utc>, i32)>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<Self>> {
+        let (after_start_at, after_id) = after.unzip();
+        sqlx::query_as!(
+            Event,
+            r#"SELECT id,room_code,start_at,end_at,title_de,title_en,stp_type,entry_type,detailed_entry_type
+            FROM calendar
+            WHERE room_code = ANY($1::text[]) AND start_at >= $2 AND end_at <= $3
+              AND ($4::timestamptz IS NULL OR (start_at, id) > ($4, $5))
+            ORDER BY start_at, id
+            LIMIT $6"#,
+            rooms,
+            start_after,
+            end_before,
+            after_start_at,
+            after_id,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+    /// Replaces the events of the room `id`.
+    ///
+    /// If `track_changes`, the differences to the previous events are recorded as [`calendar_changes`]
+    /// and their number is returned.
+    /// This is pointless for the first scrape of a room, as all events would count as added.
     #[tracing::instrument(skip(pool))]
     pub async fn store_all(
         pool: &PgPool,
         events: LimitedVec<Self>,
         id: &str,
-    ) -> anyhow::Result<()> {
+        track_changes: bool,
+    ) -> anyhow::Result<usize> {
         // insert into db
         let mut tx = pool.begin().await?;
+        let changes = if track_changes {
+            match calendar_changes::detect(&mut tx, id, &events.0).await {
+                Ok(changes) => changes,
+                Err(e) => {
+                    error!(error = ?e, "could not detect changes to the events");
+                    tx.rollback().await?;
+                    return Err(e.into());
+                }
+            }
+        } else {
+            Vec::new()
+        };
         if let Err(e) = Self::delete(&mut tx, id).await {
             error!(error = ?e, "could not delete existing events");
             tx.rollback().await?;
@@ -129,9 +205,18 @@ impl Event {
                 "events could not be inserted because",
             );
         }
+        if let Err(e) = calendar_changes::store(&mut tx, id, &changes).await {
+            error!(error = ?e, "could not store the changes to the events");
+            tx.rollback().await?;
+            return Err(e.into());
+        }
         tx.commit().await?;
-        debug!(?id, "finished inserting into the db");
-        Ok(())
+        debug!(
+            ?id,
+            changes = changes.len(),
+            "finished inserting into the db"
+        );
+        Ok(changes.len())
     }
     #[tracing::instrument(skip(tx))]
     async fn delete(tx: &mut Transaction<'_, Postgres>, id: &str) -> Result<(), sqlx::Error> {
@@ -163,14 +248,7 @@ impl Event {
         scrape_at: &DateTime<Utc>,
     ) -> Result<PgQueryResult, sqlx::Error> {
         sqlx::query!(
-            "UPDATE en SET last_calendar_scrape_at = $1 WHERE key=$2",
-            scrape_at,
-            id
-        )
-        .execute(pool)
-        .await?;
-        sqlx::query!(
-            "UPDATE de SET last_calendar_scrape_at = $1 WHERE key=$2",
+            "UPDATE locations SET last_calendar_scrape_at = $1 WHERE key=$2",
             scrape_at,
             id
         )
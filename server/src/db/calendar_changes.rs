@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::db::calendar::Event;
+
+/// How long a removal can later turn out to be a move to another room.
+///
+/// Rooms are scraped independently, so the old room may be scraped (and the event be missing)
+/// before the new room is scraped.
+const MOVE_RECONCILIATION: TimeDelta = TimeDelta::days(7);
+/// How long changes are kept, much longer than anyone follows a feed
+pub const CHANGES_RETENTION: TimeDelta = TimeDelta::days(90);
+
+/// Where and when an event takes place
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub room_code: String,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+}
+
+impl From<&Event> for Placement {
+    fn from(event: &Event) -> Self {
+        Self {
+            room_code: event.room_code.clone(),
+            start_at: event.start_at,
+            end_at: event.end_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Rescheduled,
+    Moved,
+}
+
+impl ChangeKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Rescheduled => "rescheduled",
+            Self::Moved => "moved",
+        }
+    }
+}
+
+/// A difference between two scrapes of a room, which is not yet stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedChange {
+    pub event_id: i32,
+    pub title_de: String,
+    pub title_en: String,
+    pub entry_type: String,
+    /// `None` if the event was added
+    pub previous: Option<Placement>,
+    /// `None` if the event was removed
+    pub current: Option<Placement>,
+}
+
+impl DetectedChange {
+    #[must_use]
+    pub fn kind(&self) -> ChangeKind {
+        match (&self.previous, &self.current) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            (Some(previous), Some(current)) if previous.room_code != current.room_code => {
+                ChangeKind::Moved
+            }
+            (Some(_), Some(_)) => ChangeKind::Rescheduled,
+        }
+    }
+    fn new(event: &Event, previous: Option<Placement>, current: Option<Placement>) -> Self {
+        Self {
+            event_id: event.id,
+            title_de: event.title_de.clone(),
+            title_en: event.title_en.clone(),
+            entry_type: event.entry_type.clone(),
+            previous,
+            current,
+        }
+    }
+}
+
+/// Compares the `previous` and `current` events of a room.
+///
+/// Events new to the room which are known `elsewhere` (by their id) were moved from there.
+/// Changes to events which had already ended at `now` are ignored.
+#[must_use]
+pub fn diff(
+    previous: &[Event],
+    current: &[Event],
+    elsewhere: &HashMap<i32, Placement>,
+    now: DateTime<Utc>,
+) -> Vec<DetectedChange> {
+    let previous_by_id = previous
+        .iter()
+        .map(|event| (event.id, event))
+        .collect::<HashMap<_, _>>();
+    let mut changes = Vec::new();
+    for event in current {
+        let previous = match previous_by_id.get(&event.id) {
+            Some(previous) => {
+                if previous.start_at == event.start_at && previous.end_at == event.end_at {
+                    continue;
+                }
+                Some(Placement::from(*previous))
+            }
+            None => elsewhere.get(&event.id).cloned(),
+        };
+        let has_ended = event.end_at <= now && previous.as_ref().is_none_or(|p| p.end_at <= now);
+        if !has_ended {
+            changes.push(DetectedChange::new(
+                event,
+                previous,
+                Some(Placement::from(event)),
+            ));
+        }
+    }
+    let current_ids = current.iter().map(|event| event.id).collect::<Vec<_>>();
+    for event in previous {
+        if event.end_at > now && !current_ids.contains(&event.id) {
+            changes.push(DetectedChange::new(
+                event,
+                Some(Placement::from(event)),
+                None,
+            ));
+        }
+    }
+    changes.sort_by_key(|change| change.event_id);
+    changes
+}
+
+/// Compares the stored events of `room` with the newly scraped `current` ones.
+///
+/// Has to run before the events of the room are replaced.
+#[tracing::instrument(skip(tx, current), fields(current = current.len()))]
+pub async fn detect(
+    tx: &mut Transaction<'_, Postgres>,
+    room: &str,
+    current: &[Event],
+) -> sqlx::Result<Vec<DetectedChange>> {
+    let previous = sqlx::query_as!(
+        Event,
+        r#"SELECT id,room_code,start_at,end_at,title_de,title_en,stp_type,entry_type,detailed_entry_type
+        FROM calendar
+        WHERE room_code = $1"#,
+        room
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    let new_ids = current
+        .iter()
+        .map(|event| event.id)
+        .filter(|id| !previous.iter().any(|event| event.id == *id))
+        .collect::<Vec<_>>();
+    let now = Utc::now();
+    let elsewhere = fetch_elsewhere(tx, room, &new_ids, now - MOVE_RECONCILIATION).await?;
+    Ok(diff(&previous, current, &elsewhere, now))
+}
+
+/// Where the events `ids` were before, if not in `room`.
+///
+/// Either they are still stored in another room (not yet scraped again),
+/// or they were recently removed from another room.
+async fn fetch_elsewhere(
+    tx: &mut Transaction<'_, Postgres>,
+    room: &str,
+    ids: &[i32],
+    removed_since: DateTime<Utc>,
+) -> sqlx::Result<HashMap<i32, Placement>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let removed = sqlx::query!(
+        r#"SELECT DISTINCT ON (event_id) event_id,
+               previous_room_code AS "room_code!",
+               previous_start_at  AS "start_at!",
+               previous_end_at    AS "end_at!"
+        FROM calendar_changes
+        WHERE kind = 'removed' AND event_id = ANY($1::int[]) AND previous_room_code <> $2 AND detected_at >= $3
+        ORDER BY event_id, detected_at DESC"#,
+        ids,
+        room,
+        removed_since,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    let stored = sqlx::query!(
+        r#"SELECT id, room_code, start_at, end_at
+        FROM calendar
+        WHERE id = ANY($1::int[]) AND room_code <> $2"#,
+        ids,
+        room,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    let mut elsewhere = HashMap::with_capacity(removed.len() + stored.len());
+    for r in removed {
+        let placement = Placement {
+            room_code: r.room_code,
+            start_at: r.start_at,
+            end_at: r.end_at,
+        };
+        elsewhere.insert(r.event_id, placement);
+    }
+    // still being stored is more recent than a removal
+    for s in stored {
+        let placement = Placement {
+            room_code: s.room_code,
+            start_at: s.start_at,
+            end_at: s.end_at,
+        };
+        elsewhere.insert(s.id, placement);
+    }
+    Ok(elsewhere)
+}
+
+/// Stores the `changes` of `room`.
+///
+/// Removals which turned out to be moves are replaced by the move.
+/// If the new room was scraped first, the move is already known and the removal is skipped.
+#[tracing::instrument(skip(tx, changes), fields(changes = changes.len()))]
+pub async fn store(
+    tx: &mut Transaction<'_, Postgres>,
+    room: &str,
+    changes: &[DetectedChange],
+) -> sqlx::Result<()> {
+    let removed = changes
+        .iter()
+        .filter(|change| change.kind() == ChangeKind::Removed)
+        .map(|change| change.event_id)
+        .collect::<Vec<_>>();
+    let moved_away = fetch_moved_away(tx, room, &removed).await?;
+    for change in changes {
+        if change.kind() == ChangeKind::Removed && moved_away.contains(&change.event_id) {
+            continue;
+        }
+        let previous = change.previous.as_ref();
+        let current = change.current.as_ref();
+        sqlx::query!(
+            r#"INSERT INTO calendar_changes (event_id,kind,title_de,title_en,entry_type,previous_room_code,previous_start_at,previous_end_at,room_code,start_at,end_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"#,
+            change.event_id,
+            change.kind().as_str(),
+            change.title_de,
+            change.title_en,
+            change.entry_type,
+            previous.map(|p| p.room_code.as_str()),
+            previous.map(|p| p.start_at),
+            previous.map(|p| p.end_at),
+            current.map(|c| c.room_code.as_str()),
+            current.map(|c| c.start_at),
+            current.map(|c| c.end_at),
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    let moved = changes
+        .iter()
+        .filter(|change| change.kind() == ChangeKind::Moved)
+        .map(|change| change.event_id)
+        .collect::<Vec<_>>();
+    if !moved.is_empty() {
+        sqlx::query!(
+            r#"DELETE FROM calendar_changes
+            WHERE kind = 'removed' AND event_id = ANY($1::int[]) AND detected_at >= $2"#,
+            &moved,
+            Utc::now() - MOVE_RECONCILIATION,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Which of the events `ids` are already known to have moved from `room` to another room.
+///
+/// Either they are stored in another room by now, or their move was recorded recently.
+async fn fetch_moved_away(
+    tx: &mut Transaction<'_, Postgres>,
+    room: &str,
+    ids: &[i32],
+) -> sqlx::Result<Vec<i32>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    sqlx::query_scalar!(
+        r#"SELECT id AS "id!"
+        FROM calendar
+        WHERE id = ANY($1::int[]) AND room_code <> $2
+        UNION
+        SELECT event_id
+        FROM calendar_changes
+        WHERE kind = 'moved' AND event_id = ANY($1::int[]) AND previous_room_code = $2 AND detected_at >= $3"#,
+        ids,
+        room,
+        Utc::now() - MOVE_RECONCILIATION,
+    )
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// Deletes the changes detected before `before` and returns how many were deleted
+#[tracing::instrument(skip(pool))]
+pub async fn delete_detected_before(pool: &PgPool, before: DateTime<Utc>) -> sqlx::Result<u64> {
+    let result = sqlx::query!(
+        "DELETE FROM calendar_changes WHERE detected_at < $1",
+        before
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// A stored change of the calendar
+#[derive(Debug, Clone)]
+pub struct CalendarChange {
+    pub id: i64,
+    pub event_id: i32,
+    pub kind: String,
+    pub detected_at: DateTime<Utc>,
+    pub title_de: String,
+    pub title_en: String,
+    pub entry_type: String,
+    pub previous_room_code: Option<String>,
+    pub previous_start_at: Option<DateTime<Utc>>,
+    pub previous_end_at: Option<DateTime<Utc>>,
+    pub room_code: Option<String>,
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+}
+
+impl CalendarChange {
+    /// The newest (at most `limit`) changes detected at or after `since`.
+    ///
+    /// If `rooms` are given, only changes from or to one of them.
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_since(
+        pool: &PgPool,
+        rooms: Option<&[String]>,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT id,event_id,kind,detected_at,title_de,title_en,entry_type,previous_room_code,previous_start_at,previous_end_at,room_code,start_at,end_at
+            FROM calendar_changes
+            WHERE detected_at >= $1
+              AND ($2::text[] IS NULL OR previous_room_code = ANY($2) OR room_code = ANY($2))
+            ORDER BY detected_at DESC, id DESC
+            LIMIT $3"#,
+            since,
+            rooms,
+            limit,
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2039, 4, day, hour, 0, 0).unwrap()
+    }
+
+    fn event(id: i32, room_code: &str, start_at: DateTime<Utc>) -> Event {
+        Event {
+            id,
+            room_code: room_code.to_string(),
+            start_at,
+            end_at: start_at + TimeDelta::hours(2),
+            title_de: format!("Vorlesung {id}"),
+            title_en: format!("Lecture {id}"),
+            stp_type: None,
+            entry_type: "lecture".to_string(),
+            detailed_entry_type: "Abhaltung".to_string(),
+        }
+    }
+
+    #[test]
+    fn changes_are_detected() {
+        let now = at(15, 12);
+        let previous = [
+            event(1, "hs1", at(16, 8)),
+            event(2, "hs1", at(16, 10)),
+            event(3, "hs1", at(17, 8)),
+            // already over, dropping out of the scraped window is not a cancellation
+            event(4, "hs1", at(1, 8)),
+        ];
+        let current = [
+            event(1, "hs1", at(16, 8)),
+            event(2, "hs1", at(16, 14)),
+            event(5, "hs1", at(18, 8)),
+            event(6, "hs1", at(18, 10)),
+        ];
+        let elsewhere = HashMap::from([(6, Placement::from(&event(6, "hs2", at(18, 10))))]);
+        let changes = diff(&previous, &current, &elsewhere, now);
+        let kinds = changes
+            .iter()
+            .map(|change| (change.event_id, change.kind()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (2, ChangeKind::Rescheduled),
+                (3, ChangeKind::Removed),
+                (5, ChangeKind::Added),
+                (6, ChangeKind::Moved),
+            ]
+        );
+        let moved = changes.last().unwrap();
+        assert_eq!(moved.previous.as_ref().unwrap().room_code, "hs2");
+        assert_eq!(moved.current.as_ref().unwrap().room_code, "hs1");
+    }
+
+    #[test]
+    fn unchanged_calendars_have_no_changes() {
+        let events = [event(1, "hs1", at(16, 8)), event(2, "hs1", at(16, 10))];
+        assert_eq!(diff(&events, &events, &HashMap::new(), at(15, 12)), []);
+    }
+}
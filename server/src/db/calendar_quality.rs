@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Something about the calendar data which is likely wrong
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityIssue {
+    /// The building of the room, or the part of the room code before the first `.` if the room is unknown
+    pub building: String,
+    pub room_code: String,
+    /// `overlap`, `invalid_duration`, `unknown_room`, `stale` or `never_scraped`
+    pub kind: String,
+    /// The affected event, `None` for `stale` and `never_scraped` rooms
+    pub event_id: Option<i32>,
+    /// The event overlapping with `event_id`, only for `overlap`s
+    pub other_event_id: Option<i32>,
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub title: Option<String>,
+    /// Last successful scrape, only for `stale` rooms
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Result of the last scrape, only for `stale` rooms
+    pub last_result: Option<String>,
+}
+
+impl QualityIssue {
+    /// All current issues, ordered by building and room.
+    ///
+    /// Only events which have not yet ended are checked.
+    /// Rooms with a calendar are `stale` if they were not scraped successfully since `stale_since`,
+    /// or `never_scraped` if the scraper has not tried them yet (e.g. right after they were added).
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_all(pool: &PgPool, stale_since: DateTime<Utc>) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+        WITH buildings AS (SELECT DISTINCT ON (p.key) p.key, p.id AS building
+                           FROM parents p
+                           JOIN location_data b ON b.key = p.id AND b.lang = 'de'
+                           WHERE b.type IN ('building', 'joined_building')
+                           ORDER BY p.key, b.type <> 'building', p.id),
+             issues AS (SELECT a.room_code, 'overlap' AS kind, a.id AS event_id, b.id AS other_event_id,
+                               a.start_at, a.end_at, a.title_de AS title,
+                               NULL::timestamptz AS last_success_at, NULL::text AS last_result
+                        FROM calendar a
+                        JOIN calendar b ON b.room_code = a.room_code AND b.id > a.id
+                            AND b.start_at < a.end_at AND a.start_at < b.end_at
+                        WHERE a.end_at > NOW() AND b.end_at > NOW()
+                        UNION ALL
+                        SELECT room_code, 'invalid_duration', id, NULL, start_at, end_at, title_de, NULL, NULL
+                        FROM calendar
+                        WHERE end_at <= start_at AND start_at > NOW()
+                        UNION ALL
+                        SELECT c.room_code, 'unknown_room', c.id, NULL, c.start_at, c.end_at, c.title_de, NULL, NULL
+                        FROM calendar c
+                        WHERE c.end_at > NOW() AND NOT EXISTS (SELECT 1 FROM locations l WHERE l.key = c.room_code)
+                        UNION ALL
+                        SELECT d.key, CASE WHEN s.key IS NULL THEN 'never_scraped' ELSE 'stale' END,
+                               NULL, NULL, NULL, NULL, NULL, s.last_success_at, s.last_result
+                        FROM location_data d
+                        LEFT JOIN calendar_scrape_state s ON s.key = d.key
+                        WHERE d.lang = 'de' AND d.calendar_url IS NOT NULL
+                          AND (s.key IS NULL OR s.last_success_at IS NULL OR s.last_success_at < $1))
+        SELECT COALESCE(bl.building, SPLIT_PART(i.room_code, '.', 1)) AS "building!",
+               i.room_code                                             AS "room_code!",
+               i.kind                                                  AS "kind!",
+               i.event_id,
+               i.other_event_id,
+               i.start_at,
+               i.end_at,
+               i.title,
+               i.last_success_at,
+               i.last_result
+        FROM issues i
+        LEFT JOIN buildings bl ON bl.key = i.room_code
+        ORDER BY 1, 2, 3, i.start_at, i.event_id, i.other_event_id"#,
+            stale_since,
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
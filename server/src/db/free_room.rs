@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// A room with a calendar, which has no booking in the requested time span
+#[derive(Debug, Clone)]
+pub struct FreeRoom {
+    pub key: String,
+    pub name: String,
+    pub usage: Option<String>,
+    pub seats: Option<i32>,
+    /// Start of the next booking after the requested time span, if any is known
+    pub free_until: Option<DateTime<Utc>>,
+    /// last time the calendar was scraped for this room, `None` if it was never scraped
+    pub last_calendar_scrape_at: Option<DateTime<Utc>>,
+}
+
+/// Which rooms should be considered
+#[derive(Debug, Clone)]
+pub struct FreeRoomFilter<'a> {
+    /// key of the location the rooms have to be in
+    pub within: &'a str,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// usage name in any language, compared case-insensitively
+    pub usage: Option<&'a str>,
+    pub min_seats: Option<i32>,
+}
+
+impl FreeRoom {
+    /// Rooms below (or equal to) `filter.within` whose calendar has no entry overlapping `[from, to)`.
+    ///
+    /// Sorted by how long they stay free, rooms without any further booking first.
+    /// Names and usages are in the first available of `languages`, the fallback chain.
+    /// Returns `None` if `filter.within` does not exist.
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_all(
+        pool: &PgPool,
+        filter: &FreeRoomFilter<'_>,
+        languages: &[String],
+    ) -> sqlx::Result<Option<Vec<Self>>> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM locations WHERE key = $1) AS "exists!""#,
+            filter.within
+        )
+        .fetch_one(pool)
+        .await?;
+        if !exists {
+            return Ok(None);
+        }
+        let rooms = sqlx::query_as!(
+            Self,
+            r#"
+        SELECT l.key,
+               d.name                                                                   AS "name!",
+               d.data -> 'usage' ->> 'name'                                             AS usage,
+               (d.data -> 'props' -> 'stats' ->> 'n_seats')::integer                    AS seats,
+               (SELECT MIN(c.start_at) FROM calendar c WHERE c.room_code = l.key AND c.start_at >= $3) AS free_until,
+               l.last_calendar_scrape_at
+        FROM locations l
+        CROSS JOIN LATERAL (SELECT d.name, d.data, d.type, d.calendar_url
+                            FROM location_data d
+                            WHERE d.key = l.key AND d.lang = ANY($6::text[])
+                            ORDER BY array_position($6::text[], d.lang)
+                            LIMIT 1) d
+        WHERE d.type = 'room'
+          AND d.calendar_url IS NOT NULL
+          AND (l.key = $1 OR l.key IN (SELECT p.key FROM parents p WHERE p.id = $1))
+          AND ($4::text IS NULL
+               OR EXISTS (SELECT 1 FROM location_data u WHERE u.key = l.key AND LOWER(u.data -> 'usage' ->> 'name') = LOWER($4)))
+          AND ($5::integer IS NULL OR (d.data -> 'props' -> 'stats' ->> 'n_seats')::integer >= $5)
+          AND NOT EXISTS (SELECT 1 FROM calendar c WHERE c.room_code = l.key AND c.start_at < $3 AND c.end_at > $2)
+        ORDER BY free_until DESC NULLS FIRST, l.key"#,
+            filter.within,
+            filter.from,
+            filter.to,
+            filter.usage,
+            filter.min_seats,
+            languages,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(Some(rooms))
+    }
+}
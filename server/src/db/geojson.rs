@@ -0,0 +1,97 @@
+use sqlx::PgPool;
+use tracing::debug;
+
+/// Selects an entry and (if `$2`) all entries which list it as one of their parents,
+/// each in the first available language of the fallback chain `$3`.
+/// The entry itself comes first.
+///
+/// `{GEOMETRY}` is substituted with one of [`ROOM_POLYGON`] or [`NO_POLYGON`].
+const SUBTREE_TEMPLATE: &str = r"
+WITH subtree AS (
+    SELECT key FROM locations WHERE key = $1
+    UNION
+    SELECT key FROM parents WHERE $2 AND id = $1
+)
+SELECT l.key,
+       l.name,
+       l.type,
+       l.data -> 'usage' ->> 'name'                    AS usage,
+       CASE WHEN jsonb_typeof(l.data -> 'props' -> 'floors') = 'array' THEN
+           CASE WHEN jsonb_array_length(l.data -> 'props' -> 'floors') = 1
+                THEN l.data -> 'props' -> 'floors' -> 0 ->> 'floor' END
+       END                                              AS level,
+       l.lat,
+       l.lon,
+       {GEOMETRY}                                       AS polygon
+FROM subtree s
+CROSS JOIN LATERAL (
+    SELECT d.key, d.name, d.type, d.data, d.lat, d.lon
+    FROM location_data d
+    WHERE d.key = s.key AND d.lang = ANY($3::text[])
+    ORDER BY array_position($3::text[], d.lang)
+    LIMIT 1
+) l
+ORDER BY l.key <> $1, l.key
+";
+
+/// The indoor polygon tagged with `ref:tum`, reprojected from web mercator to WGS84.
+///
+/// If a `ref:tum` is mapped to more than one polygon, the larger one wins, as in the coordinate override.
+const ROOM_POLYGON: &str = r"(
+    SELECT ST_AsGeoJSON(ST_Transform(ST_CurveToLine(r.geom), 4326))::jsonb
+    FROM rooms r
+    WHERE r.ref_tum = l.key AND NOT ST_IsEmpty(r.geom)
+    ORDER BY ST_Area(r.geom) DESC
+    LIMIT 1
+)";
+/// Used when the `rooms` table is absent.
+const NO_POLYGON: &str = "NULL::jsonb";
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct LocationFeature {
+    pub key: String,
+    pub name: String,
+    pub r#type: String,
+    pub usage: Option<String>,
+    pub level: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    /// `GeoJSON` geometry of the indoor polygon, if the room is mapped
+    pub polygon: Option<serde_json::Value>,
+}
+
+impl LocationFeature {
+    /// Fetches the entry `key` and, if `include_descendants`, everything below it.
+    ///
+    /// `rooms` is owned by osm2pgsql and absent in migration-only setups (local dev, tests), so
+    /// it is not part of the schema the `sqlx::query!` macro verifies against: we guard on its
+    /// existence and use runtime queries.
+    ///
+    /// `languages` is the fallback chain, most preferred first.
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_subtree(
+        pool: &PgPool,
+        key: &str,
+        include_descendants: bool,
+        languages: &[String],
+    ) -> sqlx::Result<Vec<Self>> {
+        let rooms_table: Option<String> =
+            sqlx::query_scalar("SELECT to_regclass('public.rooms')::text")
+                .fetch_one(pool)
+                .await?;
+        let geometry = if rooms_table.is_some() {
+            ROOM_POLYGON
+        } else {
+            debug!("rooms table absent (osm2pgsql not loaded); exporting points only");
+            NO_POLYGON
+        };
+        // `geometry` is a hard-coded literal, never user input, so the interpolation is safe.
+        let sql = sqlx::AssertSqlSafe(SUBTREE_TEMPLATE.replace("{GEOMETRY}", geometry));
+        sqlx::query_as::<_, Self>(sql)
+            .bind(key)
+            .bind(include_descendants)
+            .bind(languages)
+            .fetch_all(pool)
+            .await
+    }
+}
@@ -0,0 +1,141 @@
+use sqlx::PgPool;
+use tracing::debug;
+
+/// The levels of the indoor rooms within the footprint of `$1`.
+///
+/// The footprint is the convex hull of all rooms tagged with the `ref:tum` of `$1` or one of its
+/// descendants, so corridors and staircases without a `ref:tum` are included as well.
+/// Rooms spanning several levels (e.g. staircases) are counted on each of them.
+const LEVELS: &str = r"
+WITH subtree AS (
+    SELECT key FROM locations WHERE key = $1
+    UNION
+    SELECT key FROM parents WHERE id = $1
+),
+footprint AS (
+    SELECT ST_ConvexHull(ST_Collect(ST_CurveToLine(r.geom))) AS geom
+    FROM rooms r
+    JOIN subtree s ON r.ref_tum = s.key
+),
+building_rooms AS (
+    SELECT r.indoor, r.level_min, r.level_max, r.geom
+    FROM rooms r, footprint f
+    WHERE r.geom && f.geom AND ST_Intersects(ST_PointOnSurface(ST_CurveToLine(r.geom)), f.geom)
+),
+levels AS (
+    SELECT level_min AS level FROM building_rooms
+    UNION
+    SELECT level_max FROM building_rooms
+)
+SELECT level,
+       room_count,
+       ST_XMin(bbox) AS min_lon,
+       ST_YMin(bbox) AS min_lat,
+       ST_XMax(bbox) AS max_lon,
+       ST_YMax(bbox) AS max_lat
+FROM (
+    SELECT l.level,
+           COUNT(*) FILTER (WHERE r.indoor = 'room')                            AS room_count,
+           ST_Transform(ST_SetSRID(ST_Extent(r.geom)::geometry, 3857), 4326) AS bbox
+    FROM levels l
+    JOIN building_rooms r ON r.level_min <= l.level AND r.level_max >= l.level
+    GROUP BY l.level
+) per_level
+ORDER BY level
+";
+
+/// A level of a building, as mapped in OpenStreetMap
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct IndoorLevel {
+    /// `level=*` in OpenStreetMap, `0` being the ground floor
+    pub level: f32,
+    /// Number of rooms (`indoor=room`) on this level
+    pub room_count: i64,
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl IndoorLevel {
+    /// The levels mapped within the footprint of the building `key`, lowest first.
+    ///
+    /// `rooms` is owned by osm2pgsql and absent in migration-only setups, so it is guarded on and
+    /// queried at runtime (see [`super::geojson::LocationFeature`]).
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_all(pool: &PgPool, key: &str) -> sqlx::Result<Vec<Self>> {
+        let rooms_table: Option<String> =
+            sqlx::query_scalar("SELECT to_regclass('public.rooms')::text")
+                .fetch_one(pool)
+                .await?;
+        if rooms_table.is_none() {
+            debug!("rooms table absent (osm2pgsql not loaded); no levels are known");
+            return Ok(Vec::new());
+        }
+        sqlx::query_as::<_, Self>(LEVELS)
+            .bind(key)
+            .fetch_all(pool)
+            .await
+    }
+}
+
+/// One of the functions rendering indoor vector tiles, which Martin serves as well
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MvtFunction {
+    /// Name of the function, interpolated into the query, so it must be a trusted, hard-coded name
+    pub name: &'static str,
+    /// Tables the function reads from, all owned by osm2pgsql
+    pub tables: &'static [&'static str],
+    /// Whether the function takes `query_params` with the `level` to render
+    pub has_levels: bool,
+}
+
+impl MvtFunction {
+    /// Renders the tile `z/x/y`, `None` if the tables are absent (osm2pgsql not loaded).
+    ///
+    /// `level` defaults to the ground floor, as in Martin.
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_tile(
+        self,
+        pool: &PgPool,
+        (z, x, y): (i32, i32, i32),
+        level: Option<f32>,
+    ) -> sqlx::Result<Option<Vec<u8>>> {
+        let tables_exist: bool = sqlx::query_scalar(
+            "SELECT COALESCE(bool_and(to_regclass('public.' || t) IS NOT NULL), TRUE) FROM unnest($1::text[]) t",
+        )
+        .bind(self.tables)
+        .fetch_one(pool)
+        .await?;
+        if !tables_exist {
+            debug!(
+                function = self.name,
+                "indoor tables absent (osm2pgsql not loaded); no tiles to render"
+            );
+            return Ok(None);
+        }
+        // `name` is a hard-coded literal, never user input, so the interpolation is safe.
+        let tile: Option<Vec<u8>> = if self.has_levels {
+            let sql = format!(
+                "SELECT {name}($1, $2, $3, json_build_object('level', $4::real))",
+                name = self.name
+            );
+            sqlx::query_scalar(sqlx::AssertSqlSafe(sql))
+                .bind(z)
+                .bind(x)
+                .bind(y)
+                .bind(level)
+                .fetch_one(pool)
+                .await?
+        } else {
+            let sql = format!("SELECT {name}($1, $2, $3)", name = self.name);
+            sqlx::query_scalar(sqlx::AssertSqlSafe(sql))
+                .bind(z)
+                .bind(x)
+                .bind(y)
+                .fetch_one(pool)
+                .await?
+        };
+        Ok(Some(tile.unwrap_or_default()))
+    }
+}
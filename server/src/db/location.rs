@@ -19,29 +19,26 @@ pub struct Location {
     pub operator_id: Option<i32>,
 }
 impl Location {
+    /// `languages` is the fallback chain, most preferred first
     #[tracing::instrument(skip(pool))]
     pub async fn fetch_optional(
         pool: &PgPool,
         id: &str,
-        should_use_english: bool,
+        languages: &[String],
     ) -> sqlx::Result<Option<Self>> {
-        if should_use_english {
-            sqlx::query_as!(
-                Self,
-                r#"SELECT last_calendar_scrape_at,lat,lon,name,type_common_name,type,calendar_url,tumonline_room_nr,coordinate_accuracy,coordinate_source,comment,usage_id,operator_id
-                FROM en
-                WHERE key=$1"#,
-                id)
-                .fetch_optional(pool).await
-        } else {
-            sqlx::query_as!(
-                Self,
-                r#"SELECT last_calendar_scrape_at,lat,lon,name,type_common_name,type,calendar_url,tumonline_room_nr,coordinate_accuracy,coordinate_source,comment,usage_id,operator_id
-                FROM de
-                WHERE key=$1"#,
-                id)
-                .fetch_optional(pool).await
-        }
+        sqlx::query_as!(
+            Self,
+            r#"SELECT l.last_calendar_scrape_at,d.lat,d.lon,d.name,d.type_common_name,d.type,d.calendar_url,d.tumonline_room_nr,d.coordinate_accuracy,d.coordinate_source,d.comment,d.usage_id,d.operator_id
+            FROM location_data d
+            JOIN locations l ON l.key = d.key
+            WHERE d.key = $1 AND d.lang = ANY($2::text[])
+            ORDER BY array_position($2::text[], d.lang)
+            LIMIT 1"#,
+            id,
+            languages
+        )
+        .fetch_optional(pool)
+        .await
     }
 }
 
@@ -95,3 +92,323 @@ impl LocationKeyAlias {
         }
     }
 }
+
+/// What a location's details depend on, without loading the full `data`.
+///
+/// Used to answer conditional requests cheaply.
+#[derive(Debug, Clone)]
+pub struct LocationRevision {
+    /// Hash of the location's data, changing whenever the data changes
+    pub hash: Option<i64>,
+    /// The time-independent part of the opening hours, which are evaluated for the current time
+    pub opening_hours: Option<serde_json::Value>,
+    pub lat: f64,
+    pub lon: f64,
+}
+impl LocationRevision {
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_optional(pool: &PgPool, key: &str) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT l.hash, d.data -> 'opening_hours' AS opening_hours, d.lat, d.lon
+            FROM locations l
+            JOIN location_data d ON d.key = l.key AND d.lang = 'de'
+            WHERE l.key = $1"#,
+            key
+        )
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// An entry worth pre-rendering previews for
+#[derive(Debug, Clone)]
+pub struct TopRankedLocation {
+    pub key: String,
+    pub hash: Option<i64>,
+}
+impl TopRankedLocation {
+    /// The `limit` entries with the highest combined rank, best first
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_all(pool: &PgPool, limit: i64) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT l.key, l.hash
+            FROM locations l
+            JOIN ranking_factors r ON r.id = l.key
+            ORDER BY r.rank_combined DESC NULLS LAST, l.key
+            LIMIT $1"#,
+            limit
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+/// A key which was removed from the data, kept so that old links can be answered properly.
+#[derive(Debug, Clone)]
+pub struct Tombstone {
+    pub key: String,
+    pub name: String,
+    pub removed_at: DateTime<Utc>,
+    /// Where this entry lives on, e.g. after a room was renumbered.
+    pub successor: Option<String>,
+}
+impl Tombstone {
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_optional(pool: &PgPool, id: &str) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+        SELECT t.key, t.name, t.removed_at, s.successor AS "successor?"
+        FROM tombstones t
+        LEFT JOIN key_successors s ON s.key = t.key
+        WHERE t.key = $1 OR t.visible_id = $1
+        ORDER BY t.removed_at DESC
+        LIMIT 1"#,
+            id
+        )
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// The building a location is in
+#[derive(Debug, Clone)]
+pub struct ParentBuilding {
+    pub name: String,
+}
+impl ParentBuilding {
+    /// `languages` is the fallback chain, most preferred first
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_optional(
+        pool: &PgPool,
+        key: &str,
+        languages: &[String],
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+        SELECT b.name
+        FROM parents p
+        JOIN location_data b ON b.key = p.id
+        WHERE p.key = $1 AND b.type IN ('building', 'joined_building') AND b.lang = ANY($2::text[])
+        ORDER BY b.type <> 'building', array_position($2::text[], b.lang)
+        LIMIT 1"#,
+            key,
+            languages
+        )
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// Where a location is, as shown next to its name in previews
+#[derive(Debug, Clone)]
+pub struct LocationContext {
+    /// Names of the parents, ordered as in a breadcrumb (starting with the root)
+    pub parent_names: Vec<String>,
+    /// Name of the floor, if the location is on exactly one floor
+    pub floor_name: Option<String>,
+    /// Level of this floor, as in the indoor map
+    pub floor_level: Option<f32>,
+}
+impl LocationContext {
+    /// `languages` is the fallback chain, most preferred first
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_optional(
+        pool: &PgPool,
+        key: &str,
+        languages: &[String],
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+        SELECT ARRAY(SELECT jsonb_array_elements_text(d.data -> 'parent_names')) AS "parent_names!",
+               f.floor ->> 'name'         AS floor_name,
+               (f.floor ->> 'id')::real   AS floor_level
+        FROM location_data d
+        CROSS JOIN LATERAL (
+            SELECT CASE WHEN jsonb_typeof(d.data -> 'props' -> 'floors') = 'array' THEN
+                       CASE WHEN jsonb_array_length(d.data -> 'props' -> 'floors') = 1
+                            THEN d.data -> 'props' -> 'floors' -> 0 END
+                   END AS floor) f
+        WHERE d.key = $1 AND d.lang = ANY($2::text[])
+        ORDER BY array_position($2::text[], d.lang)
+        LIMIT 1"#,
+            key,
+            languages
+        )
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// The floors of a location, as listed in its details
+#[derive(Debug, Clone)]
+pub struct LocationFloors {
+    pub r#type: String,
+    /// `FloorResponse`s as stored in the data, lowest floor first
+    pub floors: Option<serde_json::Value>,
+}
+impl LocationFloors {
+    /// `languages` is the fallback chain, most preferred first
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_optional(
+        pool: &PgPool,
+        key: &str,
+        languages: &[String],
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+        SELECT type, data -> 'props' -> 'floors' AS floors
+        FROM location_data
+        WHERE key = $1 AND lang = ANY($2::text[])
+        ORDER BY array_position($2::text[], lang)
+        LIMIT 1"#,
+            key,
+            languages
+        )
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// The details of a location in the first available language of a fallback chain
+#[derive(Debug, Clone)]
+pub struct LocalisedLocation {
+    /// The language `data` is in
+    pub lang: String,
+    pub data: serde_json::Value,
+}
+impl LocalisedLocation {
+    /// `languages` is the fallback chain, most preferred first
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_optional(
+        pool: &PgPool,
+        key: &str,
+        languages: &[String],
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT lang, data
+            FROM location_data
+            WHERE key = $1 AND lang = ANY($2::text[])
+            ORDER BY array_position($2::text[], lang)
+            LIMIT 1"#,
+            key,
+            languages
+        )
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// Where a location is and how to link to it
+#[derive(Debug, Clone)]
+pub struct LocationPin {
+    pub key: String,
+    pub visible_id: String,
+    pub name: String,
+    pub r#type: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+impl LocationPin {
+    /// The pins of the existing `keys`, named in the first available of `languages`
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_all(
+        pool: &PgPool,
+        keys: &[String],
+        languages: &[String],
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+        SELECT DISTINCT ON (d.key)
+               d.key,
+               COALESCE((SELECT a.visible_id FROM aliases a WHERE a.key = d.key ORDER BY a.visible_id LIMIT 1), d.key) AS "visible_id!",
+               d.name,
+               d.type,
+               d.lat,
+               d.lon
+        FROM location_data d
+        WHERE d.key = ANY($1::text[]) AND d.lang = ANY($2::text[])
+        ORDER BY d.key, array_position($2::text[], d.lang)"#,
+            keys,
+            languages
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Path of the details page, like [`LocationKeyAlias::redirect_exact_match`]
+    #[must_use]
+    pub fn details_path(&self) -> String {
+        LocationKeyAlias {
+            key: self.key.clone(),
+            visible_id: self.visible_id.clone(),
+            r#type: self.r#type.clone(),
+        }
+        .redirect_exact_match()
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use super::*;
+    use crate::setup::tests::PostgresTestContainer;
+
+    async fn insert(pool: &PgPool, key: &str, floors: serde_json::Value) {
+        let data = serde_json::json!({
+            "name": key,
+            "type": "room",
+            "type_common_name": "Hörsaal",
+            "coords": { "lat": 48.26, "lon": 11.67 },
+            "parent_names": ["Standorte", "Garching Forschungszentrum"],
+            "props": { "floors": floors },
+        });
+        sqlx::query("INSERT INTO locations(key) VALUES ($1)")
+            .bind(key)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO location_data(key, lang, data) VALUES ($1, 'de', $2), ($1, 'en', $2)",
+        )
+        .bind(key)
+        .bind(&data)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn floor_name_is_read_from_the_props() {
+        let pg = PostgresTestContainer::new().await;
+        let floor = serde_json::json!({ "id": 1, "floor": "1", "name": "1. Obergeschoss" });
+        insert(&pg.pool, "5602.01.001", serde_json::json!([floor])).await;
+        insert(&pg.pool, "5602.01.002", serde_json::json!([floor, floor])).await;
+
+        let single = LocationContext::fetch_optional(&pg.pool, "5602.01.001", &["de".to_string()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(single.floor_name.as_deref(), Some("1. Obergeschoss"));
+        assert_eq!(single.floor_level, Some(1.0));
+        assert_eq!(
+            single.parent_names,
+            ["Standorte", "Garching Forschungszentrum"]
+        );
+        // spanning several floors, there is no single floor to highlight
+        let several = LocationContext::fetch_optional(&pg.pool, "5602.01.002", &["de".to_string()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(several.floor_name, None);
+        assert_eq!(several.floor_level, None);
+    }
+}
@@ -1,3 +1,11 @@
+pub mod basemap;
 pub mod calendar;
+pub mod calendar_changes;
+pub mod calendar_quality;
+pub mod free_room;
+pub mod geojson;
+pub mod indoor;
 pub mod location;
+pub mod occupancy;
 pub mod public_transport;
+pub mod sitemap;
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// A room with a calendar, whose bookings count towards the occupancy of a location
+#[derive(Debug, Clone)]
+pub struct OccupancyRoom {
+    pub key: String,
+    pub name: String,
+    pub seats: Option<i32>,
+    /// last time the calendar was scraped for this room, `None` if it was never scraped
+    pub last_calendar_scrape_at: Option<DateTime<Utc>>,
+}
+
+impl OccupancyRoom {
+    /// Rooms with a calendar below (or equal to) `within`.
+    ///
+    /// Names are in the first available of `languages`, the fallback chain.
+    /// Returns `None` if `within` does not exist.
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_all(
+        pool: &PgPool,
+        within: &str,
+        languages: &[String],
+    ) -> sqlx::Result<Option<Vec<Self>>> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM locations WHERE key = $1) AS "exists!""#,
+            within
+        )
+        .fetch_one(pool)
+        .await?;
+        if !exists {
+            return Ok(None);
+        }
+        let rooms = sqlx::query_as!(
+            Self,
+            r#"
+        SELECT DISTINCT ON (d.key)
+               d.key,
+               d.name,
+               (d.data -> 'props' -> 'stats' ->> 'n_seats')::integer AS seats,
+               l.last_calendar_scrape_at
+        FROM location_data d
+        JOIN locations l ON l.key = d.key
+        WHERE d.type = 'room'
+          AND d.calendar_url IS NOT NULL
+          AND (d.key = $1 OR d.key IN (SELECT p.key FROM parents p WHERE p.id = $1))
+          AND d.lang = ANY($2::text[])
+        ORDER BY d.key, array_position($2::text[], d.lang)"#,
+            within,
+            languages,
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(Some(rooms))
+    }
+}
+
+/// When a room is booked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Booking {
+    pub room_code: String,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+}
+
+impl Booking {
+    /// Bookings of the `rooms` overlapping `[from, to)`.
+    ///
+    /// Blocked times (`entry_type = 'barred'`, e.g. for maintenance) are not bookings.
+    #[tracing::instrument(skip(pool, rooms), fields(rooms = rooms.len()))]
+    pub async fn fetch_all(
+        pool: &PgPool,
+        rooms: &[String],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"SELECT room_code, start_at, end_at
+            FROM calendar
+            WHERE room_code = ANY($1::text[]) AND start_at < $3 AND end_at > $2 AND entry_type <> 'barred'
+            ORDER BY room_code, start_at"#,
+            rooms,
+            from,
+            to,
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
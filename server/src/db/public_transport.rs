@@ -16,7 +16,8 @@ impl Transportation {
             Transportation,
             r#"
 WITH coodinates_for_keys(key, coordinate) as (SELECT key, point(lat, lon)::geometry as coordinate
-                                              from de)
+                                              from location_data
+                                              where lang = 'de')
 
 SELECT t.id,
        t.name,
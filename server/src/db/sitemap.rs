@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::db::location::LocationKeyAlias;
+
+/// A location which should be listed in the sitemap
+#[derive(Debug, Clone)]
+pub struct SitemapEntry {
+    pub alias: LocationKeyAlias,
+    /// When the data of this location last changed
+    pub last_modified: DateTime<Utc>,
+}
+
+/// A part of the sitemap
+#[derive(Debug, Clone)]
+pub struct SitemapShard {
+    pub shard: i64,
+    /// When the data of any location in this shard last changed
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SitemapShard {
+    /// All shards, if every shard contains `shard_size` locations (ordered by key).
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_all(pool: &PgPool, shard_size: i64) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(
+            Self,
+            r#"
+        SELECT shard AS "shard!", MAX(hash_updated_at) AS "last_modified!"
+        FROM (SELECT (ROW_NUMBER() OVER (ORDER BY key) - 1) / $1 AS shard, hash_updated_at FROM locations) s
+        GROUP BY shard
+        ORDER BY shard"#,
+            shard_size
+        )
+        .fetch_all(pool)
+        .await
+    }
+}
+
+impl SitemapEntry {
+    /// The locations in the `shard`-th shard, see [`SitemapShard::fetch_all`]
+    #[tracing::instrument(skip(pool))]
+    pub async fn fetch_shard(
+        pool: &PgPool,
+        shard: i64,
+        shard_size: i64,
+    ) -> sqlx::Result<Vec<Self>> {
+        let records = sqlx::query!(
+            r#"
+        SELECT l.key, COALESCE(a.visible_id, l.key) AS "visible_id!", d.type, l.hash_updated_at
+        FROM locations l
+        JOIN location_data d ON d.key = l.key AND d.lang = 'de'
+        LEFT JOIN LATERAL (SELECT visible_id FROM aliases WHERE key = l.key ORDER BY visible_id LIMIT 1) a ON TRUE
+        ORDER BY l.key
+        LIMIT $2 OFFSET $1 * $2"#,
+            shard,
+            shard_size
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(records
+            .into_iter()
+            .map(|r| Self {
+                alias: LocationKeyAlias {
+                    key: r.key,
+                    visible_id: r.visible_id,
+                    r#type: r.r#type,
+                },
+                last_modified: r.hash_updated_at,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use tokio::fs;
+
+use crate::external::connectum::ConnectumEvent;
+
+/// Where the calendars of the rooms are scraped from
+pub trait CalendarSource: Clone + Send + Sync {
+    /// The events of the room `id`
+    fn list_events(
+        &mut self,
+        id: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<ConnectumEvent>>> + Send;
+}
+
+/// Serves the events of a room from `{dir}/{id}.json`, in the format of the `Connectum` API.
+///
+/// Allows running the scraper without access to `TUMonline`, e.g. for local development or tests.
+/// Rooms without a file have no events.
+/// The files are read on every scrape, so editing them simulates changes to the calendars.
+#[derive(Clone, Debug)]
+pub struct FixtureCalendar {
+    dir: PathBuf,
+}
+
+impl FixtureCalendar {
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl CalendarSource for FixtureCalendar {
+    async fn list_events(&mut self, id: &str) -> anyhow::Result<Vec<ConnectumEvent>> {
+        if id.contains(['/', '\\']) || id.starts_with('.') {
+            anyhow::bail!("{id:?} cannot be a file name");
+        }
+        let path = self.dir.join(format!("{id}.json"));
+        match fs::read(&path).await {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fixtures_are_read_per_room() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("5602.EG.001.json"),
+            r#"[{"id":1,"room_code":"5602.EG.001","start_at":"2039-04-15T08:00:00Z","end_at":"2039-04-15T10:00:00Z","title_de":"Analysis 1","title_en":"Analysis 1","stp_type":null,"entry_type":"lecture","detailed_entry_type":"Abhaltung"}]"#,
+        )
+        .unwrap();
+        let mut source = FixtureCalendar::new(dir.path());
+
+        let events = source.list_events("5602.EG.001").await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(source.list_events("5602.EG.002").await.unwrap().is_empty());
+        assert!(source.list_events("../5602.EG.001").await.is_err());
+    }
+}
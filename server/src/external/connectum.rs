@@ -13,6 +13,8 @@ use serde::Deserialize;
 use tokio::time::sleep;
 use tracing::error;
 
+use crate::external::calendar_source::CalendarSource;
+
 const KEEP_ALIVE: Duration = Duration::from_secs(30);
 
 #[derive(Clone)]
@@ -48,8 +50,8 @@ impl Default for APIRequestor {
         }
     }
 }
-impl APIRequestor {
-    pub async fn list_events(&mut self, id: &str) -> anyhow::Result<Vec<ConnectumEvent>> {
+impl CalendarSource for APIRequestor {
+    async fn list_events(&mut self, id: &str) -> anyhow::Result<Vec<ConnectumEvent>> {
         let token = self.oauth_token.get_possibly_refreshed_token().await;
 
         let url = format!("https://campus.tum.de/tumonline/co/connectum/api/rooms/{id}/calendars");
@@ -6,7 +6,8 @@ use meilisearch_sdk::client::Client;
 use meilisearch_sdk::errors::Error;
 use meilisearch_sdk::indexes::Index;
 use meilisearch_sdk::search::{
-    FederatedMultiSearchResponse, FederationOptions, MergeFacets, SearchQuery, Selectors,
+    FederatedMultiSearchResponse, FederationOptions, MatchingStrategies, MergeFacets, SearchQuery,
+    Selectors,
 };
 use serde::{Deserialize, Serialize};
 
@@ -87,6 +88,45 @@ const LECTURE_FEDERATION_WEIGHT: f32 = 0.5;
 /// lose against equally-strong location matches, not a hard pin below them.
 const EVENT_FEDERATION_WEIGHT: f32 = 0.5;
 
+/// Hits scoring below this are too weak to pin a free-text location to.
+///
+/// Every word of the query has to match already, this additionally rejects matches
+/// which are only found with typos or scattered over unrelated attributes.
+/// Empirical; revisit if correct locations end up `unresolved`.
+const MIN_LOCATION_MATCH_SCORE: f64 = 0.7;
+
+/// The `room_code` of the site, building, room or POI matching `query` well enough, if any.
+///
+/// Unlike [`GeoEntryQuery`], this does not fall back to hits matching only some words,
+/// so that locations like `Online` or `Raum folgt` are not pinned to an arbitrary entry.
+pub async fn fetch_location_match(client: &Client, query: &str) -> Result<Option<String>, Error> {
+    #[derive(Deserialize)]
+    struct LocationHit {
+        room_code: String,
+    }
+
+    let filter = format!(
+        "{FACET_FIELD} IN {:?}",
+        [SITE_FACET, BUILDING_FACET, ROOM_FACET, POI_FACET]
+    );
+    let results = client
+        .index(ENTRIES_INDEX)
+        .search()
+        .with_query(query)
+        .with_filter(&filter)
+        .with_matching_strategy(MatchingStrategies::ALL)
+        .with_ranking_score_threshold(MIN_LOCATION_MATCH_SCORE)
+        .with_attributes_to_retrieve(Selectors::Some(&["room_code"]))
+        .with_limit(1)
+        .execute::<LocationHit>()
+        .await?;
+    Ok(results
+        .hits
+        .into_iter()
+        .next()
+        .map(|hit| hit.result.room_code))
+}
+
 /// The type of a `NavigaTUM` entity surfaced as a search result.
 ///
 /// The closed set of location types the data pipeline exports (`valid_types`
@@ -1,3 +1,4 @@
+pub mod calendar_source;
 pub mod connectum;
 pub mod github;
 pub mod meilisearch;
@@ -1,5 +1,5 @@
-use std::io;
 use std::time::Duration;
+use std::{env, io};
 
 use image::{DynamicImage, load_from_memory};
 use tokio::time::sleep;
@@ -15,8 +15,18 @@ pub struct Camera {
     pub pitch: u32,
 }
 
+/// Base URL of the Martin tileserver rendering the basemap, configured via `MARTIN_URL`.
+///
+/// Setting `MARTIN_URL` to an empty string disables remote rendering, e.g. in offline deployments.
+#[must_use]
+pub fn martin_url() -> Option<String> {
+    let url = env::var("MARTIN_URL").unwrap_or_else(|_| "https://nav.tum.de/martin".to_string());
+    let url = url.trim_end_matches('/');
+    (!url.is_empty()).then(|| url.to_string())
+}
+
 /// Endpoint: `GET /style/{id}/static/{lon},{lat},{zoom}[@{bearing},{pitch}]/{w}x{h}.{ext}`.
-fn static_image_url(camera: Camera, width: u32, height: u32) -> String {
+fn static_image_url(martin_url: &str, camera: Camera, width: u32, height: u32) -> String {
     let Camera {
         lon,
         lat,
@@ -28,18 +38,19 @@ fn static_image_url(camera: Camera, width: u32, height: u32) -> String {
     } else {
         format!("{lon},{lat},{zoom}@0,{pitch}")
     };
-    format!("https://nav.tum.de/martin/style/navigatum-basemap/static/{view}/{width}x{height}.png")
+    format!("{martin_url}/style/navigatum-basemap/static/{view}/{width}x{height}.png")
 }
 
 /// Render a basemap image via Martin's static-image API, retrying transient `5xx`
 /// responses (static rendering has no concurrency support) with exponential backoff.
 #[tracing::instrument]
 pub async fn download_static_map_image(
+    martin_url: &str,
     camera: Camera,
     width: u32,
     height: u32,
 ) -> anyhow::Result<DynamicImage> {
-    let url = static_image_url(camera, width, height);
+    let url = static_image_url(martin_url, camera, width, height);
     for i in 1..5 {
         let response = reqwest::get(&url).await?;
         let status = response.status();
@@ -78,7 +89,7 @@ mod tests {
             pitch: 0,
         };
         assert_eq!(
-            static_image_url(camera, 1200, 505),
+            static_image_url("https://nav.tum.de/martin", camera, 1200, 505),
             "https://nav.tum.de/martin/style/navigatum-basemap/static/11.6688,48.2625,16/1200x505.png"
         );
     }
@@ -92,8 +103,22 @@ mod tests {
             pitch: 20,
         };
         assert_eq!(
-            static_image_url(camera, 1200, 505),
+            static_image_url("https://nav.tum.de/martin", camera, 1200, 505),
             "https://nav.tum.de/martin/style/navigatum-basemap/static/11.6688,48.2625,16@0,20/1200x505.png"
         );
     }
+
+    #[test]
+    fn test_static_image_url_local() {
+        let camera = Camera {
+            lat: 48.262_5,
+            lon: 11.668_8,
+            zoom: 17,
+            pitch: 0,
+        };
+        assert_eq!(
+            static_image_url("http://martin:3001", camera, 600, 300),
+            "http://martin:3001/style/navigatum-basemap/static/11.6688,48.2625,17/600x300.png"
+        );
+    }
 }
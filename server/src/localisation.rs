@@ -1,5 +1,11 @@
+use actix_web::dev::Payload;
+use actix_web::error::ErrorBadRequest;
+use actix_web::http::header::{AcceptLanguage, Header as _, Preference};
+use actix_web::{FromRequest, HttpRequest, web};
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
+use std::future::{Ready, ready};
+use std::slice;
 
 #[derive(Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq, Default, utoipa::ToSchema)]
 #[serde(rename_all = "snake_case")]
@@ -18,12 +24,230 @@ impl Display for LanguageOptions {
     }
 }
 
-#[derive(
-    Deserialize, Serialize, Copy, Clone, Debug, Eq, PartialEq, Default, utoipa::IntoParams,
-)]
+/// A language tag like `de`, `en`, `zh-hans` or `es`, normalised to lowercase.
+///
+/// Unlike [`LanguageOptions`], this is not limited to the languages every entry is available in.
+#[derive(Deserialize, Serialize, Clone, Debug, Eq, PartialEq, Hash, utoipa::ToSchema)]
+#[serde(try_from = "String", into = "String")]
+#[schema(value_type = String, examples("de", "en", "zh-hans", "es"))]
+pub struct Language(String);
+
+impl Language {
+    /// The primary subtag, e.g. `zh` for `zh-hans`
+    #[must_use]
+    pub fn primary(&self) -> &str {
+        self.0.split('-').next().unwrap_or_default()
+    }
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for Language {
+    type Error = String;
+
+    fn try_from(tag: String) -> Result<Self, Self::Error> {
+        let tag = tag.trim().replace('_', "-").to_lowercase();
+        let mut subtags = tag.split('-');
+        let primary_is_valid = subtags.next().is_some_and(|primary| {
+            (2..=3).contains(&primary.len()) && primary.bytes().all(|b| b.is_ascii_alphabetic())
+        });
+        let rest_is_valid = subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        });
+        if primary_is_valid && rest_is_valid && tag.len() <= 35 {
+            Ok(Self(tag))
+        } else {
+            Err(format!("{tag:?} is not a valid language tag"))
+        }
+    }
+}
+impl From<Language> for String {
+    fn from(language: Language) -> Self {
+        language.0
+    }
+}
+impl From<LanguageOptions> for Language {
+    fn from(language: LanguageOptions) -> Self {
+        Self(language.to_string())
+    }
+}
+impl Default for Language {
+    fn default() -> Self {
+        LanguageOptions::default().into()
+    }
+}
+impl Display for Language {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Deserialize, Default, Debug, utoipa::IntoParams)]
+#[serde(default)]
 pub struct LangQueryArgs {
-    /// The language you want your preview to be in. If either this or the query parameter is set to en, this will be delivered.
-    #[serde(default)]
-    #[param(inline)]
-    pub lang: LanguageOptions,
+    /// The language you want the response to be in.
+    ///
+    /// `de` and `en` are always available, other languages (e.g. `zh`, `es`) only for some entries.
+    /// Untranslated content falls back to `en` and then `de`.
+    /// If omitted, the `Accept-Language` header is used, defaulting to `de`.
+    #[param(value_type = Option<String>, example = "en")]
+    lang: Option<Language>,
+}
+
+impl LangQueryArgs {
+    /// The languages to try, most preferred first.
+    ///
+    /// An explicit `lang` wins over the `Accept-Language` header.
+    #[must_use]
+    pub fn negotiate(&self, accept_language: Option<&AcceptLanguage>) -> LanguagePreferences {
+        if let Some(lang) = &self.lang {
+            return LanguagePreferences::new(slice::from_ref(lang));
+        }
+        let preferred = accept_language
+            .map(AcceptLanguage::ranked)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|preference| match preference {
+                Preference::Specific(tag) => Language::try_from(tag.to_string()).ok(),
+                Preference::Any => None,
+            })
+            .collect::<Vec<_>>();
+        LanguagePreferences::new(&preferred)
+    }
+}
+
+/// Fallback chain of languages, e.g. `fr -> en -> de`
+///
+/// As an extractor, it is negotiated from the `lang` query parameter (see [`LangQueryArgs`]) and the
+/// `Accept-Language` header, so handlers document it via `params(LangQueryArgs)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LanguagePreferences {
+    preferred: Language,
+    chain: Vec<Language>,
+}
+
+impl FromRequest for LanguagePreferences {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        // the other query parameters belong to the handler
+        let lang = web::Query::<LangQueryArgs>::from_query(req.query_string())
+            .map_err(|e| ErrorBadRequest(format!("Query deserialize error: {e}")));
+        let accept_language = AcceptLanguage::parse(req).ok();
+        ready(lang.map(|lang| lang.negotiate(accept_language.as_ref())))
+    }
+}
+
+impl Default for LanguagePreferences {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+impl From<Language> for LanguagePreferences {
+    fn from(language: Language) -> Self {
+        Self::new(slice::from_ref(&language))
+    }
+}
+
+impl LanguagePreferences {
+    /// Extends `preferred` by the primary subtags (`zh-hans -> zh`) and then `en` and `de`,
+    /// so that every entry is available in one of the languages.
+    ///
+    /// Without any preference, `de` is preferred.
+    fn new(preferred: &[Language]) -> Self {
+        let default = [Language::default()];
+        let preferred = if preferred.is_empty() {
+            &default
+        } else {
+            preferred
+        };
+        let mut chain: Vec<Language> = Vec::with_capacity(preferred.len() * 2 + 2);
+        let primaries = preferred
+            .iter()
+            .flat_map(|lang| [lang.clone(), Language(lang.primary().to_string())]);
+        let fallbacks = [LanguageOptions::En, LanguageOptions::De].map(Language::from);
+        for lang in primaries.chain(fallbacks) {
+            if !chain.contains(&lang) {
+                chain.push(lang);
+            }
+        }
+        let preferred = preferred.first().cloned().unwrap_or_default();
+        Self { preferred, chain }
+    }
+    /// The most preferred language
+    #[must_use]
+    pub fn preferred(&self) -> &Language {
+        &self.preferred
+    }
+    /// The first of `de`/`en` in the chain, for content which only exists in those two
+    #[must_use]
+    pub fn base(&self) -> LanguageOptions {
+        self.chain
+            .iter()
+            .find_map(|lang| match lang.as_str() {
+                "de" => Some(LanguageOptions::De),
+                "en" => Some(LanguageOptions::En),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+    /// The whole chain as tags, most preferred first
+    #[must_use]
+    pub fn tags(&self) -> Vec<String> {
+        self.chain.iter().map(ToString::to_string).collect()
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use actix_web::http::header::{LanguageTag, QualityItem, q};
+
+    use super::*;
+
+    fn lang(tag: &str) -> Language {
+        Language::try_from(tag.to_string()).unwrap()
+    }
+
+    #[test]
+    fn language_tags_are_normalised_and_validated() {
+        assert_eq!(lang("zh_Hans").as_str(), "zh-hans");
+        assert_eq!(lang("zh-hans").primary(), "zh");
+        assert!(Language::try_from("e".to_string()).is_err());
+        assert!(Language::try_from("en--us".to_string()).is_err());
+        assert!(Language::try_from("../de".to_string()).is_err());
+    }
+
+    #[test]
+    fn fallback_chain_ends_with_en_and_de() {
+        let explicit = LangQueryArgs {
+            lang: Some(lang("fr")),
+        };
+        let chain = explicit.negotiate(None);
+        assert_eq!(chain.tags(), ["fr", "en", "de"]);
+        assert_eq!(chain.base(), LanguageOptions::En);
+
+        let specific = |tag: &str, quality| {
+            QualityItem::new(
+                Preference::Specific(LanguageTag::parse(tag).unwrap()),
+                q(quality),
+            )
+        };
+        let accept = AcceptLanguage(vec![
+            specific("zh-Hans", 0.9),
+            specific("de", 0.8),
+            QualityItem::new(Preference::Any, q(0.1)),
+        ]);
+        let negotiated = LangQueryArgs::default().negotiate(Some(&accept));
+        assert_eq!(negotiated.tags(), ["zh-hans", "zh", "de", "en"]);
+        assert_eq!(negotiated.preferred().as_str(), "zh-hans");
+        assert_eq!(negotiated.base(), LanguageOptions::De);
+
+        let chain = LangQueryArgs::default().negotiate(None);
+        assert_eq!(chain.tags(), ["de", "en"]);
+        assert_eq!(chain.base(), LanguageOptions::De);
+    }
 }
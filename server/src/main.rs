@@ -21,17 +21,21 @@ use utoipa::openapi::OpenApi;
 mod docs;
 mod limited;
 mod localisation;
+mod opening_hours_evaluator;
 mod search_executor;
 mod setup;
+mod xml;
 use utoipa_actix_web::{AppExt as _, scope};
 mod db;
 pub mod external;
 pub mod overlays;
 pub mod refresh;
 pub mod routes;
-use routes::{calendar, feedback, locations, maps, mensa, search};
+use routes::{calendar, feedback, locations, maps, mensa, oembed, search, sitemap, timetable};
 
 const MAX_JSON_PAYLOAD: usize = 1024 * 1024 * 10; // 10 MB
+/// For bodies which are not JSON, e.g. uploaded timetables
+const MAX_PAYLOAD: usize = 1024 * 1024 * 2; // 2 MB
 
 const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
 
@@ -45,12 +49,18 @@ pub struct AppData {
     motis: external::motis::MotisWrapper,
     /// moka cache for search results (size ~= 0.1Mi per entry)
     search_cache: Cache<search::SearchCacheKey, Vec<search_executor::ResultsSection>>,
+    /// rendered previews, in memory and optionally on disk
+    preview_cache: locations::preview_cache::PreviewCache,
+    /// rendered indoor vector tiles, weighted by their size, if they are served
+    tile_cache: Option<Cache<maps::tiles::TileCacheKey, bytes::Bytes>>,
+    /// locations of uploaded timetables and what they resolved to
+    timetable_resolutions: timetable::ResolutionCache,
 }
 
 impl AppData {
     async fn new() -> Self {
-        // max bumped to fit 11 parallel post-load_data loaders (9 derived
-        // tables + transportation + the tumonline_orgs->events chain),
+        // max bumped to fit 12 parallel post-load_data loaders (9 derived
+        // tables + key successors + transportation + the tumonline_orgs->events chain),
         // plus headroom for request handling while setup runs.
         let pool = PgPoolOptions::new()
             .min_connections(2)
@@ -69,6 +79,9 @@ impl From<PgPool> for AppData {
             valhalla: external::valhalla::ValhallaWrapper::default(),
             motis: external::motis::MotisWrapper::default(),
             search_cache: Cache::builder().max_capacity(200).build(),
+            preview_cache: locations::preview_cache::PreviewCache::from_env(),
+            tile_cache: maps::tiles::tile_cache(),
+            timetable_resolutions: timetable::ResolutionCache::default(),
         }
     }
 }
@@ -158,7 +171,8 @@ pub fn setup_logging() {
     meilisearch_initialised,
     initialisation_started,
     repo_pool,
-    calendar_metrics
+    calendar_metrics,
+    preview_cache
 ))]
 async fn run_maintenance_work(
     pool: Pool<Postgres>,
@@ -166,6 +180,7 @@ async fn run_maintenance_work(
     initialisation_started: Arc<Barrier>,
     repo_pool: Arc<feedback::proposed_edits::repo_pool::RepoPool>,
     calendar_metrics: refresh::calendar::CalendarMetrics,
+    preview_cache: locations::preview_cache::PreviewCache,
 ) {
     let meilisearch_enabled = env::var("SKIP_MS_SETUP") != Ok("true".to_string());
     if meilisearch_enabled {
@@ -192,9 +207,8 @@ async fn run_maintenance_work(
         info!("skipping the meilisearch setup as SKIP_MS_SETUP=true");
         initialisation_started.wait().await;
     }
-    if env::var("SKIP_DB_SETUP") == Ok("true".to_string()) {
-        info!("skipping the database setup as SKIP_DB_SETUP=true");
-    } else {
+    let db_setup_enabled = env::var("SKIP_DB_SETUP") != Ok("true".to_string());
+    if db_setup_enabled {
         async {
             setup::database::setup(&pool)
                 .await
@@ -203,8 +217,8 @@ async fn run_maintenance_work(
                 .await
                 .expect("postgis initial data load to succeed");
             // Once `de`/`en` are populated, every remaining loader fans out.
-            // The lookup tables FK back to `de`/`en` only, transportation is
-            // FK-isolated, and tumonline_orgs -> events is a self-contained
+            // The lookup tables FK back to `de`/`en` only, transportation and
+            // key_successors are FK-isolated, and tumonline_orgs -> events is a self-contained
             // sequential pair (events.organising_org_id REFERENCES
             // tumonline_orgs.org_id).
             let mut loaders = JoinSet::new();
@@ -219,12 +233,14 @@ async fn run_maintenance_work(
             loaders.spawn(setup::ranking_factors::setup(pool.clone()));
             loaders.spawn(setup::operators_de::setup(pool.clone()));
             loaders.spawn(setup::operators_en::setup(pool.clone()));
+            loaders.spawn(setup::semesters::setup(pool.clone()));
             loaders.spawn(setup::sources::setup(pool.clone()));
             loaders.spawn(setup::usages::setup(pool.clone()));
             loaders.spawn(setup::urls_de::setup(pool.clone()));
             loaders.spawn(setup::urls_en::setup(pool.clone()));
             loaders.spawn(setup::parents::setup(pool.clone()));
             loaders.spawn(setup::location_images::setup(pool.clone()));
+            loaders.spawn(setup::key_successors::setup(pool.clone()));
             while let Some(res) = loaders.join_next().await {
                 res.expect("loader task to complete")
                     .expect("loader setup to succeed");
@@ -232,6 +248,8 @@ async fn run_maintenance_work(
         }
         .instrument(debug_span!("updating postgis data"))
         .await;
+    } else {
+        info!("skipping the database setup as SKIP_DB_SETUP=true");
     }
     let mut set = JoinSet::new();
     let cal_pool = pool.clone();
@@ -241,6 +259,8 @@ async fn run_maintenance_work(
     set.spawn(async move {
         refresh::calendar::record_freshness(&freshness_pool, calendar_metrics).await;
     });
+    let prune_pool = pool.clone();
+    set.spawn(async move { refresh::calendar::prune_changes(&prune_pool).await });
     // The lecture facet is derived from the (continuously scraped) calendar, so
     // it only makes sense when Meilisearch is the destination. It builds its own
     // client because the setup client above is scoped to initial loading.
@@ -253,6 +273,17 @@ async fn run_maintenance_work(
             refresh::lectures::refresh_lectures(lecture_pool, lecture_client).await;
         });
     }
+    // previews of freshly loaded data are rendered before anyone shares them
+    if db_setup_enabled {
+        let prerender_pool = pool.clone();
+        set.spawn(async move {
+            Box::pin(locations::preview::prerender_top_ranked(
+                &prerender_pool,
+                &preview_cache,
+            ))
+            .await;
+        });
+    }
     set.join_all().await;
 
     // Warm up the bare repo for edit proposals after all other setup is done.
@@ -283,6 +314,7 @@ async fn main() -> anyhow::Result<()> {
         Arc::clone(&initialisation_started),
         Arc::clone(&repo_pool),
         calendar_metrics,
+        data.preview_cache.clone(),
     ));
 
     let shutdown_pool_clone = data.pool.clone();
@@ -314,6 +346,7 @@ async fn main() -> anyhow::Result<()> {
                 .wrap(TracingLogger::default())
                 .wrap(middleware::Compress::default())
                 .app_data(web::JsonConfig::default().limit(MAX_JSON_PAYLOAD))
+                .app_data(web::PayloadConfig::default().limit(MAX_PAYLOAD))
                 .app_data(web::Data::new(data.clone()))
                 .into_utoipa_app()
                 .app_data(recorded_tokens.clone())
@@ -321,13 +354,30 @@ async fn main() -> anyhow::Result<()> {
                 .app_data(eat_api_menus.clone())
                 .service(health_status_handler)
                 .service(calendar::calendar_handler)
+                .service(calendar::calendar_page_handler)
+                .service(calendar::changes::changes_handler)
+                .service(calendar::changes::changes_atom_handler)
+                .service(calendar::quality::quality_handler)
+                .service(calendar::ics::room_ics_handler)
+                .service(calendar::ics::combined_ics_handler)
+                .service(timetable::resolve_handler)
                 .service(maps::route::route_handler)
+                .service(maps::tiles::tile_handler)
                 .service(mensa::menu_handler)
                 .service(search::search_handler)
+                // must be registered before `get_handler`, which would otherwise match `{id}.geojson` or `free`
+                .service(locations::geojson::geojson_handler)
+                .service(locations::free::free_rooms_handler)
                 .service(locations::details::get_handler)
                 .service(locations::nearby::nearby_handler)
                 .service(locations::preview::maps_handler)
                 .service(locations::qr_code::qr_code_handler)
+                .service(locations::sign::sign_handler)
+                .service(locations::levels::levels_handler)
+                .service(locations::occupancy::occupancy_handler)
+                .service(oembed::oembed_handler)
+                .service(sitemap::sitemap_index_handler)
+                .service(sitemap::sitemap_shard_handler)
                 .service(feedback::post_feedback::send_feedback)
                 .service(feedback::proposed_edits::propose_edits)
                 .service(
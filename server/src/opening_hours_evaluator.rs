@@ -0,0 +1,424 @@
+//! Evaluation of OSM [`opening_hours`](https://wiki.openstreetmap.org/wiki/Key:opening_hours) schedules.
+//!
+//! Clients should not have to bundle their own `opening_hours` parser, so the server answers
+//! "is this open right now?", "when does this change?" and "what does the coming week look like?".
+//! Everything is evaluated in `Europe/Berlin` time.
+//!
+//! Bavarian public holidays are already expanded into absolute dates at data-build time
+//! (see `data/processors/public_holiday_expander.py`). National German holidays are still attached
+//! to the evaluation context, so that a `PH` rule which slipped through is not silently ignored.
+
+use std::ops::Range;
+
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeZone as _, Utc};
+use chrono_tz::Europe::Berlin;
+use chrono_tz::Tz;
+use opening_hours::localization::{Coordinates, Country, TzLocation};
+use opening_hours::{Context, OpeningHours, RuleKind};
+
+/// How many days, starting with today, [`Evaluation::week`] covers.
+const DAYS_EVALUATED: u64 = 7;
+
+/// The state of a schedule at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Open,
+    Closed,
+    /// The schedule says so explicitly, or we are outside the schedule's validity
+    Unknown,
+}
+
+impl From<RuleKind> for State {
+    fn from(kind: RuleKind) -> Self {
+        match kind {
+            RuleKind::Open => Self::Open,
+            RuleKind::Closed => Self::Closed,
+            RuleKind::Unknown => Self::Unknown,
+        }
+    }
+}
+
+/// A period of time in which the state of the schedule does not change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interval {
+    pub range: Range<DateTime<Tz>>,
+    pub state: State,
+    /// Comment of the rule which is active in this period (e.g. `"by appointment"`)
+    pub comment: Option<String>,
+}
+
+/// All intervals of a day in which the location is not closed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub intervals: Vec<Interval>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// `None` if the schedule could not be parsed or is never valid
+    pub state: Option<State>,
+    /// When [`Self::state`] changes next, if this happens within the evaluated days
+    pub next_change_at: Option<DateTime<Tz>>,
+    /// One entry per day, starting with today
+    pub week: Vec<Day>,
+    /// Problems found while evaluating, such as an unparseable schedule
+    pub warnings: Vec<String>,
+}
+
+impl Evaluation {
+    /// Nothing is known about a schedule which cannot be evaluated
+    fn rejected(warnings: Vec<String>) -> Self {
+        Self {
+            state: None,
+            next_change_at: None,
+            week: Vec::new(),
+            warnings,
+        }
+    }
+}
+
+/// An `opening_hours` schedule, optionally only valid between two dates (both inclusive)
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule<'a> {
+    pub osm: &'a str,
+    pub valid_from: Option<&'a str>,
+    pub valid_until: Option<&'a str>,
+    /// `(lat, lon)` of the location, used for sun events such as `sunset`
+    pub coords: Option<(f64, f64)>,
+}
+
+impl Schedule<'_> {
+    /// Evaluates the schedule from `now` on.
+    #[tracing::instrument(level = tracing::Level::DEBUG)]
+    pub fn evaluate(&self, now: DateTime<Utc>) -> Evaluation {
+        let mut warnings = Vec::new();
+        let valid_from = parse_bound(self.valid_from, "valid_from", &mut warnings);
+        let valid_until = parse_bound(self.valid_until, "valid_until", &mut warnings);
+        if let (Some(from), Some(until)) = (valid_from, valid_until)
+            && from > until
+        {
+            warnings.push(format!(
+                "valid_from={from} is after valid_until={until}, so the schedule is never valid"
+            ));
+            return Evaluation::rejected(warnings);
+        }
+        let opening_hours = match self.osm.parse::<OpeningHours>() {
+            Ok(opening_hours) => opening_hours.with_context(self.context()),
+            Err(e) => {
+                warnings.push(format!("could not parse the opening hours: {e}"));
+                return Evaluation::rejected(warnings);
+            }
+        };
+
+        let now = now.with_timezone(&Berlin);
+        let today = now.date_naive();
+        let window_end_date = today + Days::new(DAYS_EVALUATED);
+        let validity = valid_from.map_or(start_of_day(today), start_of_day)
+            ..valid_until.map_or(start_of_day(window_end_date), |until| {
+                start_of_day(until + Days::new(1))
+            });
+        let intervals = merge_adjacent(
+            opening_hours
+                .iter_range(start_of_day(today), start_of_day(window_end_date))
+                .flat_map(|dtr| {
+                    let comment = Some(dtr.comment.to_string()).filter(|c| !c.is_empty());
+                    restrict_to_validity(dtr.range, dtr.kind.into(), comment, &validity)
+                }),
+        );
+
+        let current = intervals.iter().find(|i| i.range.contains(&now));
+        let week = (0..DAYS_EVALUATED)
+            .map(|offset| {
+                let date = today + Days::new(offset);
+                let day = start_of_day(date)..start_of_day(date + Days::new(1));
+                Day {
+                    date,
+                    intervals: intervals
+                        .iter()
+                        .filter(|i| i.state != State::Closed)
+                        .filter_map(|i| intersect(i, &day))
+                        .collect(),
+                }
+            })
+            .collect();
+        Evaluation {
+            state: current.map(|i| i.state),
+            next_change_at: current
+                .map(|i| i.range.end)
+                .filter(|end| *end < start_of_day(window_end_date)),
+            week,
+            warnings,
+        }
+    }
+
+    fn context(&self) -> Context<TzLocation<Tz>> {
+        let location = TzLocation::new(Berlin);
+        let location = match self
+            .coords
+            .and_then(|(lat, lon)| Coordinates::new(lat, lon))
+        {
+            Some(coords) => location.with_coords(coords),
+            None => location,
+        };
+        Context::default()
+            .with_holidays(Country::DE.holidays())
+            .with_locale(location)
+    }
+}
+
+fn parse_bound(bound: Option<&str>, name: &str, warnings: &mut Vec<String>) -> Option<NaiveDate> {
+    let bound = bound?;
+    match NaiveDate::parse_from_str(bound, "%Y-%m-%d") {
+        Ok(date) => Some(date),
+        Err(e) => {
+            warnings.push(format!(
+                "ignoring {name}={bound:?}, as it is not a YYYY-MM-DD date: {e}"
+            ));
+            None
+        }
+    }
+}
+
+/// The first instant of `date` in Berlin
+///
+/// Germany switches DST at 02:00/03:00, so midnight always exists exactly once.
+fn start_of_day(date: NaiveDate) -> DateTime<Tz> {
+    Berlin
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .expect("midnight exists exactly once in Europe/Berlin")
+}
+
+/// Splits `range` so that anything outside `validity` is [`State::Unknown`]
+fn restrict_to_validity(
+    range: Range<DateTime<Tz>>,
+    state: State,
+    comment: Option<String>,
+    validity: &Range<DateTime<Tz>>,
+) -> Vec<Interval> {
+    let inside_start = range.start.max(validity.start);
+    let inside_end = range.end.min(validity.end);
+    let mut result = Vec::with_capacity(3);
+    if range.start < validity.start {
+        result.push(Interval {
+            range: range.start..inside_end.min(validity.start),
+            state: State::Unknown,
+            comment: None,
+        });
+    }
+    if inside_start < inside_end {
+        result.push(Interval {
+            range: inside_start..inside_end,
+            state,
+            comment,
+        });
+    }
+    if validity.end < range.end {
+        result.push(Interval {
+            range: inside_start.max(validity.end)..range.end,
+            state: State::Unknown,
+            comment: None,
+        });
+    }
+    result
+}
+
+fn merge_adjacent(intervals: impl IntoIterator<Item = Interval>) -> Vec<Interval> {
+    let mut merged: Vec<Interval> = Vec::new();
+    for interval in intervals {
+        match merged.last_mut() {
+            Some(last)
+                if last.range.end == interval.range.start
+                    && last.state == interval.state
+                    && last.comment == interval.comment =>
+            {
+                last.range.end = interval.range.end;
+            }
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
+fn intersect(interval: &Interval, day: &Range<DateTime<Tz>>) -> Option<Interval> {
+    let range = interval.range.start.max(day.start)..interval.range.end.min(day.end);
+    (range.start < range.end).then(|| Interval {
+        range,
+        state: interval.state,
+        comment: interval.comment.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::indexing_slicing,
+        reason = "tests assert via unwrap and index into known-shape results"
+    )]
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn berlin(date: &str, time: &str) -> DateTime<Tz> {
+        let naive = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap()
+            .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap());
+        Berlin.from_local_datetime(&naive).single().unwrap()
+    }
+
+    fn schedule(osm: &str) -> Schedule<'_> {
+        Schedule {
+            osm,
+            valid_from: None,
+            valid_until: None,
+            coords: None,
+        }
+    }
+
+    #[test]
+    fn open_during_opening_hours() {
+        // 2026-05-04 is a Monday
+        let now = berlin("2026-05-04", "10:00").with_timezone(&Utc);
+        let evaluation = schedule("Mo-Fr 08:00-22:00; Sa 09:00-17:00").evaluate(now);
+        assert_eq!(evaluation.state, Some(State::Open));
+        assert_eq!(
+            evaluation.next_change_at,
+            Some(berlin("2026-05-04", "22:00"))
+        );
+        assert_eq!(evaluation.warnings, Vec::<String>::new());
+    }
+
+    #[test]
+    fn week_lists_only_non_closed_intervals() {
+        let now = berlin("2026-05-04", "23:00").with_timezone(&Utc);
+        let evaluation = schedule("Mo-Fr 08:00-22:00; Sa 09:00-17:00").evaluate(now);
+        assert_eq!(evaluation.state, Some(State::Closed));
+        assert_eq!(
+            evaluation.next_change_at,
+            Some(berlin("2026-05-05", "08:00"))
+        );
+        assert_eq!(evaluation.week.len(), 7);
+        let dates = evaluation
+            .week
+            .iter()
+            .map(|d| (d.date.to_string(), d.intervals.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dates,
+            vec![
+                ("2026-05-04".to_string(), 1),
+                ("2026-05-05".to_string(), 1),
+                ("2026-05-06".to_string(), 1),
+                ("2026-05-07".to_string(), 1),
+                ("2026-05-08".to_string(), 1),
+                ("2026-05-09".to_string(), 1),
+                ("2026-05-10".to_string(), 0),
+            ]
+        );
+        assert_eq!(
+            evaluation.week[5].intervals[0].range,
+            berlin("2026-05-09", "09:00")..berlin("2026-05-09", "17:00")
+        );
+    }
+
+    #[test]
+    fn outside_of_validity_is_unknown() {
+        let now = berlin("2026-05-04", "10:00").with_timezone(&Utc);
+        let evaluation = Schedule {
+            valid_from: Some("2026-05-06"),
+            valid_until: Some("2026-05-07"),
+            ..schedule("Mo-Fr 08:00-22:00")
+        }
+        .evaluate(now);
+        assert_eq!(evaluation.state, Some(State::Unknown));
+        assert_eq!(
+            evaluation.next_change_at,
+            Some(berlin("2026-05-06", "00:00"))
+        );
+        let states = evaluation
+            .week
+            .iter()
+            .map(|d| d.intervals.iter().map(|i| i.state).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            vec![
+                vec![State::Unknown],
+                vec![State::Unknown],
+                vec![State::Open],
+                vec![State::Open],
+                vec![State::Unknown],
+                vec![State::Unknown],
+                vec![State::Unknown],
+            ]
+        );
+    }
+
+    #[test]
+    fn national_public_holidays_are_honoured() {
+        // 2026-05-01 (Friday) is Labour Day
+        let now = berlin("2026-05-01", "10:00").with_timezone(&Utc);
+        let evaluation = schedule("Mo-Fr 08:00-22:00; PH off").evaluate(now);
+        assert_eq!(evaluation.state, Some(State::Closed));
+    }
+
+    #[test]
+    fn comments_are_kept() {
+        let now = berlin("2026-05-04", "10:00").with_timezone(&Utc);
+        let evaluation = schedule(r#"Mo 08:00-12:00 "nur mit Termin""#).evaluate(now);
+        assert_eq!(
+            evaluation.week[0].intervals[0].comment.as_deref(),
+            Some("nur mit Termin")
+        );
+    }
+
+    #[test]
+    fn unparseable_schedules_are_reported() {
+        let now = berlin("2026-05-04", "10:00").with_timezone(&Utc);
+        let evaluation = Schedule {
+            valid_from: Some("next monday"),
+            ..schedule("whenever we feel like it")
+        }
+        .evaluate(now);
+        assert_eq!(evaluation.state, None);
+        assert_eq!(evaluation.week, Vec::new());
+        assert_eq!(evaluation.warnings.len(), 2);
+        assert!(evaluation.warnings[0].starts_with("ignoring valid_from=\"next monday\""));
+        assert!(evaluation.warnings[1].starts_with("could not parse the opening hours"));
+    }
+
+    #[test]
+    fn inverted_validity_is_rejected() {
+        let now = berlin("2026-05-04", "10:00").with_timezone(&Utc);
+        let evaluation = Schedule {
+            valid_from: Some("2026-05-07"),
+            valid_until: Some("2026-05-06"),
+            ..schedule("Mo-Fr 08:00-22:00")
+        }
+        .evaluate(now);
+        assert_eq!(evaluation.state, None);
+        assert_eq!(evaluation.next_change_at, None);
+        assert_eq!(evaluation.week, Vec::new());
+        assert_eq!(
+            evaluation.warnings,
+            [
+                "valid_from=2026-05-07 is after valid_until=2026-05-06, so the schedule is never valid"
+            ]
+        );
+    }
+
+    #[test]
+    fn dst_switch_is_handled() {
+        // 2026-03-29 is the switch to summer time, the day only has 23 hours
+        let now = berlin("2026-03-29", "12:00").with_timezone(&Utc);
+        let evaluation = schedule("24/7").evaluate(now);
+        assert_eq!(evaluation.state, Some(State::Open));
+        assert_eq!(evaluation.next_change_at, None);
+        assert_eq!(
+            evaluation.week[0].intervals[0].range,
+            berlin("2026-03-29", "00:00")..berlin("2026-03-30", "00:00")
+        );
+    }
+}
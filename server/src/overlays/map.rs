@@ -1,9 +1,37 @@
-use image::imageops;
+use std::f64::consts::PI;
+
+use image::{Rgba, RgbaImage, imageops};
+use imageproc::drawing::{
+    draw_filled_circle_mut, draw_hollow_polygon_mut, draw_line_segment_mut, draw_polygon_mut,
+};
+use imageproc::point::Point;
+use serde_json::Value;
+use sqlx::PgPool;
 use tracing::warn;
 
-use crate::external::static_map_image::{Camera, download_static_map_image};
+use crate::db::basemap::BasemapFeature;
+use crate::external::static_map_image::{Camera, download_static_map_image, martin_url};
+
+/// Size of a tile of the static renderer, which defines the scale of a zoom level
+const TILE_SIZE: f64 = 512.0;
+/// Colours of the locally rendered basemap, close to the indoor style of the remote one
+const LOCAL_BACKGROUND: Rgba<u8> = Rgba([0xF2, 0xF1, 0xED, 0xFF]);
+const LOCAL_BUILDING: Rgba<u8> = Rgba([0xDD, 0xD9, 0xD3, 0xFF]);
+const LOCAL_BUILDING_OUTLINE: Rgba<u8> = Rgba([0xC8, 0xC3, 0xBB, 0xFF]);
+const LOCAL_ROAD: Rgba<u8> = Rgba([0xFF, 0xFF, 0xFF, 0xFF]);
+const LOCAL_ROOM: Rgba<u8> = Rgba([0xFF, 0xFF, 0xFF, 0xFF]);
+const LOCAL_CORRIDOR: Rgba<u8> = Rgba([0xE4, 0xE2, 0xDC, 0xFF]);
+const LOCAL_OUTLINE: Rgba<u8> = Rgba([0xB4, 0xB1, 0xA9, 0xFF]);
+const LOCAL_WALL: Rgba<u8> = Rgba([0x8A, 0x87, 0x80, 0xFF]);
 
-const BOTTOM_PANEL_HEIGHT: u32 = 125;
+/// Which renderer drew the basemap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Basemap {
+    /// Martin, with the full style
+    Remote,
+    /// The fallback rasterised from the database
+    Local,
+}
 
 #[derive(Debug)]
 pub struct OverlayMapTask {
@@ -36,18 +64,410 @@ impl OverlayMapTask {
         }
     }
 
-    #[tracing::instrument(skip(img))]
-    pub async fn draw_onto(&self, img: &mut image::RgbaImage) -> bool {
-        let map_height = img.height() - BOTTOM_PANEL_HEIGHT;
-        match download_static_map_image(self.camera, img.width(), map_height).await {
-            Ok(map) => {
-                imageops::overlay(img, &map, 0, 0);
-                true
+    /// Draws the map onto `img`, leaving space for a panel of `bottom_panel_height` pixels at the bottom.
+    ///
+    /// The basemap is rendered by Martin if `MARTIN_URL` is configured and reachable, otherwise it
+    /// is rasterised from the buildings, roads and the indoor geometry of `level` in the database.
+    /// Returns `None` if neither produced a map.
+    #[tracing::instrument(skip(pool, img))]
+    pub async fn draw_onto(
+        &self,
+        pool: &PgPool,
+        img: &mut RgbaImage,
+        bottom_panel_height: u32,
+        level: f32,
+    ) -> Option<Basemap> {
+        let map_height = img.height() - bottom_panel_height;
+        if let Some(martin_url) = martin_url() {
+            match download_static_map_image(&martin_url, self.camera, img.width(), map_height).await
+            {
+                Ok(map) => {
+                    imageops::overlay(img, &map, 0, 0);
+                    return Some(Basemap::Remote);
+                }
+                Err(e) => {
+                    warn!(error = ?e, camera = ?self.camera, "could not render preview basemap, falling back to local rendering");
+                }
+            }
+        }
+        let features =
+            match BasemapFeature::fetch_within(pool, self.bounds(img.width(), map_height), level)
+                .await
+            {
+                Ok(features) => features,
+                Err(e) => {
+                    warn!(error = ?e, camera = ?self.camera, "could not get the local basemap");
+                    return None;
+                }
+            };
+        // without any geometry, the map would be an empty canvas
+        if features.is_empty() {
+            return None;
+        }
+        self.draw_local_basemap(img, bottom_panel_height, &features);
+        Some(Basemap::Local)
+    }
+
+    /// Rasterises `features` as a flat map, ignoring the pitch of the camera
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "the map height is far below 2^23"
+    )]
+    fn draw_local_basemap(
+        &self,
+        img: &mut RgbaImage,
+        bottom_panel_height: u32,
+        features: &[BasemapFeature],
+    ) {
+        let (width, map_height) = (img.width(), img.height() - bottom_panel_height);
+        for x in 0..width {
+            for y in 0..map_height {
+                img.put_pixel(x, y, LOCAL_BACKGROUND);
+            }
+        }
+        let mut map = RgbaImage::new(width, map_height);
+        for feature in features {
+            let (fill, outline) = match (feature.layer.as_str(), feature.class.as_deref()) {
+                ("road", kind) => {
+                    let line_width = match kind {
+                        Some("major") => 6.0,
+                        Some("minor") => 4.0,
+                        _ => 2.0,
+                    };
+                    for line in self.project_lines(&feature.geometry, width, map_height) {
+                        draw_thick_line(&mut map, &line, line_width, LOCAL_ROAD);
+                    }
+                    continue;
+                }
+                ("wall", _) => {
+                    for line in self.project_lines(&feature.geometry, width, map_height) {
+                        draw_thick_line(&mut map, &line, 1.0, LOCAL_WALL);
+                    }
+                    continue;
+                }
+                ("building", _) => (LOCAL_BUILDING, LOCAL_BUILDING_OUTLINE),
+                (_, Some("room")) => (LOCAL_ROOM, LOCAL_OUTLINE),
+                _ => (LOCAL_CORRIDOR, LOCAL_OUTLINE),
+            };
+            for (is_outer, ring) in self.project_rings(&feature.geometry, width, map_height) {
+                let visible = ring
+                    .iter()
+                    .any(|p| p.y > -(map_height as f32) && p.y < 2.0 * map_height as f32);
+                if !visible {
+                    continue;
+                }
+                if is_outer {
+                    fill_polygon(&mut map, &ring, fill);
+                }
+                draw_hollow_polygon_mut(&mut map, &ring, outline);
+            }
+        }
+        imageops::overlay(img, &map, 0, 0);
+    }
+
+    /// Web mercator position of a coordinate in pixels at the zoom level of the camera
+    fn mercator(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let world = self.world_size();
+        let lat = lat.to_radians();
+        (
+            (lon + 180.0) / 360.0 * world,
+            (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * world,
+        )
+    }
+
+    fn world_size(&self) -> f64 {
+        TILE_SIZE * 2_f64.powi(self.camera.zoom.try_into().unwrap_or(i32::MAX))
+    }
+
+    /// `[min_lon, min_lat, max_lon, max_lat]` of the map drawn by [`Self::draw_onto`]
+    fn bounds(&self, width: u32, map_height: u32) -> [f64; 4] {
+        let world = self.world_size();
+        let (center_x, center_y) = self.mercator(self.camera.lon, self.camera.lat);
+        let lon = |x: f64| x / world * 360.0 - 180.0;
+        let lat = |y: f64| (PI * (1.0 - 2.0 * y / world)).sinh().atan().to_degrees();
+        let (half_width, half_height) = (f64::from(width) / 2.0, f64::from(map_height) / 2.0);
+        [
+            lon(center_x - half_width),
+            lat(center_y + half_height),
+            lon(center_x + half_width),
+            lat(center_y - half_height),
+        ]
+    }
+
+    /// Pixel position of a coordinate on the map drawn by [`Self::draw_onto`]
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "pixel positions of anything near the map are far below f32::MAX"
+    )]
+    fn project(&self, lon: f64, lat: f64, width: u32, map_height: u32) -> Point<f32> {
+        let (x, y) = self.mercator(lon, lat);
+        let (center_x, center_y) = self.mercator(self.camera.lon, self.camera.lat);
+        Point::new(
+            (f64::from(width) / 2.0 + x - center_x) as f32,
+            (f64::from(map_height) / 2.0 + y - center_y) as f32,
+        )
+    }
+
+    /// Highlights a `GeoJSON` `Polygon` or `MultiPolygon` on the map drawn by [`Self::draw_onto`].
+    ///
+    /// Tilted maps are not supported, as the projection would need to account for the perspective.
+    #[tracing::instrument(skip(img, geometry))]
+    pub fn draw_highlight(
+        &self,
+        img: &mut RgbaImage,
+        bottom_panel_height: u32,
+        geometry: &Value,
+        color: Rgba<u8>,
+    ) {
+        if self.camera.pitch != 0 {
+            return;
+        }
+        let map_height = img.height() - bottom_panel_height;
+        let Rgba([red, green, blue, _]) = color;
+        let mut fill = RgbaImage::new(img.width(), map_height);
+        let mut outlines = Vec::new();
+        for (is_outer, ring) in self.project_rings(geometry, img.width(), map_height) {
+            // holes stay filled, rooms rarely have any
+            if is_outer {
+                fill_polygon(&mut fill, &ring, Rgba([red, green, blue, 0x60]));
             }
-            Err(e) => {
-                warn!(error = ?e, camera = ?self.camera, "could not render preview basemap");
-                false
+            outlines.push(ring);
+        }
+        imageops::overlay(img, &fill, 0, 0);
+        for outline in outlines {
+            draw_hollow_polygon_mut(img, &outline, color);
+        }
+    }
+
+    /// Projected rings of a `GeoJSON` `Polygon` or `MultiPolygon`, with whether they are an outer ring
+    fn project_rings(
+        &self,
+        geometry: &Value,
+        width: u32,
+        map_height: u32,
+    ) -> Vec<(bool, Vec<Point<f32>>)> {
+        let polygons: Vec<&Value> = match geometry.get("type").and_then(Value::as_str) {
+            Some("Polygon") => geometry.get("coordinates").into_iter().collect(),
+            Some("MultiPolygon") => geometry
+                .get("coordinates")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut projected = Vec::new();
+        for rings in polygons.iter().filter_map(|p| p.as_array()) {
+            for (i, ring) in rings.iter().enumerate() {
+                let mut points = self.project_positions(ring, width, map_height);
+                // GeoJSON rings are closed, imageproc expects them open
+                if points.len() > 1 && points.first() == points.last() {
+                    points.pop();
+                }
+                if points.len() >= 3 {
+                    projected.push((i == 0, points));
+                }
             }
         }
+        projected
+    }
+
+    /// Projected lines of a `GeoJSON` `LineString` or `MultiLineString`
+    fn project_lines(&self, geometry: &Value, width: u32, map_height: u32) -> Vec<Vec<Point<f32>>> {
+        let coordinates = geometry.get("coordinates");
+        match geometry.get("type").and_then(Value::as_str) {
+            Some("LineString") => coordinates
+                .map(|line| self.project_positions(line, width, map_height))
+                .into_iter()
+                .collect(),
+            Some("MultiLineString") => coordinates
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|line| self.project_positions(line, width, map_height))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Projects an array of `GeoJSON` positions, skipping malformed ones
+    fn project_positions(&self, positions: &Value, width: u32, map_height: u32) -> Vec<Point<f32>> {
+        positions
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|position| {
+                let position = position.as_array()?;
+                let lon = position.first()?.as_f64()?;
+                let lat = position.get(1)?.as_f64()?;
+                Some(self.project(lon, lat, width, map_height))
+            })
+            .collect()
+    }
+}
+
+/// Fills an open ring, skipping rings which collapse to a closed one when rounded to pixels
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "the points are pixel positions near the image"
+)]
+fn fill_polygon(img: &mut RgbaImage, ring: &[Point<f32>], color: Rgba<u8>) {
+    let rounded: Vec<Point<i32>> = ring
+        .iter()
+        .map(|p| Point::new(p.x.round() as i32, p.y.round() as i32))
+        .collect();
+    if rounded.len() >= 3 && rounded.first() != rounded.last() {
+        draw_polygon_mut(img, &rounded, color);
+    }
+}
+
+/// Draws a polyline of `line_width` pixels with round joins, or a plain one for a width of one pixel
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "the points are pixel positions near the image and the widths a few pixels"
+)]
+fn draw_thick_line(img: &mut RgbaImage, line: &[Point<f32>], line_width: f32, color: Rgba<u8>) {
+    let half_width = line_width / 2.0;
+    for segment in line.windows(2) {
+        let [from, to] = segment else {
+            continue;
+        };
+        if line_width <= 1.0 {
+            draw_line_segment_mut(img, (from.x, from.y), (to.x, to.y), color);
+            continue;
+        }
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = dx.hypot(dy);
+        if length > 0.0 {
+            // the normal of the segment, scaled to half the width
+            let (nx, ny) = (-dy / length * half_width, dx / length * half_width);
+            let quad = [
+                Point::new(from.x + nx, from.y + ny),
+                Point::new(to.x + nx, to.y + ny),
+                Point::new(to.x - nx, to.y - ny),
+                Point::new(from.x - nx, from.y - ny),
+            ];
+            fill_polygon(img, &quad, color);
+        }
+        for point in [from, to] {
+            draw_filled_circle_mut(
+                img,
+                (point.x.round() as i32, point.y.round() as i32),
+                half_width.round() as i32,
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projection_is_north_up_around_the_center() {
+        let map = OverlayMapTask::new("room", 48.262_5, 11.668_8);
+        let center = map.project(11.668_8, 48.262_5, 1200, 505);
+        assert!((center.x - 600.0).abs() < 0.01 && (center.y - 252.5).abs() < 0.01);
+        let north_east = map.project(11.669_0, 48.262_7, 1200, 505);
+        assert!(north_east.x > center.x && north_east.y < center.y);
+        // 0.0002° of longitude are ~37px at zoom 17
+        assert!(
+            (north_east.x - center.x - 37.3).abs() < 0.5,
+            "{north_east:?}"
+        );
+    }
+
+    #[test]
+    fn highlight_is_drawn_around_the_room() {
+        let map = OverlayMapTask::new("room", 48.262_5, 11.668_8);
+        let mut img = RgbaImage::new(400, 400);
+        let room = serde_json::json!({
+            "type": "Polygon",
+            "coordinates": [[[11.6687, 48.2624], [11.6689, 48.2624], [11.6689, 48.2626], [11.6687, 48.2626], [11.6687, 48.2624]]]
+        });
+        map.draw_highlight(&mut img, 100, &room, Rgba([0x30, 0x70, 0xB3, 0xFF]));
+        assert_eq!(img.get_pixel(200, 150).0[3], 0x60, "the room is filled");
+        assert_eq!(img.get_pixel(10, 10).0[3], 0, "outside is untouched");
+    }
+
+    #[test]
+    fn bounds_contain_the_center() {
+        let map = OverlayMapTask::new("room", 48.262_5, 11.668_8);
+        let [min_lon, min_lat, max_lon, max_lat] = map.bounds(1200, 505);
+        assert!(min_lon < 11.668_8 && 11.668_8 < max_lon);
+        assert!(min_lat < 48.262_5 && 48.262_5 < max_lat);
+        let south_west = map.project(min_lon, min_lat, 1200, 505);
+        assert!(south_west.x.abs() < 0.01 && (south_west.y - 505.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn local_basemap_draws_rooms_and_walls() {
+        let map = OverlayMapTask::new("room", 48.262_5, 11.668_8);
+        let mut img = RgbaImage::new(400, 400);
+        let features = [
+            BasemapFeature {
+                layer: "room".to_string(),
+                class: Some("room".to_string()),
+                geometry: serde_json::json!({
+                    "type": "Polygon",
+                    "coordinates": [[[11.6687, 48.2624], [11.6689, 48.2624], [11.6689, 48.2626], [11.6687, 48.2626], [11.6687, 48.2624]]]
+                }),
+            },
+            BasemapFeature {
+                layer: "wall".to_string(),
+                class: None,
+                geometry: serde_json::json!({
+                    "type": "LineString",
+                    "coordinates": [[11.6685, 48.2627], [11.6691, 48.2627]]
+                }),
+            },
+        ];
+        map.draw_local_basemap(&mut img, 100, &features);
+        assert_eq!(*img.get_pixel(200, 150), LOCAL_ROOM, "the room is filled");
+        assert_eq!(*img.get_pixel(10, 10), LOCAL_BACKGROUND);
+        assert!(
+            (0..300).any(|y| *img.get_pixel(200, y) == LOCAL_WALL),
+            "the wall is drawn"
+        );
+        assert_eq!(img.get_pixel(10, 350).0[3], 0, "the panel is untouched");
+    }
+
+    #[test]
+    fn local_basemap_draws_buildings_and_roads() {
+        let map = OverlayMapTask::new("building", 48.262_5, 11.668_8);
+        let mut img = RgbaImage::new(400, 400);
+        let features = [
+            BasemapFeature {
+                layer: "building".to_string(),
+                class: None,
+                geometry: serde_json::json!({
+                    "type": "MultiPolygon",
+                    "coordinates": [[[[11.6680, 48.2620], [11.6696, 48.2620], [11.6696, 48.2627], [11.6680, 48.2627], [11.6680, 48.2620]]]]
+                }),
+            },
+            BasemapFeature {
+                layer: "road".to_string(),
+                class: Some("major".to_string()),
+                geometry: serde_json::json!({
+                    "type": "LineString",
+                    "coordinates": [[11.6650, 48.2631], [11.6720, 48.2631]]
+                }),
+            },
+        ];
+        map.draw_local_basemap(&mut img, 100, &features);
+        assert_eq!(
+            *img.get_pixel(200, 150),
+            LOCAL_BUILDING,
+            "the building is filled"
+        );
+        let road: Vec<u32> = (0..300)
+            .filter(|&y| *img.get_pixel(200, y) == LOCAL_ROAD)
+            .collect();
+        assert!(
+            (5..=7).contains(&road.len()),
+            "the road is drawn about 6px wide: {road:?}"
+        );
+        assert_eq!(*img.get_pixel(10, 290), LOCAL_BACKGROUND);
     }
 }
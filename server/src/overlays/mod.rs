@@ -1,2 +1,3 @@
 pub mod map;
+pub mod sign;
 pub mod text;
@@ -0,0 +1,519 @@
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
+    reason = "layout math: coordinates are small, non-negative and rounding to whole pixels is intended"
+)]
+//! Printable door signs.
+//!
+//! A sign is laid out once and then drawn either as a raster image (PNG, and PDF wrapping it),
+//! or as SVG, so all formats look the same.
+
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::mem;
+
+use ab_glyph::{Font as _, FontArc, PxScale, ScaleFont as _};
+use fast_qr::convert::{Builder as _, Shape, image::ImageBuilder, svg::SvgBuilder};
+use fast_qr::qr::QRBuilder;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage, imageops};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut, text_size};
+use imageproc::rect::Rect;
+
+use crate::overlays::text::{CANTARELL_BOLD, CANTARELL_REGULAR};
+use crate::xml;
+
+const TUM_BLUE: Rgba<u8> = Rgba([0x30, 0x70, 0xB3, 0xFF]);
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 0xFF]);
+const WHITE: Rgba<u8> = Rgba([0xFF, 0xFF, 0xFF, 0xFF]);
+const LINE_HEIGHT: f32 = 1.25;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Paper {
+    A4,
+    A5,
+}
+impl Paper {
+    /// Size in pixels at 150 dpi
+    fn pixels(self) -> (u32, u32) {
+        match self {
+            Self::A4 => (1240, 1754),
+            Self::A5 => (874, 1240),
+        }
+    }
+    /// Size in millimeters
+    fn millimeters(self) -> (u32, u32) {
+        match self {
+            Self::A4 => (210, 297),
+            Self::A5 => (148, 210),
+        }
+    }
+    /// Size in PDF points (1/72 inch)
+    fn points(self) -> (f32, f32) {
+        let (w, h) = self.millimeters();
+        (w as f32 * 72.0 / 25.4, h as f32 * 72.0 / 25.4)
+    }
+}
+
+/// The bookings of the room, already formatted for display
+#[derive(Debug, Clone)]
+pub struct SignBookings {
+    /// e.g. `Today`
+    pub heading: String,
+    /// One line per booking
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DoorSign {
+    /// The room code, printed largest
+    pub code: String,
+    pub name: String,
+    pub building: Option<String>,
+    /// Where the QR code links to
+    pub url: String,
+    pub bookings: Option<SignBookings>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Element {
+    /// `y` is the top of the line
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        bold: bool,
+        text: String,
+    },
+    Rule {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    Qr {
+        x: f32,
+        y: f32,
+        size: f32,
+    },
+}
+
+fn font(bold: bool) -> &'static FontArc {
+    if bold {
+        &CANTARELL_BOLD
+    } else {
+        &CANTARELL_REGULAR
+    }
+}
+
+/// Greedily wraps `text` into at most `max_lines` lines of at most `max_width` pixels.
+///
+/// If the text does not fit, the last line is ended with an ellipsis.
+fn wrap(text: &str, size: f32, bold: bool, max_width: f32, max_lines: usize) -> Vec<String> {
+    let scale = PxScale::from(size);
+    let fits = |line: &str| text_size(scale, font(bold), line).0 as f32 <= max_width;
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{current} {word}")
+        };
+        if fits(&candidate) || current.is_empty() {
+            current = candidate;
+        } else {
+            lines.push(mem::replace(&mut current, word.to_string()));
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            last.push('…');
+        }
+    }
+    for line in &mut lines {
+        while !fits(line) && line.chars().count() > 1 {
+            let mut shortened = line.trim_end_matches('…').to_string();
+            shortened.pop();
+            *line = format!("{shortened}…");
+        }
+    }
+    lines
+}
+
+/// Stacks lines of text from top to bottom
+struct Column {
+    x: f32,
+    y: f32,
+    elements: Vec<Element>,
+}
+impl Column {
+    fn lines(&mut self, lines: Vec<String>, size: f32, bold: bool) {
+        for text in lines {
+            self.elements.push(Element::Text {
+                x: self.x,
+                y: self.y,
+                size,
+                bold,
+                text,
+            });
+            self.y += size * LINE_HEIGHT;
+        }
+    }
+}
+
+impl DoorSign {
+    fn layout(&self, paper: Paper) -> Vec<Element> {
+        let (width, height) = paper.pixels();
+        let (width, height) = (width as f32, height as f32);
+        let unit = width / 100.0;
+        let margin = 7.0 * unit;
+        let content_width = width - 2.0 * margin;
+        let qr_size = 28.0 * unit;
+        let qr_top = height - margin - qr_size;
+
+        let mut column = Column {
+            x: margin,
+            y: margin,
+            elements: Vec::new(),
+        };
+        column.lines(
+            wrap(&self.code, 9.0 * unit, true, content_width, 1),
+            9.0 * unit,
+            true,
+        );
+        column.y += unit;
+        column.elements.push(Element::Rule {
+            x: margin,
+            y: column.y,
+            width: content_width,
+            height: 0.6 * unit,
+        });
+        column.y += 3.0 * unit;
+        column.lines(
+            wrap(&self.name, 5.0 * unit, true, content_width, 3),
+            5.0 * unit,
+            true,
+        );
+        if let Some(building) = &self.building {
+            column.lines(
+                wrap(building, 3.5 * unit, false, content_width, 2),
+                3.5 * unit,
+                false,
+            );
+        }
+        if let Some(bookings) = &self.bookings {
+            column.y += 3.0 * unit;
+            column.lines(vec![bookings.heading.clone()], 3.5 * unit, true);
+            let size = 2.8 * unit;
+            let available =
+                ((qr_top - 3.0 * unit - column.y) / (size * LINE_HEIGHT)).max(0.0) as usize;
+            let mut lines: Vec<String> = bookings
+                .lines
+                .iter()
+                .flat_map(|line| wrap(line, size, false, content_width, 1))
+                .collect();
+            if lines.len() > available {
+                lines.truncate(available.saturating_sub(1));
+                lines.push("…".to_string());
+            }
+            column.lines(lines, size, false);
+        }
+
+        // footer: our name and the link next to the QR code
+        let url_width = content_width - qr_size - 3.0 * unit;
+        let display_url = self.url.trim_start_matches("https://");
+        column.y = height - margin - (4.0 + 2.5) * unit * LINE_HEIGHT;
+        column.lines(vec!["NavigaTUM".to_string()], 4.0 * unit, true);
+        column.lines(
+            wrap(display_url, 2.5 * unit, false, url_width, 1),
+            2.5 * unit,
+            false,
+        );
+        let mut elements = column.elements;
+        elements.push(Element::Qr {
+            x: width - margin - qr_size,
+            y: qr_top,
+            size: qr_size,
+        });
+        elements
+    }
+
+    /// Renders the sign as an image at 150 dpi
+    #[tracing::instrument]
+    pub fn render_image(&self, paper: Paper) -> anyhow::Result<RgbaImage> {
+        let (width, height) = paper.pixels();
+        let mut img = RgbaImage::from_pixel(width, height, WHITE);
+        for element in self.layout(paper) {
+            match element {
+                Element::Text {
+                    x,
+                    y,
+                    size,
+                    bold,
+                    text,
+                } => {
+                    draw_text_mut(
+                        &mut img,
+                        BLACK,
+                        x as i32,
+                        y as i32,
+                        PxScale::from(size),
+                        font(bold),
+                        &text,
+                    );
+                }
+                Element::Rule {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    let rect = Rect::at(x as i32, y as i32)
+                        .of_size(width.round() as u32, height.round().max(1.0) as u32);
+                    draw_filled_rect_mut(&mut img, rect, TUM_BLUE);
+                }
+                Element::Qr { x, y, size } => {
+                    let qrcode = QRBuilder::new(self.url.as_str())
+                        .build()
+                        .map_err(|e| anyhow::anyhow!("Failed to build QR code: {e}"))?;
+                    let png = ImageBuilder::default()
+                        .margin(0)
+                        .shape(Shape::RoundedSquare)
+                        .module_color(TUM_BLUE.0)
+                        .background_color(WHITE.0)
+                        .fit_width(size as u32)
+                        .fit_height(size as u32)
+                        .to_bytes(&qrcode)
+                        .map_err(|e| anyhow::anyhow!("cannot build QR code: {e}"))?;
+                    let qr = image::load_from_memory(&png)?;
+                    imageops::overlay(&mut img, &qr, i64::from(x as i32), i64::from(y as i32));
+                }
+            }
+        }
+        Ok(img)
+    }
+
+    #[tracing::instrument]
+    pub fn render_png(&self, paper: Paper) -> anyhow::Result<Vec<u8>> {
+        let img = self.render_image(paper)?;
+        let mut w = Cursor::new(Vec::new());
+        img.write_to(&mut w, ImageFormat::Png)?;
+        Ok(w.into_inner())
+    }
+
+    /// Renders a single-page PDF containing the rendered image
+    #[tracing::instrument]
+    pub fn render_pdf(&self, paper: Paper) -> anyhow::Result<Vec<u8>> {
+        let img = DynamicImage::ImageRgba8(self.render_image(paper)?).into_rgb8();
+        let mut jpeg = Cursor::new(Vec::new());
+        img.write_to(&mut jpeg, ImageFormat::Jpeg)?;
+        Ok(pdf_with_jpeg(
+            &jpeg.into_inner(),
+            img.dimensions(),
+            paper.points(),
+        ))
+    }
+
+    /// Renders the sign as SVG, with the text kept as text
+    #[tracing::instrument]
+    pub fn render_svg(&self, paper: Paper) -> anyhow::Result<String> {
+        let (width, height) = paper.pixels();
+        let (width_mm, height_mm) = paper.millimeters();
+        let mut svg = format!(
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="{width_mm}mm" height="{height_mm}mm" viewBox="0 0 {width} {height}">
+<rect width="100%" height="100%" fill="#ffffff"/>
+"##
+        );
+        for element in self.layout(paper) {
+            match element {
+                Element::Text {
+                    x,
+                    y,
+                    size,
+                    bold,
+                    text,
+                } => {
+                    let baseline = y + font(bold).as_scaled(PxScale::from(size)).ascent();
+                    writeln!(
+                        svg,
+                        r#"<text x="{x:.1}" y="{baseline:.1}" font-family="Cantarell, sans-serif" font-size="{size:.1}" font-weight="{weight}">{text}</text>"#,
+                        weight = if bold { "bold" } else { "normal" },
+                        text = xml::escape(&text),
+                    )?;
+                }
+                Element::Rule {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    writeln!(
+                        svg,
+                        r##"<rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" fill="#3070B3"/>"##
+                    )?;
+                }
+                Element::Qr { x, y, size } => {
+                    let qrcode = QRBuilder::new(self.url.as_str())
+                        .build()
+                        .map_err(|e| anyhow::anyhow!("Failed to build QR code: {e}"))?;
+                    let qr = SvgBuilder::default()
+                        .margin(0)
+                        .shape(Shape::RoundedSquare)
+                        .module_color(TUM_BLUE.0)
+                        .background_color(WHITE.0)
+                        .to_str(&qrcode)
+                        .replacen(
+                            "<svg ",
+                            &format!(r#"<svg x="{x:.1}" y="{y:.1}" width="{size:.1}" height="{size:.1}" "#),
+                            1,
+                        );
+                    writeln!(svg, "{qr}")?;
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+}
+
+/// A minimal PDF with one page of `page_size` points, filled by the `jpeg` image.
+#[must_use]
+pub fn pdf_with_jpeg(jpeg: &[u8], (width, height): (u32, u32), page_size: (f32, f32)) -> Vec<u8> {
+    let (page_width, page_height) = page_size;
+    let mut pdf: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::new();
+    let content = format!("q {page_width:.2} 0 0 {page_height:.2} 0 0 cm /Im0 Do Q");
+    let objects: [Vec<u8>; 5] = [
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_width:.2} {page_height:.2}] /Resources << /XObject << /Im0 4 0 R >> >> /Contents 5 0 R >>"
+        )
+        .into_bytes(),
+        [
+            format!(
+                "<< /Type /XObject /Subtype /Image /Width {width} /Height {height} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {length} >>\nstream\n",
+                length = jpeg.len()
+            )
+            .as_bytes(),
+            jpeg,
+            b"\nendstream",
+        ]
+        .concat(),
+        format!(
+            "<< /Length {length} >>\nstream\n{content}\nendstream",
+            length = content.len()
+        )
+        .into_bytes(),
+    ];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{id} 0 obj\n", id = i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref_offset = pdf.len();
+    let mut xref = format!(
+        "xref\n0 {count}\n0000000000 65535 f \n",
+        count = objects.len() + 1
+    );
+    for offset in offsets {
+        writeln!(xref, "{offset:010} 00000 n ").expect("writing to a String is infallible");
+    }
+    write!(
+        xref,
+        "trailer\n<< /Size {count} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+        count = objects.len() + 1
+    )
+    .expect("writing to a String is infallible");
+    pdf.extend_from_slice(xref.as_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        clippy::indexing_slicing,
+        clippy::panic,
+        reason = "tests assert via unwrap and slice known-shape bytes"
+    )]
+    use super::*;
+
+    fn sign() -> DoorSign {
+        DoorSign {
+            code: "5602.EG.001".to_string(),
+            name: "MI HS 1, Friedrich L. Bauer Hörsaal".to_string(),
+            building: Some("Mathematik / Informatik".to_string()),
+            url: "https://nav.tum.de/room/5602.EG.001".to_string(),
+            bookings: Some(SignBookings {
+                heading: "Today".to_string(),
+                lines: (0..100)
+                    .map(|i| format!("{i:02}:00–{i:02}:45 Lecture"))
+                    .collect(),
+            }),
+        }
+    }
+
+    #[test]
+    fn long_text_is_wrapped_and_truncated() {
+        let lines = wrap(&"word ".repeat(100), 50.0, false, 400.0, 2);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with('…'));
+        assert!(
+            lines
+                .iter()
+                .all(|l| text_size(PxScale::from(50.0), font(false), l).0 <= 400)
+        );
+    }
+
+    #[test]
+    fn bookings_do_not_overlap_the_qr_code() {
+        for paper in [Paper::A4, Paper::A5] {
+            let layout = sign().layout(paper);
+            let Some(Element::Qr { y: qr_top, .. }) = layout.last().cloned() else {
+                panic!("the qr code is drawn last");
+            };
+            let last_booking = layout
+                .iter()
+                .filter_map(|e| match e {
+                    Element::Text { y, size, text, .. }
+                        if text.contains("Lecture") || text == "…" =>
+                    {
+                        Some(y + size)
+                    }
+                    _ => None,
+                })
+                .fold(0.0_f32, f32::max);
+            assert!(
+                last_booking < qr_top,
+                "{paper:?}: {last_booking} >= {qr_top}"
+            );
+        }
+    }
+
+    #[test]
+    fn formats_are_valid() {
+        let png = sign().render_png(Paper::A5).unwrap();
+        assert_eq!(&png[0..8], &[137, 80, 78, 71, 13, 10, 26, 10]);
+
+        let pdf = sign().render_pdf(Paper::A5).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        let svg = sign().render_svg(Paper::A4).unwrap();
+        assert!(svg.contains(r#"width="210mm" height="297mm""#));
+        assert!(svg.contains(">5602.EG.001</text>"));
+        assert_eq!(
+            svg.matches("<svg").count(),
+            2,
+            "the QR code is nested as vector"
+        );
+    }
+}
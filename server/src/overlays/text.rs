@@ -10,6 +10,7 @@ use imageproc::drawing::{draw_text_mut, text_size};
 use std::fmt;
 use std::fmt::Formatter;
 use std::sync::LazyLock;
+use unicode_truncate::UnicodeTruncateStr as _;
 
 pub static CANTARELL_BOLD: LazyLock<FontArc> = LazyLock::new(|| {
     FontArc::try_from_slice(include_bytes!("font/Cantarell-Bold.ttf"))
@@ -26,6 +27,8 @@ pub struct OverlayText {
     y: i32,
     text: String,
     font: &'static FontArc,
+    scale: PxScale,
+    color: Rgba<u8>,
 }
 
 #[expect(
@@ -38,6 +41,8 @@ impl fmt::Debug for OverlayText {
             .field("x", &self.x)
             .field("y", &self.y)
             .field("text", &self.text)
+            .field("scale", &self.scale)
+            .field("color", &self.color)
             .finish()
     }
 }
@@ -50,8 +55,38 @@ impl OverlayText {
             y: 0,
             text: text.to_string(),
             font,
+            scale: SCALE,
+            color: Rgba::black(),
         }
     }
+    #[must_use]
+    pub fn size(self, size: f32) -> Self {
+        Self {
+            scale: PxScale::from(size),
+            ..self
+        }
+    }
+    #[must_use]
+    pub fn color(self, color: Rgba<u8>) -> Self {
+        Self { color, ..self }
+    }
+    /// Shortens the text with `...` until it is at most `max_width` pixels wide
+    #[must_use]
+    pub fn truncated_to(self, max_width: u32) -> Self {
+        let fits = |text: &str| text_size(self.scale, self.font, text).0 <= max_width;
+        if fits(&self.text) {
+            return self;
+        }
+        let mut chars = self.text.chars().count();
+        let text = loop {
+            chars = chars.saturating_sub(1);
+            let candidate = format!("{}...", self.text.unicode_truncate(chars).0);
+            if chars == 0 || fits(&candidate) {
+                break candidate;
+            }
+        };
+        Self { text, ..self }
+    }
     /// x and y are in pixels from bottom right corner
     #[must_use]
     pub fn at(self, x: i32, y: i32) -> Self {
@@ -60,13 +95,13 @@ impl OverlayText {
 
     #[tracing::instrument(skip(img))]
     pub fn draw_onto(self, img: &mut image::RgbaImage) {
-        let (w, _) = text_size(SCALE, self.font, &self.text);
+        let (w, _) = text_size(self.scale, self.font, &self.text);
         draw_text_mut(
             img,
-            Rgba::black(),
+            self.color,
             img.width() as i32 - w as i32 - self.x,
             img.height() as i32 - self.y,
-            SCALE,
+            self.scale,
             self.font,
             &self.text,
         );
@@ -1,24 +1,34 @@
 use crate::db::calendar::Event;
+use crate::db::calendar_changes::{self, CHANGES_RETENTION};
+use crate::external::calendar_source::{CalendarSource, FixtureCalendar};
 use crate::external::connectum::APIRequestor;
 use crate::limited::vec::LimitedVec;
+use chrono::{DateTime, Utc};
 use futures::StreamExt as _;
 use futures::stream::FuturesUnordered;
-use prometheus::{IntCounterVec, IntGaugeVec, Opts, Registry};
+use prometheus::{IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry};
 use serde::{Deserialize, Serialize, Serializer as _};
 use sqlx::PgPool;
 use std::env;
 use std::fmt::{self, Debug, Formatter};
 use std::time::Duration;
 use tokio::time::sleep;
-use tracing::{debug, error};
+use tracing::{debug, error, info};
+
+use schedule::{Outcome, ScrapeHistory};
+
+mod schedule;
 
 const NUMBER_OF_CONCURRENT_SCRAPES: usize = 3;
 
 /// How often the freshness gauge is recomputed; a cheap aggregate over `de`.
 const FRESHNESS_RECOMPUTE_INTERVAL: Duration = Duration::from_mins(1);
 
+/// How often changes older than [`CHANGES_RETENTION`] are deleted
+const CHANGES_PRUNE_INTERVAL: Duration = Duration::from_hours(24);
+
 /// Outcome of a single calendar scrape, used as the bounded `result` label.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 enum ScrapeResult {
     Success,
     FetchError,
@@ -44,6 +54,7 @@ impl ScrapeResult {
 pub struct CalendarMetrics {
     scrape_total: IntCounterVec,
     rooms_by_freshness: IntGaugeVec,
+    queue_depth: IntGauge,
 }
 
 impl CalendarMetrics {
@@ -62,11 +73,17 @@ impl CalendarMetrics {
             ),
             &["bucket"],
         )?;
+        let queue_depth = IntGauge::new(
+            "navigatum_api_calendar_queue_depth",
+            "Scrapeable rooms which are due to be scraped.",
+        )?;
         registry.register(Box::new(scrape_total.clone()))?;
         registry.register(Box::new(rooms_by_freshness.clone()))?;
+        registry.register(Box::new(queue_depth.clone()))?;
         Ok(Self {
             scrape_total,
             rooms_by_freshness,
+            queue_depth,
         })
     }
 
@@ -76,12 +93,14 @@ impl CalendarMetrics {
 }
 
 /// Distribution of scrapeable rooms over freshness buckets, mirroring the
-/// scheduler's 60-minute staleness threshold in [`entries_which_need_scraping`].
+/// scheduler's default 60-minute interval in [`entries_which_need_scraping`].
 struct FreshnessBuckets {
     under_60m: i64,
     within_24h: i64,
     over_24h: i64,
     never: i64,
+    /// rooms which are due to be scraped, see [`entries_which_need_scraping`]
+    due: i64,
 }
 
 #[tracing::instrument(skip(pool))]
@@ -93,8 +112,13 @@ SELECT
     COUNT(*) FILTER (WHERE last_calendar_scrape_at <  DATE_SUBTRACT(NOW(), '60 minutes'::INTERVAL, 'Europe/Berlin')
                       AND  last_calendar_scrape_at >= DATE_SUBTRACT(NOW(), '24 hours'::INTERVAL, 'Europe/Berlin'))     AS "within_24h!",
     COUNT(*) FILTER (WHERE last_calendar_scrape_at <  DATE_SUBTRACT(NOW(), '24 hours'::INTERVAL, 'Europe/Berlin'))     AS "over_24h!",
-    COUNT(*) FILTER (WHERE last_calendar_scrape_at IS NULL)                                                            AS "never!"
-FROM de
+    COUNT(*) FILTER (WHERE last_calendar_scrape_at IS NULL)                                                            AS "never!",
+    COUNT(*) FILTER (WHERE COALESCE(s.next_due_at <= NOW(),
+                                    last_calendar_scrape_at < DATE_SUBTRACT(NOW(), '60 minutes'::INTERVAL, 'Europe/Berlin'),
+                                    TRUE))                                                                             AS "due!"
+FROM locations l
+JOIN location_data d ON d.key = l.key AND d.lang = 'de'
+LEFT JOIN calendar_scrape_state s ON s.key = l.key
 WHERE calendar_url IS NOT NULL"#
     )
     .fetch_one(pool)
@@ -104,10 +128,11 @@ WHERE calendar_url IS NOT NULL"#
         within_24h: row.within_24h,
         over_24h: row.over_24h,
         never: row.never,
+        due: row.due,
     })
 }
 
-/// Recompute the freshness gauge on a timer until cancelled; `over_24h` and
+/// Recompute the freshness and queue depth gauges on a timer until cancelled; `over_24h` and
 /// `never` are the scraper-falling-behind / frozen-rooms signal.
 #[tracing::instrument(skip(pool, metrics))]
 pub async fn record_freshness(pool: &PgPool, metrics: CalendarMetrics) {
@@ -119,6 +144,7 @@ pub async fn record_freshness(pool: &PgPool, metrics: CalendarMetrics) {
                 gauge.with_label_values(&["1h_24h"]).set(b.within_24h);
                 gauge.with_label_values(&["over_24h"]).set(b.over_24h);
                 gauge.with_label_values(&["never"]).set(b.never);
+                metrics.queue_depth.set(b.due);
             }
             Err(e) => error!(error = ?e, "could not recompute calendar freshness metrics"),
         }
@@ -126,9 +152,24 @@ pub async fn record_freshness(pool: &PgPool, metrics: CalendarMetrics) {
     }
 }
 
-#[derive(Serialize, Deserialize, sqlx::Type)]
+/// Deletes old calendar changes on a timer until cancelled, as they would otherwise grow without limit
+#[tracing::instrument(skip(pool))]
+pub async fn prune_changes(pool: &PgPool) {
+    loop {
+        match calendar_changes::delete_detected_before(pool, Utc::now() - CHANGES_RETENTION).await {
+            Ok(deleted) => debug!(deleted, "pruned old calendar changes"),
+            Err(e) => error!(error = ?e, "could not prune old calendar changes"),
+        }
+        sleep(CHANGES_PRUNE_INTERVAL).await;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 struct LocationKey {
     key: String,
+    last_calendar_scrape_at: Option<DateTime<Utc>>,
+    consecutive_failures: i32,
+    unchanged_scrapes: i32,
 }
 
 impl Debug for LocationKey {
@@ -140,18 +181,26 @@ impl Debug for LocationKey {
 #[tracing::instrument(skip(pool))]
 async fn entries_which_need_scraping(pool: &PgPool) -> anyhow::Result<LimitedVec<LocationKey>> {
     let res = sqlx::query_as!(LocationKey,r#"
-WITH ENTRIES_TO_SCRAPE AS (SELECT KEY,
+WITH ENTRIES_TO_SCRAPE AS (SELECT L.KEY,
+                                  LAST_CALENDAR_SCRAPE_AT,
+                                  COALESCE(S.CONSECUTIVE_FAILURES, 0)                                    AS consecutive_failures,
+                                  COALESCE(S.UNCHANGED_SCRAPES, 0)                                       AS unchanged_scrapes,
                                   CASE WHEN last_calendar_scrape_at IS NULL THEN 100 ELSE 1 END          AS boost_if_never_scraped,
                                   CAST(data -> 'ranking_factors' ->> 'rank_combined' AS INTEGER)         AS rank_combined,
-                                  (LAST_CALENDAR_SCRAPE_AT < DATE_SUBTRACT(NOW(), '60 minutes'::INTERVAL, 'Europe/Berlin')
-                                      OR LAST_CALENDAR_SCRAPE_AT IS NULL)                                AS would_need_scraping,
+                                  -- rooms without a scrape state (yet) are scraped hourly
+                                  COALESCE(S.NEXT_DUE_AT <= NOW(),
+                                           LAST_CALENDAR_SCRAPE_AT < DATE_SUBTRACT(NOW(), '60 minutes'::INTERVAL, 'Europe/Berlin'),
+                                           TRUE)                                                         AS would_need_scraping,
                                   EXTRACT(EPOCH FROM (NOW() - LAST_CALENDAR_SCRAPE_AT))                  AS seconds_ago,
                                   CALENDAR_URL IS NOT NULL                                               AS can_be_scraped
-                           FROM de)
+                           FROM locations L
+                           JOIN location_data D ON D.KEY = L.KEY AND D.LANG = 'de'
+                           LEFT JOIN calendar_scrape_state S ON S.KEY = L.KEY)
 
-SELECT key
+SELECT key, last_calendar_scrape_at, consecutive_failures AS "consecutive_failures!", unchanged_scrapes AS "unchanged_scrapes!"
 FROM entries_to_scrape
 WHERE would_need_scraping AND can_be_scraped
+-- the schedule (see `schedule.rs`) decides when a room is due, e.g. backing off after failures
 -- boost_if_never_scraped: has this ever been scraped? => give a good bonus
 -- rank_combined: "how important is this room?" (range 1..1k)
 -- seconds_ago: "how long since we last scraped it?" (range null,30*60/3=600..)
@@ -169,7 +218,7 @@ fn can_never_succeed() -> bool {
     };
     if client_id_invalid {
         error!(
-            "cannot get environment variable CONNECTUM_OAUTH_CLIENT_ID, necessary to refresh all calendars"
+            "cannot get environment variable CONNECTUM_OAUTH_CLIENT_ID, necessary to refresh all calendars (or set CALENDAR_FIXTURES_DIR)"
         );
         return true;
     }
@@ -179,20 +228,40 @@ fn can_never_succeed() -> bool {
     };
     if client_secret_invalid {
         error!(
-            "cannot get environment variable CONNECTUM_OAUTH_CLIENT_SECRET, necessary to refresh all calendars"
+            "cannot get environment variable CONNECTUM_OAUTH_CLIENT_SECRET, necessary to refresh all calendars (or set CALENDAR_FIXTURES_DIR)"
         );
         return true;
     }
     false
 }
 
+/// Scrapes the calendars of all rooms continuously.
+///
+/// If `CALENDAR_FIXTURES_DIR` is set, the calendars are read from the files there instead of `TUMonline`.
 #[tracing::instrument(skip(pool, metrics))]
 pub async fn all_entries(pool: &PgPool, metrics: CalendarMetrics) {
-    if can_never_succeed() {
-        return;
+    match env::var("CALENDAR_FIXTURES_DIR") {
+        Ok(dir) if !dir.trim().is_empty() => {
+            info!(
+                dir,
+                "scraping the calendars from fixtures instead of TUMonline"
+            );
+            scrape_continuously(pool, &metrics, FixtureCalendar::new(dir.trim())).await;
+        }
+        _ => {
+            if can_never_succeed() {
+                return;
+            }
+            scrape_continuously(pool, &metrics, APIRequestor::default()).await;
+        }
     }
+}
 
-    let api = APIRequestor::default();
+async fn scrape_continuously(
+    pool: &PgPool,
+    metrics: &CalendarMetrics,
+    source: impl CalendarSource,
+) {
     loop {
         let ids = match entries_which_need_scraping(pool).await {
             Ok(ids) => ids,
@@ -209,14 +278,14 @@ pub async fn all_entries(pool: &PgPool, metrics: CalendarMetrics) {
             sleep(Duration::from_mins(1)).await;
         }
 
-        refresh_events(pool, &api, &metrics, ids).await;
+        refresh_events(pool, &source, metrics, ids).await;
     }
 }
 
-#[tracing::instrument(skip(api, pool, metrics))]
+#[tracing::instrument(skip(source, pool, metrics))]
 async fn refresh_events(
     pool: &PgPool,
-    api: &APIRequestor,
+    source: &impl CalendarSource,
     metrics: &CalendarMetrics,
     mut ids: LimitedVec<LocationKey>,
 ) {
@@ -227,31 +296,38 @@ async fn refresh_events(
     let mut work_queue = FuturesUnordered::new();
     for _ in 0..NUMBER_OF_CONCURRENT_SCRAPES {
         if let Some(id) = ids.pop() {
-            work_queue.push(refresh_single(pool, api.clone(), metrics, id.key));
+            work_queue.push(refresh_single(pool, source.clone(), metrics, id));
         }
     }
 
     while work_queue.next().await.is_some() {
         if let Some(id) = ids.pop() {
-            work_queue.push(refresh_single(pool, api.clone(), metrics, id.key));
+            work_queue.push(refresh_single(pool, source.clone(), metrics, id));
         }
     }
 }
 
-#[tracing::instrument(skip(pool, api, metrics))]
+#[tracing::instrument(skip(pool, source, metrics))]
 async fn refresh_single(
     pool: &PgPool,
-    mut api: APIRequestor,
+    mut source: impl CalendarSource,
     metrics: &CalendarMetrics,
-    id: String,
+    location: LocationKey,
 ) -> anyhow::Result<()> {
-    let sync_start = chrono::Utc::now();
+    let id = location.key;
+    // all events of a room scraped for the first time would count as added
+    let track_changes = location.last_calendar_scrape_at.is_some();
+    let history = ScrapeHistory {
+        consecutive_failures: location.consecutive_failures,
+        unchanged_scrapes: location.unchanged_scrapes,
+    };
+    let sync_start = Utc::now();
     if let Err(e) = Event::update_last_calendar_scrape_at(pool, &id, &sync_start).await {
         error!(error = ?e, "could not update last_calendar_scrape_at");
         return Err(e.into());
     }
 
-    let events = match api.list_events(&id).await {
+    let events = match source.list_events(&id).await {
         Ok(events) => {
             debug!(
                 id,
@@ -262,16 +338,17 @@ async fn refresh_single(
         }
         Err(e) => {
             // TODO: this measure is to temporarily make the log usefully again until CO accepts my fix
-            if e.to_string() == *"error decoding response body" {
-                metrics.record_scrape(ScrapeResult::DecodeError);
+            let result = if e.to_string() == *"error decoding response body" {
                 debug!(
                     error = "https://gitlab.campusonline.community/tum/connectum/-/issues/118",
                     "Cannot download calendar"
                 );
+                ScrapeResult::DecodeError
             } else {
-                metrics.record_scrape(ScrapeResult::FetchError);
                 error!(error = ?e, "Could not download calendar");
-            }
+                ScrapeResult::FetchError
+            };
+            record_outcome(pool, metrics, &id, history, Outcome::Failed(result)).await;
             return Err(e);
         }
     };
@@ -284,14 +361,38 @@ async fn refresh_single(
         })
         .map(Event::from)
         .collect::<LimitedVec<_>>();
-    if let Err(e) = Event::store_all(pool, events, &id).await {
-        metrics.record_scrape(ScrapeResult::StoreError);
-        return Err(e);
-    }
-    metrics.record_scrape(ScrapeResult::Success);
+    let has_upcoming_events =
+        Outcome::has_upcoming(events.0.iter().map(|e| e.start_at), sync_start);
+    let changes = match Event::store_all(pool, events, &id, track_changes).await {
+        Ok(changes) => changes,
+        Err(e) => {
+            let outcome = Outcome::Failed(ScrapeResult::StoreError);
+            record_outcome(pool, metrics, &id, history, outcome).await;
+            return Err(e);
+        }
+    };
+    let outcome = Outcome::Succeeded {
+        changed: !track_changes || changes > 0,
+        has_upcoming_events,
+    };
+    record_outcome(pool, metrics, &id, history, outcome).await;
     Ok(())
 }
 
+/// Counts the `outcome` and schedules the next scrape of `id` accordingly
+async fn record_outcome(
+    pool: &PgPool,
+    metrics: &CalendarMetrics,
+    id: &str,
+    history: ScrapeHistory,
+    outcome: Outcome,
+) {
+    metrics.record_scrape(outcome.result());
+    if let Err(e) = schedule::record(pool, id, history, outcome).await {
+        error!(error = ?e, "could not schedule the next scrape");
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(
@@ -299,10 +400,20 @@ mod tests {
         reason = "tests assert via unwrap on fixtures and infallible metric setup"
     )]
     use super::*;
-    use crate::setup::tests::PostgresTestContainer;
+    use crate::refresh::lectures::refresh_once;
+    use crate::setup::tests::{MeiliSearchTestContainer, PostgresTestContainer};
     use chrono::{DateTime, Duration as TimeDelta, Utc};
+    use meilisearch_sdk::documents::DocumentsQuery;
+    use std::fs;
+
+    /// The parts of a lecture document the tests check
+    #[derive(Deserialize)]
+    struct Lecture {
+        title_de: String,
+        next_occurrence_at: DateTime<Utc>,
+    }
 
-    /// Minimal `de` row payload; `de.calendar_url` is generated from `props.calendar_url`,
+    /// Minimal `location_data` payload; `calendar_url` is generated from `props.calendar_url`,
     /// so a missing `calendar_url` makes the room unscrapeable.
     fn room_data(key: &str, calendar_url: Option<&str>) -> serde_json::Value {
         let mut props = serde_json::Map::new();
@@ -324,13 +435,20 @@ mod tests {
         calendar_url: Option<&str>,
         scraped_at: Option<DateTime<Utc>>,
     ) {
-        sqlx::query("INSERT INTO de(key, data, last_calendar_scrape_at) VALUES ($1, $2, $3)")
+        sqlx::query("INSERT INTO locations(key, last_calendar_scrape_at) VALUES ($1, $2)")
             .bind(key)
-            .bind(room_data(key, calendar_url))
             .bind(scraped_at)
             .execute(pool)
             .await
             .unwrap();
+        sqlx::query(
+            "INSERT INTO location_data(key, lang, data) VALUES ($1, 'de', $2), ($1, 'en', $2)",
+        )
+        .bind(key)
+        .bind(room_data(key, calendar_url))
+        .execute(pool)
+        .await
+        .unwrap();
     }
 
     #[tokio::test]
@@ -357,12 +475,183 @@ mod tests {
         insert_room(&pg.pool, "never.scraped", url, None).await;
         // unscrapeable (no calendar_url) => excluded from every bucket.
         insert_room(&pg.pool, "no.url", None, Some(now - TimeDelta::hours(30))).await;
+        // backing off => not due, even though it was not scraped for 3h
+        sqlx::query(
+            "INSERT INTO calendar_scrape_state(key, consecutive_failures, last_result, last_attempt_at, next_due_at) VALUES ('mid.3h', 5, 'decode_error', $1, $2)",
+        )
+        .bind(now - TimeDelta::hours(3))
+        .bind(now + TimeDelta::hours(1))
+        .execute(&pg.pool)
+        .await
+        .unwrap();
 
         let b = freshness_buckets(&pg.pool).await.unwrap();
         assert_eq!(b.under_60m, 2);
         assert_eq!(b.within_24h, 1);
         assert_eq!(b.over_24h, 1);
         assert_eq!(b.never, 1);
+        assert_eq!(b.due, 2);
+    }
+
+    fn write_fixture(dir: &tempfile::TempDir, key: &str, start_at: DateTime<Utc>) {
+        let events = serde_json::json!([{
+            "id": 1,
+            "room_code": key,
+            "start_at": start_at,
+            "end_at": start_at + TimeDelta::hours(2),
+            "title_de": "Analysis 1",
+            "title_en": "Analysis 1",
+            "stp_type": "Vorlesung",
+            "entry_type": "lecture",
+            "detailed_entry_type": "Abhaltung",
+        }]);
+        fs::write(dir.path().join(format!("{key}.json")), events.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn scraping_fixtures_stores_events_and_changes() {
+        let pg = PostgresTestContainer::new().await;
+        let ms = MeiliSearchTestContainer::new().await;
+        let key = "5602.EG.001";
+        let url = Some("https://campus.tum.de/x");
+        insert_room(&pg.pool, key, url, None).await;
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = FixtureCalendar::new(dir.path());
+        let metrics = CalendarMetrics::new(&Registry::new()).unwrap();
+        let start_at = DateTime::parse_from_rfc3339("2039-04-15T08:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        let due = entries_which_need_scraping(&pg.pool).await.unwrap();
+        assert_eq!(due.len(), 1);
+        write_fixture(&dir, key, start_at);
+        for location in due.0 {
+            refresh_single(&pg.pool, source.clone(), &metrics, location)
+                .await
+                .unwrap();
+        }
+        // scheduled for later => not due
+        assert_eq!(
+            entries_which_need_scraping(&pg.pool).await.unwrap().len(),
+            0
+        );
+
+        // the lecture is moved by an hour
+        write_fixture(&dir, key, start_at + TimeDelta::hours(1));
+        let location = LocationKey {
+            key: key.to_string(),
+            last_calendar_scrape_at: Some(Utc::now()),
+            consecutive_failures: 0,
+            unchanged_scrapes: 3,
+        };
+        refresh_single(&pg.pool, source, &metrics, location)
+            .await
+            .unwrap();
+
+        let stored: DateTime<Utc> =
+            sqlx::query_scalar("SELECT start_at FROM calendar WHERE id = 1")
+                .fetch_one(&pg.pool)
+                .await
+                .unwrap();
+        assert_eq!(stored, start_at + TimeDelta::hours(1));
+        let changes: Vec<String> = sqlx::query_scalar("SELECT kind FROM calendar_changes")
+            .fetch_all(&pg.pool)
+            .await
+            .unwrap();
+        assert_eq!(changes, ["rescheduled"]);
+        let unchanged: i32 = sqlx::query_scalar(
+            "SELECT unchanged_scrapes FROM calendar_scrape_state WHERE key = $1",
+        )
+        .bind(key)
+        .fetch_one(&pg.pool)
+        .await
+        .unwrap();
+        assert_eq!(unchanged, 0);
+        let successes = metrics.scrape_total.with_label_values(&["success"]).get();
+        assert_eq!(successes, 2);
+
+        // the scraped events are searchable as a lecture
+        refresh_once(&pg.pool, &ms.client).await.unwrap();
+        let lectures = DocumentsQuery::new(&ms.client.index("entries"))
+            .with_filter("facet = \"lecture\"")
+            .execute::<Lecture>()
+            .await
+            .unwrap()
+            .results;
+        let lectures = lectures
+            .into_iter()
+            .map(|l| (l.title_de, l.next_occurrence_at))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lectures,
+            [("Analysis 1".to_string(), start_at + TimeDelta::hours(1))]
+        );
+    }
+
+    #[tokio::test]
+    async fn moves_are_not_recorded_as_removals_if_the_new_room_is_scraped_first() {
+        let pg = PostgresTestContainer::new().await;
+        let (old, new) = ("5602.EG.001", "5602.EG.002");
+        let url = Some("https://campus.tum.de/x");
+        for key in [old, new] {
+            insert_room(&pg.pool, key, url, None).await;
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = FixtureCalendar::new(dir.path());
+        let metrics = CalendarMetrics::new(&Registry::new()).unwrap();
+        let start_at = DateTime::parse_from_rfc3339("2039-04-15T08:00:00Z")
+            .unwrap()
+            .to_utc();
+        let location = |key: &str| LocationKey {
+            key: key.to_string(),
+            last_calendar_scrape_at: Some(Utc::now()),
+            consecutive_failures: 0,
+            unchanged_scrapes: 0,
+        };
+        let no_events =
+            |key: &str| fs::write(dir.path().join(format!("{key}.json")), "[]").unwrap();
+        write_fixture(&dir, old, start_at);
+        no_events(new);
+        for key in [old, new] {
+            let first_scrape = LocationKey {
+                last_calendar_scrape_at: None,
+                ..location(key)
+            };
+            refresh_single(&pg.pool, source.clone(), &metrics, first_scrape)
+                .await
+                .unwrap();
+        }
+
+        // the lecture moves to the new room
+        no_events(old);
+        write_fixture(&dir, new, start_at);
+        // the old room is scraped concurrently, its changes are detected before the new room is stored …
+        let mut tx = pg.pool.begin().await.unwrap();
+        let changes = calendar_changes::detect(&mut tx, old, &[]).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        refresh_single(&pg.pool, source.clone(), &metrics, location(new))
+            .await
+            .unwrap();
+        // … but stored afterwards
+        calendar_changes::store(&mut tx, old, &changes)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        // and scraped once more
+        refresh_single(&pg.pool, source, &metrics, location(old))
+            .await
+            .unwrap();
+
+        let changes: Vec<String> = sqlx::query_scalar("SELECT kind FROM calendar_changes")
+            .fetch_all(&pg.pool)
+            .await
+            .unwrap();
+        assert_eq!(changes, ["moved"]);
+        let room: String = sqlx::query_scalar("SELECT room_code FROM calendar WHERE id = 1")
+            .fetch_one(&pg.pool)
+            .await
+            .unwrap();
+        assert_eq!(room, new);
     }
 
     #[test]
@@ -392,6 +681,7 @@ mod tests {
             .rooms_by_freshness
             .with_label_values(&["never"])
             .set(3);
+        metrics.queue_depth.set(7);
 
         let mut buf = Vec::new();
         TextEncoder::new()
@@ -401,5 +691,6 @@ mod tests {
 
         assert!(text.contains(r#"navigatum_api_calendar_scrape_total{result="success"} 1"#));
         assert!(text.contains(r#"navigatum_api_calendar_rooms_by_freshness{bucket="never"} 3"#));
+        assert!(text.contains("navigatum_api_calendar_queue_depth 7"));
     }
 }
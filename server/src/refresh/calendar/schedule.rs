@@ -0,0 +1,209 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::PgPool;
+use sqlx::postgres::PgQueryResult;
+
+use super::ScrapeResult;
+
+/// How often a room is scraped by default
+const DEFAULT_INTERVAL: TimeDelta = TimeDelta::minutes(60);
+/// How often a room with events in the [`UPCOMING_WINDOW`] is scraped, to catch last-minute changes
+const UPCOMING_INTERVAL: TimeDelta = TimeDelta::minutes(30);
+const UPCOMING_WINDOW: TimeDelta = TimeDelta::hours(24);
+/// After this many scrapes without changes, the interval doubles
+const UNCHANGED_SCRAPES_PER_DOUBLING: i32 = 6;
+/// Delay after the first failure, doubling with each further failure
+const FAILURE_BACKOFF: TimeDelta = TimeDelta::minutes(15);
+/// Upper bound for both growing intervals, so that every room is scraped at least daily
+const MAX_INTERVAL: TimeDelta = TimeDelta::hours(24);
+
+/// What is known about a room from its previous scrapes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct ScrapeHistory {
+    pub(super) consecutive_failures: i32,
+    pub(super) unchanged_scrapes: i32,
+}
+
+/// The result of scraping a room
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Outcome {
+    Failed(ScrapeResult),
+    Succeeded {
+        /// whether the events differ from the previous scrape
+        changed: bool,
+        /// whether an event starts within the [`UPCOMING_WINDOW`]
+        has_upcoming_events: bool,
+    },
+}
+
+impl Outcome {
+    /// Whether any of `starts` is within the [`UPCOMING_WINDOW`]
+    pub(super) fn has_upcoming(
+        starts: impl IntoIterator<Item = DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        starts
+            .into_iter()
+            .any(|start_at| start_at >= now && start_at - now <= UPCOMING_WINDOW)
+    }
+    pub(super) fn result(self) -> ScrapeResult {
+        match self {
+            Self::Failed(result) => result,
+            Self::Succeeded { .. } => ScrapeResult::Success,
+        }
+    }
+}
+
+/// `base` doubled `exponent` times, at most [`MAX_INTERVAL`]
+fn doubled(base: TimeDelta, exponent: i32) -> TimeDelta {
+    u32::try_from(exponent)
+        .ok()
+        .and_then(|exponent| 2_i32.checked_pow(exponent))
+        .and_then(|factor| base.checked_mul(factor))
+        .map_or(MAX_INTERVAL, |interval| interval.min(MAX_INTERVAL))
+}
+
+impl ScrapeHistory {
+    /// The history after the `outcome`, and when the room is due to be scraped next
+    pub(super) fn after(self, outcome: Outcome, now: DateTime<Utc>) -> (Self, DateTime<Utc>) {
+        match outcome {
+            Outcome::Failed(_) => {
+                let history = Self {
+                    consecutive_failures: self.consecutive_failures.saturating_add(1),
+                    ..self
+                };
+                let delay = doubled(FAILURE_BACKOFF, history.consecutive_failures - 1);
+                (history, now + delay)
+            }
+            Outcome::Succeeded {
+                changed,
+                has_upcoming_events,
+            } => {
+                let history = Self {
+                    consecutive_failures: 0,
+                    unchanged_scrapes: if changed {
+                        0
+                    } else {
+                        self.unchanged_scrapes.saturating_add(1)
+                    },
+                };
+                let delay = if has_upcoming_events {
+                    UPCOMING_INTERVAL
+                } else {
+                    doubled(
+                        DEFAULT_INTERVAL,
+                        history.unchanged_scrapes / UNCHANGED_SCRAPES_PER_DOUBLING,
+                    )
+                };
+                (history, now + delay)
+            }
+        }
+    }
+}
+
+/// Records the `outcome` of scraping `key` and schedules its next scrape
+#[tracing::instrument(skip(pool))]
+pub(super) async fn record(
+    pool: &PgPool,
+    key: &str,
+    history: ScrapeHistory,
+    outcome: Outcome,
+) -> Result<PgQueryResult, sqlx::Error> {
+    let now = Utc::now();
+    let (history, next_due_at) = history.after(outcome, now);
+    let changed_at = matches!(outcome, Outcome::Succeeded { changed: true, .. }).then_some(now);
+    let succeeded_at = matches!(outcome, Outcome::Succeeded { .. }).then_some(now);
+    sqlx::query!(
+        r#"INSERT INTO calendar_scrape_state (key,consecutive_failures,unchanged_scrapes,last_result,last_attempt_at,last_change_at,next_due_at,last_success_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (key) DO UPDATE SET
+         consecutive_failures = EXCLUDED.consecutive_failures,
+         unchanged_scrapes = EXCLUDED.unchanged_scrapes,
+         last_result = EXCLUDED.last_result,
+         last_attempt_at = EXCLUDED.last_attempt_at,
+         last_change_at = COALESCE(EXCLUDED.last_change_at, calendar_scrape_state.last_change_at),
+         next_due_at = EXCLUDED.next_due_at,
+         last_success_at = COALESCE(EXCLUDED.last_success_at, calendar_scrape_state.last_success_at)"#,
+        key,
+        history.consecutive_failures,
+        history.unchanged_scrapes,
+        outcome.result().label(),
+        now,
+        changed_at,
+        next_due_at,
+        succeeded_at,
+    )
+    .execute(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2039, 4, 15, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn failures_back_off_exponentially() {
+        let mut history = ScrapeHistory::default();
+        let mut delays = Vec::new();
+        for _ in 0..10 {
+            let (next, due_at) = history.after(Outcome::Failed(ScrapeResult::DecodeError), now());
+            delays.push((due_at - now()).num_minutes());
+            history = next;
+        }
+        assert_eq!(delays, [15, 30, 60, 120, 240, 480, 960, 1440, 1440, 1440]);
+
+        let success = Outcome::Succeeded {
+            changed: false,
+            has_upcoming_events: false,
+        };
+        let (history, due_at) = history.after(success, now());
+        assert_eq!(history.consecutive_failures, 0);
+        assert_eq!(due_at - now(), DEFAULT_INTERVAL);
+    }
+
+    #[test]
+    fn unchanged_rooms_are_scraped_less_often() {
+        let unchanged = Outcome::Succeeded {
+            changed: false,
+            has_upcoming_events: false,
+        };
+        let mut history = ScrapeHistory::default();
+        let mut delays = Vec::new();
+        for _ in 0..(UNCHANGED_SCRAPES_PER_DOUBLING * 8) {
+            let (next, due_at) = history.after(unchanged, now());
+            delays.push((due_at - now()).num_hours());
+            history = next;
+        }
+        delays.dedup();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 24]);
+
+        // upcoming events are scraped often, even if nothing changed for long
+        let upcoming = Outcome::Succeeded {
+            changed: false,
+            has_upcoming_events: true,
+        };
+        let (_, due_at) = history.after(upcoming, now());
+        assert_eq!(due_at - now(), UPCOMING_INTERVAL);
+
+        let changed = Outcome::Succeeded {
+            changed: true,
+            has_upcoming_events: false,
+        };
+        let (history, due_at) = history.after(changed, now());
+        assert_eq!(history.unchanged_scrapes, 0);
+        assert_eq!(due_at - now(), DEFAULT_INTERVAL);
+    }
+
+    #[test]
+    fn upcoming_events_are_within_a_day() {
+        let in_hours = |hours| now() + TimeDelta::hours(hours);
+        assert!(Outcome::has_upcoming([in_hours(-2), in_hours(3)], now()));
+        assert!(!Outcome::has_upcoming([in_hours(-2), in_hours(25)], now()));
+        assert!(!Outcome::has_upcoming([], now()));
+    }
+}
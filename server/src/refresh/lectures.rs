@@ -119,6 +119,8 @@ struct LectureGroup {
     next_occurrence_at: DateTime<Utc>,
     /// Distinct rooms hosting upcoming occurrences of this lecture.
     room_codes: Vec<String>,
+    /// Distinct `semesters.key`s the upcoming occurrences fall into.
+    semesters: Vec<String>,
     /// The next occurrences in chronological order, capped at whichever covers
     /// more events: the next 10 or those within a 14-day window (see
     /// [`aggregate_lectures`]).
@@ -145,21 +147,24 @@ async fn aggregate_lectures(pool: &PgPool) -> anyhow::Result<Vec<LectureGroup>>
         r#"
         WITH upcoming AS (
             SELECT
-                LOWER(title_de)        AS key_title_de,
-                LOWER(title_en)        AS key_title_en,
-                COALESCE(stp_type, '') AS key_stp_type,
-                title_de,
-                title_en,
-                stp_type,
-                start_at,
-                end_at,
-                room_code,
+                LOWER(c.title_de)        AS key_title_de,
+                LOWER(c.title_en)        AS key_title_en,
+                COALESCE(c.stp_type, '') AS key_stp_type,
+                c.title_de,
+                c.title_en,
+                c.stp_type,
+                c.start_at,
+                c.end_at,
+                c.room_code,
+                s.key                    AS semester,
                 ROW_NUMBER() OVER (
-                    PARTITION BY LOWER(title_de), LOWER(title_en), COALESCE(stp_type, '')
-                    ORDER BY start_at, room_code
+                    PARTITION BY LOWER(c.title_de), LOWER(c.title_en), COALESCE(c.stp_type, '')
+                    ORDER BY c.start_at, c.room_code
                 ) AS rn
-            FROM calendar
-            WHERE end_at >= NOW()
+            FROM calendar c
+            LEFT JOIN semesters s
+                ON (c.start_at AT TIME ZONE 'Europe/Berlin')::date BETWEEN s.start_date AND s.end_date
+            WHERE c.end_at >= NOW()
         )
         SELECT
             key_title_de                                  AS "key_title_de!",
@@ -170,6 +175,8 @@ async fn aggregate_lectures(pool: &PgPool) -> anyhow::Result<Vec<LectureGroup>>
             (ARRAY_AGG(stp_type ORDER BY start_at))[1]    AS "stp_type",
             MIN(start_at)                                 AS "next_occurrence_at!",
             ARRAY_AGG(DISTINCT room_code)                 AS "room_codes!",
+            COALESCE(ARRAY_AGG(DISTINCT semester) FILTER (WHERE semester IS NOT NULL), '{}')
+                                                          AS "semesters!",
             JSONB_AGG(
                 JSONB_BUILD_OBJECT('start_at', start_at, 'end_at', end_at, 'room_code', room_code)
                 ORDER BY rn
@@ -194,6 +201,7 @@ struct RoomContext {
     name: String,
     building_names: Vec<String>,
     keywords: Vec<String>,
+    campus: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -205,6 +213,8 @@ struct RoomContextDoc {
     parent_building_names: Vec<String>,
     #[serde(default)]
     parent_keywords: Vec<String>,
+    #[serde(default)]
+    campus: Option<String>,
 }
 
 /// Build a `room_code -> context` map from the geo room documents already in the
@@ -226,6 +236,7 @@ async fn fetch_room_context(client: &Client) -> anyhow::Result<HashMap<String, R
                 "name",
                 "parent_building_names",
                 "parent_keywords",
+                "campus",
             ])
             .with_limit(PAGE_SIZE)
             .with_offset(offset)
@@ -239,6 +250,7 @@ async fn fetch_room_context(client: &Client) -> anyhow::Result<HashMap<String, R
                     name: doc.name,
                     building_names: doc.parent_building_names,
                     keywords: doc.parent_keywords,
+                    campus: doc.campus,
                 },
             );
         }
@@ -298,6 +310,10 @@ async fn stale_lecture_ids(
 /// pipeline (`name`, `rank`, `parent_*`) so the shared index settings apply
 /// uniformly; the lecture-specific fields (`title_*`, `next_occurrence_at`) are
 /// additive.
+///
+/// There is no teaching organisation: the calendar entries do not name it and we have no other
+/// source for it (the organisation operating a room is a different one), so lectures cannot be
+/// filtered by organisation yet.
 #[derive(Serialize)]
 struct LectureDocument {
     ms_id: String,
@@ -311,6 +327,10 @@ struct LectureDocument {
     rank: i32,
     parent_building_names: Vec<String>,
     parent_keywords: Vec<String>,
+    /// Slugified campus names of the hosting rooms, so that `in=garching` matches.
+    campus: Vec<String>,
+    /// `semesters.key`s (e.g. `2026W`) the upcoming occurrences fall into.
+    semester: Vec<String>,
     next_occurrence_at: DateTime<Utc>,
     upcoming: Vec<UpcomingEvent>,
 }
@@ -331,6 +351,8 @@ impl LectureDocument {
             rank: 0,
             parent_building_names,
             parent_keywords,
+            campus: group.campus(room_context),
+            semester: group.semesters.clone(),
             next_occurrence_at: group.next_occurrence_at,
             upcoming: group.upcoming(room_context),
         }
@@ -385,6 +407,18 @@ impl LectureGroup {
         (building_names, keywords)
     }
 
+    /// Distinct campuses of the rooms hosting an upcoming occurrence, sorted.
+    fn campus(&self, room_context: &HashMap<String, RoomContext>) -> Vec<String> {
+        let mut campus = self
+            .room_codes
+            .iter()
+            .filter_map(|room_code| room_context.get(room_code)?.campus.clone())
+            .collect::<Vec<_>>();
+        campus.sort_unstable();
+        campus.dedup();
+        campus
+    }
+
     /// The capped, chronologically ordered occurrences with their rooms resolved
     /// to display names. A room missing from the index (e.g. an online-only
     /// slot) falls back to its code so the entry stays clickable.
@@ -417,6 +451,7 @@ mod test {
         reason = "tests assert via panic/unwrap"
     )]
     use super::*;
+    use crate::setup::tests::PostgresTestContainer;
 
     /// A group whose identity key is derived from the (case-folded) titles and
     /// `stp_type`, exactly as [`aggregate_lectures`] produces it. The display
@@ -431,6 +466,7 @@ mod test {
             stp_type: stp_type.map(str::to_string),
             next_occurrence_at: DateTime::from_timestamp(0, 0).unwrap(),
             room_codes: vec![],
+            semesters: vec![],
             upcoming: Json(vec![]),
         }
     }
@@ -470,6 +506,66 @@ mod test {
         );
     }
 
+    #[test]
+    fn campus_is_deduplicated_across_rooms() {
+        let mut lecture = group("Analysis 1", "Calculus 1", Some("Vorlesung"));
+        lecture.room_codes = ["5602.EG.001", "5510.02.001", "5602.EG.002", "online"]
+            .map(str::to_string)
+            .to_vec();
+        let context = |campus: &str| RoomContext {
+            campus: Some(campus.to_string()),
+            ..RoomContext::default()
+        };
+        let room_context = HashMap::from([
+            ("5602.EG.001".to_string(), context("garching")),
+            ("5602.EG.002".to_string(), context("garching")),
+            ("5510.02.001".to_string(), context("garching-hochbruck")),
+        ]);
+        assert_eq!(
+            lecture.campus(&room_context),
+            ["garching", "garching-hochbruck"]
+        );
+    }
+
+    #[tokio::test]
+    async fn semesters_are_aggregated() {
+        let pg = PostgresTestContainer::new().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO semesters (key, start_date, end_date)
+            VALUES ('2039S', '2039-04-01', '2039-09-30'),
+                   ('2039W', '2039-10-01', '2040-03-31');
+            INSERT INTO locations (key) VALUES ('5606.EG.011'), ('5501.01.001'), ('5502.01.001');
+            INSERT INTO location_data (key, lang, data)
+            SELECT key, lang, jsonb_build_object('name', key, 'type', 'room', 'type_common_name', 'Hörsaal',
+                                                 'coords', '{"lat": 48.0, "lon": 11.0, "source": "navigatum"}'::jsonb)
+            FROM (VALUES ('5606.EG.011'), ('5501.01.001'), ('5502.01.001')) rooms(key)
+            CROSS JOIN (VALUES ('de'), ('en')) languages(lang);
+            INSERT INTO calendar (id, room_code, start_at, end_at, title_de, title_en, stp_type, entry_type, detailed_entry_type)
+            VALUES (1, '5606.EG.011', '2039-05-02 08:00Z', '2039-05-02 10:00Z', 'Analysis 1', 'Calculus 1', 'Vorlesung', 'lecture', 'Abhaltung'),
+                   (2, '5501.01.001', '2039-10-10 08:00Z', '2039-10-10 10:00Z', 'Analysis 1', 'Calculus 1', 'Vorlesung', 'lecture', 'Abhaltung'),
+                   (3, '5502.01.001', '2039-05-03 08:00Z', '2039-05-03 10:00Z', 'Mechanik', 'Mechanics', 'Vorlesung', 'lecture', 'Abhaltung');
+            "#,
+        )
+        .execute(&pg.pool)
+        .await
+        .unwrap();
+
+        let mut groups = aggregate_lectures(&pg.pool).await.unwrap();
+        groups.sort_by(|a, b| a.title_de.cmp(&b.title_de));
+        let aggregated = groups
+            .iter()
+            .map(|g| (g.title_de.as_str(), g.semesters.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            aggregated,
+            [
+                ("Analysis 1", vec!["2039S".to_string(), "2039W".to_string()]),
+                ("Mechanik", vec!["2039S".to_string()]),
+            ]
+        );
+    }
+
     #[test]
     fn ms_id_delimiter_prevents_concatenation_collisions() {
         // Without the unit-separator delimiter, ("ab", "x") and ("a", "bx")
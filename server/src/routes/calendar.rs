@@ -1,11 +1,21 @@
-use actix_web::{HttpResponse, post, web};
+use actix_web::{HttpResponse, get, post, web};
+use base64::Engine as _;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing::error;
 
 use crate::db::calendar::{CalendarLocation, Event, LocationEvents};
+use crate::db::location::LocationKeyAlias;
+use crate::routes::HtmlQuery;
 use actix_web::http::header::{CacheControl, CacheDirective};
+use series::{SeriesResponse, group_series};
+
+pub mod changes;
+pub mod ics;
+pub mod quality;
+mod series;
 
 #[expect(
     unused_imports,
@@ -26,34 +36,54 @@ pub struct Arguments {
     /// The last allowed time the calendar would like to display
     #[schema(examples("2039-01-19T03:14:07+01:00", "2042-01-07T00:00:00 UTC"))]
     end_before: DateTime<Utc>,
+    /// How to group the entries of each location
+    #[serde(default)]
+    group: Grouping,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum Grouping {
+    /// Every entry is returned in `events`
+    #[default]
+    None,
+    /// Weekly recurring entries are returned once in `series`, the others in `events`
+    Series,
 }
 
+/// Ids which can be requested at once via `POST /api/calendar` and the iCalendar feeds
+const MAX_IDS: usize = 10;
+/// Ids which can be requested at once via `GET /api/calendar`, which is paginated
+const MAX_PAGINATED_IDS: usize = 100;
+
 impl Arguments {
     fn validate_ids(&self) -> Result<Vec<String>, HttpResponse> {
-        let ids = self
-            .ids
-            .clone()
-            .into_iter()
-            .map(|s| s.replace(|c: char| c.is_whitespace() || c.is_control(), ""))
-            .collect::<Vec<String>>();
-        if ids.iter().any(|id| id.is_empty() || id.len() > 255) {
-            return Err(HttpResponse::BadRequest()
-                .content_type("text/plain")
-                .body("one ID has an invalid length"));
-        }
+        validate_ids(&self.ids, MAX_IDS)
+    }
+}
 
-        if ids.len() > 10 {
-            return Err(HttpResponse::BadRequest()
-                .content_type("text/plain")
-                .body("Too many ids to query. We suspect that users don't need this. If you need this limit increased, please send us a message"));
-        }
-        if ids.is_empty() {
-            return Err(HttpResponse::BadRequest()
-                .content_type("text/plain")
-                .body("No id requested"));
-        }
-        Ok(ids)
+fn validate_ids(ids: &[String], max_ids: usize) -> Result<Vec<String>, HttpResponse> {
+    let ids = ids
+        .iter()
+        .map(|s| s.replace(|c: char| c.is_whitespace() || c.is_control(), ""))
+        .collect::<Vec<String>>();
+    if ids.iter().any(|id| id.is_empty() || id.len() > 255) {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("one ID has an invalid length"));
+    }
+
+    if ids.len() > max_ids {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Too many ids to query. We suspect that users don't need this. If you need this limit increased, please send us a message"));
+    }
+    if ids.is_empty() {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("No id requested"));
     }
+    Ok(ids)
 }
 
 /// Retrieve Calendar Entries
@@ -63,6 +93,10 @@ impl Arguments {
 /// Ensure to provide valid date-time formats for these parameters.
 ///
 /// If successful, returns additional entries in the requested time span.
+///
+/// With `group=series`, entries recurring weekly (same title, `stp_type`, weekday and local time) are returned once per series,
+/// described by an `RRULE` with `exdates` for missing weeks and `exceptions` for occurrences moved within their week.
+/// This shrinks the response for long time spans like a semester and can be used to import timetables.
 #[utoipa::path(
     tags=["calendar"],
     responses(
@@ -111,7 +145,7 @@ pub async fn calendar_handler(
     };
     let events = events
         .into_iter()
-        .map(|(id, events)| (id, LocationEventsResponse::from(events)))
+        .map(|(id, events)| (id, LocationEventsResponse::new(events, args.group)))
         .collect::<HashMap<_, _>>();
     HttpResponse::Ok()
         .insert_header(CacheControl(vec![
@@ -121,15 +155,264 @@ pub async fn calendar_handler(
         .json(events)
 }
 
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+pub struct CalendarQueryArgs {
+    /// ids of the rooms you want the calendars for, repeated (`?ids=5602.EG.001&ids=5602.EG.002`)
+    ///
+    /// Either `ids` or `building` has to be given.
+    #[serde(default)]
+    #[param(max_items = 100, example = json!(["5605.EG.011", "5510.02.001"]))]
+    ids: Vec<String>,
+    /// A building (or any other location containing rooms) you want the calendars of all its rooms for
+    #[param(example = "5602")]
+    building: Option<String>,
+    /// The first allowed time the calendar would like to display
+    #[param(example = "2039-01-19T03:14:07+01:00")]
+    start_after: DateTime<Utc>,
+    /// The last allowed time the calendar would like to display
+    #[param(example = "2039-01-26T03:14:07+01:00")]
+    end_before: DateTime<Utc>,
+    /// Maximum number of events per page
+    #[serde(default = "default_page_limit")]
+    #[param(minimum = 1, maximum = 1000, default = 100)]
+    limit: u16,
+    /// `next_cursor` of the previous page
+    cursor: Option<String>,
+}
+
+const fn default_page_limit() -> u16 {
+    100
+}
+
+/// Position after the last event of a page, as `(start_at, id)`
+fn encode_cursor(event: &Event) -> String {
+    let cursor = format!(
+        "{start_at}:{id}",
+        start_at = event.start_at.timestamp_micros(),
+        id = event.id
+    );
+    BASE64_URL_SAFE_NO_PAD.encode(cursor)
+}
+
+fn decode_cursor(cursor: &str) -> Option<(DateTime<Utc>, i32)> {
+    let cursor = String::from_utf8(BASE64_URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+    let (start_at, id) = cursor.split_once(':')?;
+    let start_at = DateTime::from_timestamp_micros(start_at.parse().ok()?)?;
+    Some((start_at, id.parse().ok()?))
+}
+
+/// Retrieve Calendar Entries (paginated)
+///
+/// Like `POST /api/calendar`, but cacheable and paginated:
+/// - up to 100 `ids`, or all rooms with a calendar within a `building`
+/// - events of all rooms are merged into one list, ordered by `start_at`
+/// - if there are more events, `next_cursor` is set and can be passed as `cursor` to get the next page
+///
+/// Rooms within a `building` whose calendar was not yet scraped are left out.
+#[utoipa::path(
+    tags=["calendar"],
+    params(CalendarQueryArgs),
+    responses(
+        (status = 200, description = "**A page of entries** in the requested time span", body = CalendarPageResponse, content_type = "application/json"),
+        (status = 400, description = "**Bad Request.** Make sure to request either `ids` or a `building` and that the `cursor` is from a previous page", body = String, content_type = "text/plain", example = "Request either ids or a building"),
+        (status = 404, description = "**Not found.** The requested location does not exist or does not have a calendar", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 503, description = "**Not Ready.** please retry later", body = String, content_type = "text/plain", example = "Waiting for first sync with TUMonline"),
+    )
+)]
+#[get("/api/calendar", wrap = "actix_middleware_etag::Etag::default()")]
+pub async fn calendar_page_handler(
+    HtmlQuery(args): HtmlQuery<CalendarQueryArgs>,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    if !(1..=1000).contains(&args.limit) {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("limit has to be between 1 and 1000");
+    }
+    let after = match args.cursor.as_deref().map(decode_cursor) {
+        None => None,
+        Some(Some(after)) => Some(after),
+        Some(None) => {
+            return HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body("Invalid cursor");
+        }
+    };
+    let locations = match (&args.building, args.ids.is_empty()) {
+        (Some(building), true) => match building_rooms(&data.pool, building).await {
+            Ok(locations) => locations,
+            Err(e) => return e,
+        },
+        (None, false) => {
+            let ids = match validate_ids(&args.ids, MAX_PAGINATED_IDS) {
+                Ok(ids) => ids,
+                Err(e) => return e,
+            };
+            let locations = match CalendarLocation::get_locations(&data.pool, &ids).await {
+                Ok(l) => l.0,
+                Err(e) => {
+                    error!(error = ?e, "could not refetch");
+                    return HttpResponse::InternalServerError()
+                        .content_type("text/plain")
+                        .body("could not get calendar entries, please try again later");
+                }
+            };
+            if let Err(e) = validate_locations(&ids, &locations) {
+                return e;
+            }
+            locations
+        }
+        _ => {
+            return HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body("Request either ids or a building");
+        }
+    };
+    let rooms = locations.iter().map(|l| l.key.clone()).collect::<Vec<_>>();
+    // one more than requested, to know whether there is a next page
+    let mut events = match Event::fetch_page(
+        &data.pool,
+        &rooms,
+        &args.start_after,
+        &args.end_before,
+        after,
+        i64::from(args.limit) + 1,
+    )
+    .await
+    {
+        Ok(events) => events,
+        Err(e) => {
+            error!(error = ?e, rooms = ?rooms, "could not get entries from the db");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not get calendar entries, please try again later");
+        }
+    };
+    let next_cursor = if events.len() > usize::from(args.limit) {
+        events.truncate(usize::from(args.limit));
+        events.last().map(encode_cursor)
+    } else {
+        None
+    };
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(60 * 60), // valid for 1h
+            CacheDirective::Public,
+        ]))
+        .json(CalendarPageResponse::new(locations, events, next_cursor))
+}
+
+/// The rooms with a scraped calendar within `building`, which may also be an alias (like `mi`)
+async fn building_rooms(
+    pool: &sqlx::PgPool,
+    building: &str,
+) -> Result<Vec<CalendarLocation>, HttpResponse> {
+    let building = building.replace(|c: char| c.is_whitespace() || c.is_control(), "");
+    if building.is_empty() || building.len() > 255 {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid ID"));
+    }
+    let building = match LocationKeyAlias::fetch_optional(pool, &building).await {
+        Ok(Some(alias)) => alias.key,
+        Ok(None) => building,
+        Err(e) => {
+            error!(error = ?e, building, "error requesting alias");
+            return Err(HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error"));
+        }
+    };
+    let rooms = match CalendarLocation::get_rooms_in(pool, &building).await {
+        Ok(rooms) => rooms.0,
+        Err(e) => {
+            error!(error = ?e, building, "could not get the rooms");
+            return Err(HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not get calendar entries, please try again later"));
+        }
+    };
+    if rooms.is_empty() {
+        return match LocationKeyAlias::fetch_all(pool, &building).await {
+            Ok(locations) if !locations.is_empty() => Err(HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body(format!("{building} does not contain rooms with a calendar"))),
+            Ok(_) => Err(HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found")),
+            Err(e) => {
+                error!(error = ?e, building, "error requesting alias");
+                Err(HttpResponse::InternalServerError()
+                    .content_type("text/plain")
+                    .body("Internal Server Error"))
+            }
+        };
+    }
+    Ok(rooms
+        .into_iter()
+        .filter(|room| room.last_calendar_scrape_at.is_some())
+        .collect())
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct CalendarPageResponse {
+    /// The locations of the `events` on this page, by their key
+    locations: HashMap<String, CalendarLocationResponse>,
+    /// Entries of all requested locations, ordered by `start_at`
+    events: Vec<EventResponse>,
+    /// Pass this as `cursor` to get the next page, `null` on the last page
+    #[schema(examples("MTMyNTM3NjAwMDAwMDAwMDoy"))]
+    next_cursor: Option<String>,
+}
+impl CalendarPageResponse {
+    fn new(
+        locations: Vec<CalendarLocation>,
+        events: Vec<Event>,
+        next_cursor: Option<String>,
+    ) -> Self {
+        let mut locations = locations
+            .into_iter()
+            .map(|l| (l.key.clone(), l))
+            .collect::<HashMap<_, _>>();
+        let mut referenced = HashMap::new();
+        for event in &events {
+            if let Some(location) = locations.remove(&event.room_code) {
+                referenced.insert(
+                    event.room_code.clone(),
+                    CalendarLocationResponse::from(location),
+                );
+            }
+        }
+        Self {
+            locations: referenced,
+            events: events.into_iter().map(EventResponse::from).collect(),
+            next_cursor,
+        }
+    }
+}
+
 #[derive(Serialize, utoipa::ToSchema)]
 struct LocationEventsResponse {
+    /// With `group=series`, only the entries which are not part of a series
     events: Vec<EventResponse>,
+    /// Weekly recurring entries, only present with `group=series`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    series: Option<Vec<SeriesResponse>>,
     location: CalendarLocationResponse,
 }
-impl From<LocationEvents> for LocationEventsResponse {
-    fn from(value: LocationEvents) -> Self {
+impl LocationEventsResponse {
+    fn new(value: LocationEvents, group: Grouping) -> Self {
+        let (events, series) = match group {
+            Grouping::None => (value.events.0, None),
+            Grouping::Series => {
+                let grouped = group_series(value.events.0);
+                let series = grouped.series.into_iter().map(SeriesResponse::from);
+                (grouped.events, Some(series.collect()))
+            }
+        };
         Self {
-            events: value.events.into_iter().map(EventResponse::from).collect(),
+            events: events.into_iter().map(EventResponse::from).collect(),
+            series,
             location: CalendarLocationResponse::from(value.location),
         }
     }
@@ -295,7 +578,8 @@ mod db_tests {
         clippy::panic_in_result_fn,
         clippy::absolute_paths,
         clippy::unreachable,
-        reason = "tests assert via panic/unwrap, reference absolute fixture paths, and use unreachable!() for exhaustive matches"
+        clippy::indexing_slicing,
+        reason = "tests assert via panic/unwrap, reference absolute fixture paths, index JSON by key, and use unreachable!() for exhaustive matches"
     )]
     use actix_web::App;
     use actix_web::http::header::ContentType;
@@ -409,24 +693,22 @@ mod db_tests {
         let mut tx = pool.begin().await.unwrap();
         let (locations, events) = sample_data();
         for (key, data) in locations {
-            for lang in ["de", "en"] {
-                let sql = match lang {
-                    "de" => {
-                        "INSERT INTO de(key,data,last_calendar_scrape_at) VALUES ($1, $2, $3::timestamptz)"
-                    }
-                    "en" => {
-                        "INSERT INTO en(key,data,last_calendar_scrape_at) VALUES ($1, $2, $3::timestamptz)"
-                    }
-                    _ => unreachable!(),
-                };
-                sqlx::query(sql)
-                    .bind(&key)
-                    .bind(&data)
-                    .bind(now_rfc3339)
-                    .execute(&mut *tx)
-                    .await
-                    .unwrap();
-            }
+            sqlx::query(
+                "INSERT INTO locations(key,last_calendar_scrape_at) VALUES ($1, $2::timestamptz)",
+            )
+            .bind(&key)
+            .bind(now_rfc3339)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO location_data(key,lang,data) VALUES ($1, 'de', $2), ($1, 'en', $2)",
+            )
+            .bind(&key)
+            .bind(&data)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
         }
 
         for event in events {
@@ -468,6 +750,7 @@ mod db_tests {
                 end_before: Utc::now(),
                 start_after: Utc::now(),
                 ids: vec![],
+                group: Grouping::None,
             };
             let req = test::TestRequest::post()
                 .uri("/api/calendar")
@@ -486,6 +769,7 @@ mod db_tests {
                 end_before: Utc::now(),
                 start_after: Utc::now(),
                 ids: (0..10_000).map(|i| i.to_string()).collect(),
+                group: Grouping::None,
             };
             let req = test::TestRequest::post()
                 .uri("/api/calendar")
@@ -504,6 +788,7 @@ mod db_tests {
                 end_before: Utc::now(),
                 start_after: Utc::now(),
                 ids: vec!["5121.EG.002".into()],
+                group: Grouping::None,
             };
             let req = test::TestRequest::post()
                 .uri("/api/calendar")
@@ -522,6 +807,7 @@ mod db_tests {
                 start_after: TIME_Y2K,
                 end_before: TIME_2020,
                 ids: vec!["5121.EG.003".into()],
+                group: Grouping::None,
             };
             let req = test::TestRequest::post()
                 .uri("/api/calendar")
@@ -540,6 +826,7 @@ mod db_tests {
                 start_after: TIME_2012,
                 end_before: TIME_2014,
                 ids: vec!["5121.EG.003".into(), "5121.EG.001".into()],
+                group: Grouping::None,
             };
             let req = test::TestRequest::post()
                 .uri("/api/calendar")
@@ -554,6 +841,94 @@ mod db_tests {
         }
     }
 
+    #[actix_web::test]
+    async fn test_paginated_get() {
+        let pg = PostgresTestContainer::new().await;
+        let now = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        load_sample_data(&pg.pool, &now).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppData::from(pg.pool.clone())))
+                .service(calendar_page_handler),
+        )
+        .await;
+        let page = |cursor: Option<String>| {
+            let cursor = cursor.map(|c| format!("&cursor={c}")).unwrap_or_default();
+            let uri = format!(
+                "/api/calendar?ids=5121.EG.003&ids=5121.EG.001&start_after={start}&end_before={end}&limit=2{cursor}",
+                start = TIME_Y2K.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                end = TIME_2020.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            );
+            test::TestRequest::get().uri(&uri).to_request()
+        };
+        let mut ids = Vec::new();
+        let mut cursor = None;
+        for _ in 0..5 {
+            let (_, resp) = test::call_service(&app, page(cursor)).await.into_parts();
+            let (status, actual) = run_testcase(resp).await;
+            assert_eq!(status, 200);
+            for event in actual["events"].as_array().unwrap() {
+                ids.push(event["id"].as_i64().unwrap());
+            }
+            cursor = actual["next_cursor"].as_str().map(ToString::to_string);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        // ordered by `start_at`, then `id`
+        assert_eq!(ids, [4, 5, 1, 2, 3]);
+    }
+
+    #[actix_web::test]
+    async fn test_building_get_resolves_aliases() {
+        let pg = PostgresTestContainer::new().await;
+        let now = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        load_sample_data(&pg.pool, &now).await;
+        let building = serde_json::json!({"id":"5121","type":"building","name":"Atlashalle","parents":["root","garching","physik","mll"]});
+        sqlx::query("INSERT INTO locations(key) VALUES ('5121')")
+            .execute(&pg.pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO location_data(key,lang,data) VALUES ('5121', 'de', $1), ('5121', 'en', $1)",
+        )
+        .bind(&building)
+        .execute(&pg.pool)
+        .await
+        .unwrap();
+        for room in ["5121.EG.001", "5121.EG.002", "5121.EG.003"] {
+            sqlx::query("INSERT INTO parents(key, id, name) VALUES ($1, '5121', 'Atlashalle')")
+                .bind(room)
+                .execute(&pg.pool)
+                .await
+                .unwrap();
+        }
+        sqlx::query("INSERT INTO aliases(alias, key, type, visible_id) VALUES ('atlashalle', '5121', 'building', '5121')")
+            .execute(&pg.pool)
+            .await
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppData::from(pg.pool.clone())))
+                .service(calendar_page_handler),
+        )
+        .await;
+        for (building, expected_status) in [("5121", 200), ("atlashalle", 200), ("unknown", 404)] {
+            let uri = format!(
+                "/api/calendar?building={building}&start_after={start}&end_before={end}",
+                start = TIME_Y2K.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                end = TIME_2020.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            );
+            let req = test::TestRequest::get().uri(&uri).to_request();
+            let (_, resp) = test::call_service(&app, req).await.into_parts();
+            let (status, actual) = run_testcase(resp).await;
+            assert_eq!(status, expected_status, "{building}: {actual}");
+            if expected_status == 200 {
+                assert_eq!(actual["events"].as_array().unwrap().len(), 5, "{building}");
+            }
+        }
+    }
+
     async fn run_testcase(resp: HttpResponse) -> (u16, Value) {
         let actual_status = resp.status().as_u16();
         let body_box = resp.into_body();
@@ -568,3 +943,29 @@ mod db_tests {
         (actual_status, body)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    #[test]
+    fn cursors_round_trip() {
+        let event = Event {
+            id: 42,
+            room_code: "5602.EG.001".to_string(),
+            start_at: Utc.with_ymd_and_hms(2026, 10, 19, 8, 15, 0).unwrap(),
+            end_at: Utc.with_ymd_and_hms(2026, 10, 19, 9, 45, 0).unwrap(),
+            title_de: "Analysis 1".to_string(),
+            title_en: "Analysis 1".to_string(),
+            stp_type: None,
+            entry_type: "lecture".to_string(),
+            detailed_entry_type: "Abhaltung".to_string(),
+        };
+        let cursor = encode_cursor(&event);
+        assert_eq!(decode_cursor(&cursor), Some((event.start_at, 42)));
+        assert_eq!(decode_cursor("not a cursor"), None);
+        assert_eq!(decode_cursor(&BASE64_URL_SAFE_NO_PAD.encode("1:x")), None);
+    }
+}
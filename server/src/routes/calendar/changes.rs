@@ -0,0 +1,472 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::{CacheControl, CacheDirective, VARY};
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use chrono_tz::Europe::Berlin;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::{EventTypeResponse, MAX_PAGINATED_IDS, building_rooms, validate_ids};
+use crate::db::calendar::CalendarLocation;
+use crate::db::calendar_changes::CalendarChange;
+use crate::localisation::{LangQueryArgs, LanguageOptions, LanguagePreferences};
+use crate::routes::HtmlQuery;
+use crate::xml;
+
+#[expect(
+    unused_imports,
+    reason = "has to be imported as otherwise utoipa generates incorrect code"
+)]
+use serde_json::json;
+
+/// Used if `since` is not given
+const DEFAULT_SINCE: TimeDelta = TimeDelta::days(7);
+
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+struct ChangesQueryArgs {
+    /// Only changes detected at or after this time. Defaults to 7 days ago.
+    ///
+    /// Changes are kept for 90 days.
+    #[param(example = "2039-01-19T03:14:07+01:00")]
+    since: Option<DateTime<Utc>>,
+    /// Only changes from or to these rooms, repeated (`?ids=5602.EG.001&ids=5602.EG.002`)
+    ///
+    /// At most one of `ids` or `building` can be given. Without either, changes of all rooms are returned.
+    #[serde(default)]
+    #[param(max_items = 100, example = json!(["5605.EG.011", "5510.02.001"]))]
+    ids: Vec<String>,
+    /// Only changes from or to the rooms within this building (or any other location containing rooms)
+    #[param(example = "5602")]
+    building: Option<String>,
+    /// Maximum number of changes
+    #[serde(default = "default_changes_limit")]
+    #[param(minimum = 1, maximum = 1000, default = 100)]
+    limit: u16,
+}
+
+const fn default_changes_limit() -> u16 {
+    100
+}
+
+/// Changes to the calendars
+///
+/// Lists lectures and other events which were added, cancelled (`removed`), `rescheduled` or `moved` to another room, newest first.
+/// Changes are detected when the calendar of a room is scraped from `TUMonline`, which happens roughly hourly.
+/// Only changes to events which had not yet ended are reported.
+///
+/// To follow the changes, poll with the `detected_at` of the newest known change as `since` and skip already known `id`s.
+/// The same changes are available as an Atom feed via `GET /api/calendar/changes.atom`.
+#[utoipa::path(
+    tags=["calendar"],
+    params(ChangesQueryArgs),
+    responses(
+        (status = 200, description = "**Changes** detected since `since`", body = ChangesResponse, content_type = "application/json"),
+        (status = 400, description = "**Bad Request.** Make sure to request at most one of `ids` or a `building`", body = String, content_type = "text/plain", example = "Request either ids or a building"),
+        (status = 404, description = "**Not found.** The requested building does not exist or does not have rooms with a calendar", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+    )
+)]
+#[get(
+    "/api/calendar/changes",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn changes_handler(
+    HtmlQuery(args): HtmlQuery<ChangesQueryArgs>,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let changes = match fetch_changes(&data.pool, &args).await {
+        Ok(changes) => changes,
+        Err(e) => return e,
+    };
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(10 * 60), // valid for 10min
+            CacheDirective::Public,
+        ]))
+        .json(ChangesResponse {
+            changes: changes.into_iter().map(ChangeResponse::from).collect(),
+        })
+}
+
+/// Changes to the calendars as an Atom feed
+///
+/// The same changes as `GET /api/calendar/changes` as an [Atom (RFC 4287)](https://www.rfc-editor.org/rfc/rfc4287) feed,
+/// which can be subscribed to in a feed reader.
+/// Subscribe to the rooms of your lectures (`ids`) or to a `building` to learn about cancellations and room changes.
+#[utoipa::path(
+    tags=["calendar"],
+    params(ChangesQueryArgs, LangQueryArgs),
+    responses(
+        (status = 200, description = "**Atom feed** of the changes", body = String, content_type = "application/atom+xml"),
+        (status = 400, description = "**Bad Request.** Make sure to request at most one of `ids` or a `building`", body = String, content_type = "text/plain", example = "Request either ids or a building"),
+        (status = 404, description = "**Not found.** The requested building does not exist or does not have rooms with a calendar", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+    )
+)]
+#[get(
+    "/api/calendar/changes.atom",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn changes_atom_handler(
+    req: HttpRequest,
+    HtmlQuery(args): HtmlQuery<ChangesQueryArgs>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let changes = match fetch_changes(&data.pool, &args).await {
+        Ok(changes) => changes,
+        Err(e) => return e,
+    };
+    let mut rooms = changes
+        .iter()
+        .flat_map(|c| [c.previous_room_code.clone(), c.room_code.clone()])
+        .flatten()
+        .collect::<Vec<_>>();
+    rooms.sort_unstable();
+    rooms.dedup();
+    let names = match CalendarLocation::get_locations(&data.pool, &rooms).await {
+        Ok(locations) => locations
+            .0
+            .into_iter()
+            .map(|l| (l.key, l.name))
+            .collect::<HashMap<_, _>>(),
+        Err(e) => {
+            error!(error = ?e, "could not get the names of the rooms");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not get calendar changes, please try again later");
+        }
+    };
+    let self_url = format!("https://nav.tum.de{uri}", uri = req.uri());
+    HttpResponse::Ok()
+        .content_type("application/atom+xml; charset=utf-8")
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(10 * 60), // valid for 10min
+            CacheDirective::Public,
+        ]))
+        .insert_header((VARY, "Accept-Language"))
+        .body(render_feed(
+            &changes,
+            &names,
+            languages.base(),
+            &self_url,
+            Utc::now(),
+        ))
+}
+
+async fn fetch_changes(
+    pool: &sqlx::PgPool,
+    args: &ChangesQueryArgs,
+) -> Result<Vec<CalendarChange>, HttpResponse> {
+    if !(1..=1000).contains(&args.limit) {
+        return Err(HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("limit has to be between 1 and 1000"));
+    }
+    let rooms = match (&args.building, args.ids.is_empty()) {
+        (None, true) => None,
+        (None, false) => Some(validate_ids(&args.ids, MAX_PAGINATED_IDS)?),
+        (Some(building), true) => Some(
+            building_rooms(pool, building)
+                .await?
+                .into_iter()
+                .map(|room| room.key)
+                .collect(),
+        ),
+        (Some(_), false) => {
+            return Err(HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body("Request either ids or a building"));
+        }
+    };
+    let since = args.since.unwrap_or_else(|| Utc::now() - DEFAULT_SINCE);
+    CalendarChange::fetch_since(pool, rooms.as_deref(), since, i64::from(args.limit))
+        .await
+        .map_err(|e| {
+            error!(error = ?e, "could not get calendar changes from the db");
+            HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not get calendar changes, please try again later")
+        })
+}
+
+/// Renders the `changes` as an Atom feed
+fn render_feed(
+    changes: &[CalendarChange],
+    names: &HashMap<String, String>,
+    lang: LanguageOptions,
+    self_url: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let title = match lang {
+        LanguageOptions::De => "NavigaTUM: Änderungen im Vorlesungsplan",
+        LanguageOptions::En => "NavigaTUM: Changes to the lecture schedule",
+    };
+    let updated = changes.first().map_or(now, |c| c.detected_at);
+    let mut feed = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="{lang}">
+  <id>{self_url}</id>
+  <title>{title}</title>
+  <updated>{updated}</updated>
+  <link rel="self" href="{self_url}"/>
+  <author><name>NavigaTUM</name></author>
+"#,
+        self_url = xml::escape(self_url),
+        updated = updated.to_rfc3339_opts(SecondsFormat::Secs, true),
+    );
+    for change in changes {
+        feed.push_str(&render_entry(change, names, lang));
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+fn render_entry(
+    change: &CalendarChange,
+    names: &HashMap<String, String>,
+    lang: LanguageOptions,
+) -> String {
+    let kind = ChangeKindResponse::from(change.kind.as_str());
+    let (title, label) = match lang {
+        LanguageOptions::De => (&change.title_de, kind.label_de()),
+        LanguageOptions::En => (&change.title_en, kind.label_en()),
+    };
+    let previous = placement(
+        change.previous_room_code.as_ref(),
+        change.previous_start_at,
+        change.previous_end_at,
+    );
+    let current = placement(change.room_code.as_ref(), change.start_at, change.end_at);
+    let (before, now) = match lang {
+        LanguageOptions::De => ("Bisher", "Jetzt"),
+        LanguageOptions::En => ("Before", "Now"),
+    };
+    let content = [(before, previous), (now, current)]
+        .into_iter()
+        .filter_map(|(prefix, placement)| {
+            let p = placement?;
+            Some(format!(
+                "{prefix}: {room}, {time}",
+                room = names.get(&p.room_code).unwrap_or(&p.room_code),
+                time = time_span(p.start_at, p.end_at, lang),
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let room = change
+        .room_code
+        .as_ref()
+        .or(change.previous_room_code.as_ref())
+        .map(String::as_str)
+        .unwrap_or_default();
+    format!(
+        r#"  <entry>
+    <id>tag:nav.tum.de,2026:calendar-change:{id}</id>
+    <title>{label}: {title}</title>
+    <updated>{updated}</updated>
+    <link href="https://nav.tum.de/view/{room}"/>
+    <category term="{kind}"/>
+    <content type="text">{content}</content>
+  </entry>
+"#,
+        id = change.id,
+        title = xml::escape(title),
+        updated = change
+            .detected_at
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        room = xml::escape(room),
+        kind = change.kind,
+        content = xml::escape(&content),
+    )
+}
+
+fn time_span(start_at: DateTime<Utc>, end_at: DateTime<Utc>, lang: LanguageOptions) -> String {
+    let start_at = start_at.with_timezone(&Berlin);
+    let end_at = end_at.with_timezone(&Berlin);
+    match lang {
+        LanguageOptions::De => format!(
+            "{} - {} Uhr",
+            start_at.format("%d.%m.%Y %H:%M"),
+            end_at.format("%H:%M")
+        ),
+        LanguageOptions::En => format!(
+            "{} - {}",
+            start_at.format("%Y-%m-%d %H:%M"),
+            end_at.format("%H:%M")
+        ),
+    }
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct ChangesResponse {
+    /// The changes, newest first
+    changes: Vec<ChangeResponse>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct ChangeResponse {
+    /// ID of the change
+    #[schema(examples(4711))]
+    id: i64,
+    /// ID of the calendar entry used in `TUMonline` internally
+    #[schema(examples(6424))]
+    event_id: i32,
+    kind: ChangeKindResponse,
+    /// When the change was detected
+    #[schema(examples("2039-01-19T03:14:07Z"))]
+    detected_at: DateTime<Utc>,
+    /// German title of the Entry
+    #[schema(examples("Quantenteleportation"))]
+    title_de: String,
+    /// English title of the Entry
+    #[schema(examples("Quantum teleportation"))]
+    title_en: String,
+    entry_type: EventTypeResponse,
+    /// Where and when the entry was before the change, `null` if it was `added`
+    previous: Option<PlacementResponse>,
+    /// Where and when the entry is after the change, `null` if it was `removed`
+    current: Option<PlacementResponse>,
+}
+
+impl From<CalendarChange> for ChangeResponse {
+    fn from(value: CalendarChange) -> Self {
+        Self {
+            id: value.id,
+            event_id: value.event_id,
+            kind: ChangeKindResponse::from(value.kind.as_str()),
+            detected_at: value.detected_at,
+            previous: placement(
+                value.previous_room_code.as_ref(),
+                value.previous_start_at,
+                value.previous_end_at,
+            ),
+            current: placement(value.room_code.as_ref(), value.start_at, value.end_at),
+            title_de: value.title_de,
+            title_en: value.title_en,
+            entry_type: EventTypeResponse::from(value.entry_type),
+        }
+    }
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct PlacementResponse {
+    /// Structured, globaly unique room code
+    #[schema(examples("5602.EG.001"))]
+    room_code: String,
+    /// start of the entry
+    #[schema(examples("2039-01-19T03:14:07Z"))]
+    start_at: DateTime<Utc>,
+    /// end of the entry
+    #[schema(examples("2039-01-19T04:44:07Z"))]
+    end_at: DateTime<Utc>,
+}
+
+fn placement(
+    room_code: Option<&String>,
+    start_at: Option<DateTime<Utc>>,
+    end_at: Option<DateTime<Utc>>,
+) -> Option<PlacementResponse> {
+    Some(PlacementResponse {
+        room_code: room_code?.clone(),
+        start_at: start_at?,
+        end_at: end_at?,
+    })
+}
+
+/// What happened to the calendar entry
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKindResponse {
+    /// The entry is new
+    Added,
+    /// The entry was cancelled
+    Removed,
+    /// The entry takes place at a different time in the same room
+    Rescheduled,
+    /// The entry takes place in a different room, possibly also at a different time
+    Moved,
+}
+
+impl From<&str> for ChangeKindResponse {
+    fn from(value: &str) -> Self {
+        // other values are prevented by a `CHECK` constraint
+        match value {
+            "removed" => Self::Removed,
+            "rescheduled" => Self::Rescheduled,
+            "moved" => Self::Moved,
+            _ => Self::Added,
+        }
+    }
+}
+
+impl ChangeKindResponse {
+    const fn label_de(self) -> &'static str {
+        match self {
+            Self::Added => "Neu",
+            Self::Removed => "Entfällt",
+            Self::Rescheduled => "Verschoben",
+            Self::Moved => "Raumänderung",
+        }
+    }
+    const fn label_en(self) -> &'static str {
+        match self {
+            Self::Added => "New",
+            Self::Removed => "Cancelled",
+            Self::Rescheduled => "Rescheduled",
+            Self::Moved => "Room change",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn change(
+        id: i64,
+        kind: &str,
+        previous: Option<&str>,
+        current: Option<&str>,
+    ) -> CalendarChange {
+        let start_at = Utc.with_ymd_and_hms(2026, 11, 2, 8, 15, 0).unwrap();
+        CalendarChange {
+            id,
+            event_id: 6424,
+            kind: kind.to_string(),
+            detected_at: Utc.with_ymd_and_hms(2026, 10, 30, 9, 0, 0).unwrap(),
+            title_de: "Analysis & Lineare Algebra".to_string(),
+            title_en: "Analysis & Linear Algebra".to_string(),
+            entry_type: "lecture".to_string(),
+            previous_room_code: previous.map(ToString::to_string),
+            previous_start_at: previous.map(|_| start_at),
+            previous_end_at: previous.map(|_| start_at + TimeDelta::minutes(90)),
+            room_code: current.map(ToString::to_string),
+            start_at: current.map(|_| start_at + TimeDelta::days(1)),
+            end_at: current.map(|_| start_at + TimeDelta::days(1) + TimeDelta::minutes(90)),
+        }
+    }
+
+    #[test]
+    fn feed_is_rendered() {
+        let changes = [
+            change(2, "moved", Some("5602.EG.001"), Some("5602.EG.002")),
+            change(1, "removed", Some("5602.EG.001"), None),
+        ];
+        let names = HashMap::from([(
+            "5602.EG.001".to_string(),
+            "5602.EG.001 (MI HS 1, Friedrich L. Bauer Hörsaal)".to_string(),
+        )]);
+        let feed = render_feed(
+            &changes,
+            &names,
+            LanguageOptions::De,
+            "https://nav.tum.de/api/calendar/changes.atom?building=5602&lang=de",
+            Utc::now(),
+        );
+        insta::assert_snapshot!(feed);
+    }
+}
@@ -0,0 +1,366 @@
+use actix_web::http::header::{CacheControl, CacheDirective, VARY};
+use actix_web::{HttpResponse, get, web};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Europe::Berlin;
+use serde::Deserialize;
+use tracing::error;
+
+use super::{MAX_IDS, validate_ids, validate_locations};
+use crate::db::calendar::{CalendarLocation, Event, LocationEvents};
+use crate::localisation::{LangQueryArgs, LanguageOptions, LanguagePreferences};
+use crate::routes::HtmlQuery;
+
+#[expect(
+    unused_imports,
+    reason = "has to be imported as otherwise utoipa generates incorrect code"
+)]
+use serde_json::json;
+
+/// How far into the past a feed reaches, so that recent bookings stay visible after subscribing
+const FEED_PAST: TimeDelta = TimeDelta::days(28);
+/// How far into the future a feed reaches, roughly one semester
+const FEED_FUTURE: TimeDelta = TimeDelta::days(183);
+/// Lines longer than this (in octets, without the line break) have to be folded, see RFC 5545, section 3.1
+const MAX_LINE_OCTETS: usize = 75;
+
+/// The rules of `Europe/Berlin` since 1996, which all events are expressed in
+const VTIMEZONE_BERLIN: &str = "BEGIN:VTIMEZONE\r
+TZID:Europe/Berlin\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+TZNAME:CEST\r
+DTSTART:19700329T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+TZNAME:CET\r
+DTSTART:19701025T030000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct IcsPathParams {
+    /// ID of the room
+    id: String,
+}
+
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+struct CombinedIcsQueryArgs {
+    /// ids you want the calendar for, repeated (`?ids=5602.EG.001&ids=5602.EG.002`)
+    ///
+    /// Limit of max. 10 ids, as for `POST /api/calendar`
+    #[param(min_items = 1, max_items = 10, example = json!(["5602.EG.001", "5602.EG.002"]))]
+    ids: Vec<String>,
+}
+
+/// Subscribe to the calendar of a room
+///
+/// Returns the bookings of the room as an [iCalendar (RFC 5545)](https://www.rfc-editor.org/rfc/rfc5545) feed,
+/// which can be subscribed to in Outlook, Thunderbird, Apple or Google Calendar.
+///
+/// The feed covers the past four weeks and the next six months.
+/// Events keep their `UID` across updates, so calendar apps update them in place.
+#[utoipa::path(
+    tags=["calendar"],
+    params(IcsPathParams, LangQueryArgs),
+    responses(
+        (status = 200, description = "**iCalendar feed** of the room", body = String, content_type = "text/calendar"),
+        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty, not longer than 255 characters and exists", body = String, content_type = "text/plain", example = "one ID has an invalid length"),
+        (status = 404, description = "**Not found.** The requested location does not have a calendar", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 503, description = "**Not Ready.** please retry later", body = String, content_type = "text/plain", example = "Waiting for first sync with TUMonline"),
+    )
+)]
+#[get(
+    "/api/calendar/{id}.ics",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn room_ics_handler(
+    params: web::Path<IcsPathParams>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    feed(&data, &[params.into_inner().id], languages.base()).await
+}
+
+/// Subscribe to the calendars of several rooms
+///
+/// Like `GET /api/calendar/{id}.ics`, but merges the bookings of up to 10 rooms into one feed.
+/// Useful for a lab or a chair, which books several rooms.
+#[utoipa::path(
+    tags=["calendar"],
+    params(CombinedIcsQueryArgs, LangQueryArgs),
+    responses(
+        (status = 200, description = "**iCalendar feed** of the rooms", body = String, content_type = "text/calendar"),
+        (status = 400, description = "**Bad request.** Make sure that between 1 and 10 ids are requested and that they exist", body = String, content_type = "text/plain", example = "No id requested"),
+        (status = 404, description = "**Not found.** One of the requested locations does not have a calendar", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 503, description = "**Not Ready.** please retry later", body = String, content_type = "text/plain", example = "Waiting for first sync with TUMonline"),
+    )
+)]
+#[get("/api/calendar.ics", wrap = "actix_middleware_etag::Etag::default()")]
+pub async fn combined_ics_handler(
+    HtmlQuery(args): HtmlQuery<CombinedIcsQueryArgs>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    feed(&data, &args.ids, languages.base()).await
+}
+
+async fn feed(data: &crate::AppData, ids: &[String], lang: LanguageOptions) -> HttpResponse {
+    let ids = match validate_ids(ids, MAX_IDS) {
+        Ok(ids) => ids,
+        Err(e) => return e,
+    };
+    let locations = match CalendarLocation::get_locations(&data.pool, &ids).await {
+        Ok(l) => l.0,
+        Err(e) => {
+            error!(error = ?e, "could not refetch");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not get calendar entries, please try again later");
+        }
+    };
+    if let Err(e) = validate_locations(&ids, &locations) {
+        return e;
+    }
+    let now = Utc::now();
+    let mut events = match LocationEvents::get_from_db(
+        &data.pool,
+        locations,
+        &(now - FEED_PAST),
+        &(now + FEED_FUTURE),
+    )
+    .await
+    {
+        Ok(events) => events.0,
+        Err(e) => {
+            error!(error = ?e, ids = ?ids, "could not get entries from the db");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not get calendar entries, please try again later");
+        }
+    };
+    // in the requested order, so that the feed is stable
+    let feed = ids
+        .iter()
+        .filter_map(|id| events.remove(id))
+        .collect::<Vec<_>>();
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(60 * 60), // valid for 1h
+            CacheDirective::Public,
+        ]))
+        .insert_header((VARY, "Accept-Language"))
+        .body(render_calendar(&feed, lang, now))
+}
+
+/// Renders the events of the `feed` as a `VCALENDAR`
+fn render_calendar(feed: &[LocationEvents], lang: LanguageOptions, now: DateTime<Utc>) -> String {
+    let mut ics = IcsWriter::default();
+    ics.line("BEGIN", "VCALENDAR");
+    ics.line("VERSION", "2.0");
+    ics.line("PRODID", "-//TUM-Dev//NavigaTUM//EN");
+    ics.line("CALSCALE", "GREGORIAN");
+    ics.line("METHOD", "PUBLISH");
+    let name = feed
+        .iter()
+        .map(|l| l.location.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    ics.text("X-WR-CALNAME", &name);
+    ics.line("X-WR-TIMEZONE", "Europe/Berlin");
+    // matches the `Cache-Control`, calendar apps otherwise refresh rarely (daily or weekly)
+    ics.line("REFRESH-INTERVAL;VALUE=DURATION", "PT1H");
+    ics.line("X-PUBLISHED-TTL", "PT1H");
+    ics.0.push_str(VTIMEZONE_BERLIN);
+    for LocationEvents { events, location } in feed {
+        let stamp = location.last_calendar_scrape_at.unwrap_or(now);
+        let mut events = events.as_ref().iter().collect::<Vec<_>>();
+        events.sort_by_key(|e| (e.start_at, e.id));
+        for event in events {
+            render_event(&mut ics, event, location, lang, stamp);
+        }
+    }
+    ics.line("END", "VCALENDAR");
+    ics.0
+}
+
+fn render_event(
+    ics: &mut IcsWriter,
+    event: &Event,
+    location: &CalendarLocation,
+    lang: LanguageOptions,
+    stamp: DateTime<Utc>,
+) {
+    ics.line("BEGIN", "VEVENT");
+    // TUMonline ids are unique across rooms and stable across scrapes
+    ics.line("UID", &format!("{id}@nav.tum.de", id = event.id));
+    ics.line("DTSTAMP", &utc_date_time(stamp));
+    ics.line(
+        "DTSTART;TZID=Europe/Berlin",
+        &local_date_time(event.start_at),
+    );
+    ics.line("DTEND;TZID=Europe/Berlin", &local_date_time(event.end_at));
+    ics.text("SUMMARY", &summary(event, lang));
+    ics.text("LOCATION", &location.name);
+    let description = [
+        event.stp_type.as_deref(),
+        Some(event.detailed_entry_type.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|d| !d.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n");
+    if !description.is_empty() {
+        ics.text("DESCRIPTION", &description);
+    }
+    ics.text("CATEGORIES", &event.entry_type);
+    ics.line(
+        "URL",
+        &format!("https://nav.tum.de/view/{key}", key = location.key),
+    );
+    ics.line("TRANSP", "OPAQUE");
+    ics.line("END", "VEVENT");
+}
+
+/// Both titles, the requested language first, or one if they are the same
+fn summary(event: &Event, lang: LanguageOptions) -> String {
+    let (first, second) = match lang {
+        LanguageOptions::De => (&event.title_de, &event.title_en),
+        LanguageOptions::En => (&event.title_en, &event.title_de),
+    };
+    if second.is_empty() || first == second {
+        first.clone()
+    } else if first.is_empty() {
+        second.clone()
+    } else {
+        format!("{first} / {second}")
+    }
+}
+
+fn utc_date_time(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn local_date_time(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Berlin)
+        .format("%Y%m%dT%H%M%S")
+        .to_string()
+}
+
+/// Writes content lines, terminated by `CRLF` and folded as required by RFC 5545
+#[derive(Default)]
+struct IcsWriter(String);
+
+impl IcsWriter {
+    /// A property whose value is written verbatim
+    fn line(&mut self, name: &str, value: &str) {
+        let line = format!("{name}:{value}");
+        let mut octets = 0;
+        for c in line.chars() {
+            if octets + c.len_utf8() > MAX_LINE_OCTETS {
+                self.0.push_str("\r\n ");
+                // the space of the continuation counts towards the limit
+                octets = 1;
+            }
+            self.0.push(c);
+            octets += c.len_utf8();
+        }
+        self.0.push_str("\r\n");
+    }
+    /// A property of type `TEXT`, which has to be escaped
+    fn text(&mut self, name: &str, value: &str) {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '\\' | ';' | ',' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                '\n' => escaped.push_str("\\n"),
+                '\r' => {}
+                c => escaped.push(c),
+            }
+        }
+        self.line(name, &escaped);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn location() -> CalendarLocation {
+        CalendarLocation {
+            key: "5602.EG.001".to_string(),
+            name: "5602.EG.001 (MI HS 1, Friedrich L. Bauer Hörsaal)".to_string(),
+            last_calendar_scrape_at: Some(Utc.with_ymd_and_hms(2026, 7, 1, 4, 0, 0).unwrap()),
+            calendar_url: Some("https://campus.tum.de/tumonline/tvKalender.wSicht".to_string()),
+            type_common_name: "Hörsaal".to_string(),
+            r#type: "room".to_string(),
+        }
+    }
+
+    fn event(id: i32, start_at: DateTime<Utc>, title_de: &str, title_en: &str) -> Event {
+        Event {
+            id,
+            room_code: "5602.EG.001".to_string(),
+            start_at,
+            end_at: start_at + TimeDelta::minutes(90),
+            title_de: title_de.to_string(),
+            title_en: title_en.to_string(),
+            stp_type: Some("Vorlesung mit Zentralübung".to_string()),
+            entry_type: "lecture".to_string(),
+            detailed_entry_type: "Abhaltung".to_string(),
+        }
+    }
+
+    #[test]
+    fn feed_is_rendered_in_berlin_time() {
+        let summer = Utc.with_ymd_and_hms(2026, 7, 2, 8, 15, 0).unwrap();
+        let winter = Utc.with_ymd_and_hms(2026, 11, 2, 8, 15, 0).unwrap();
+        let feed = [LocationEvents {
+            location: location(),
+            events: vec![
+                event(2, winter, "Analysis 1", "Analysis 1"),
+                event(
+                    1,
+                    summer,
+                    "Einführung in die Informatik",
+                    "Introduction to Informatics",
+                ),
+            ]
+            .into(),
+        }];
+        let ics = render_calendar(&feed, LanguageOptions::En, Utc::now());
+        insta::assert_snapshot!(ics.replace("\r\n", "\n"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+    }
+
+    #[test]
+    fn text_is_escaped_and_folded() {
+        let mut ics = IcsWriter::default();
+        ics.text("SUMMARY", "Übung; Tutorium, Raum\\Teil\nzwei");
+        assert_eq!(
+            ics.0,
+            "SUMMARY:Übung\\; Tutorium\\, Raum\\\\Teil\\nzwei\r\n"
+        );
+        let mut ics = IcsWriter::default();
+        ics.line("X", &"ä".repeat(50));
+        let lines = ics.0.split("\r\n").collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert_eq!(
+            ics.0.replace("\r\n ", ""),
+            format!("X:{}\r\n", "ä".repeat(50))
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use std::env;
+
+use actix_web::http::header::{
+    AUTHORIZATION, CacheControl, CacheDirective, ContentDisposition, DispositionParam,
+    DispositionType,
+};
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::db::calendar_quality::QualityIssue;
+
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+struct QualityQueryArgs {
+    /// Rooms with a calendar are reported as `stale` if they were not scraped successfully within this many days
+    #[serde(default = "default_stale_after_days")]
+    #[param(minimum = 1, maximum = 365, default = 7)]
+    stale_after_days: u16,
+    /// The format of the report
+    #[serde(default)]
+    #[param(inline)]
+    format: ReportFormat,
+}
+
+const fn default_stale_after_days() -> u16 {
+    7
+}
+
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum ReportFormat {
+    #[default]
+    Json,
+    /// One issue per row, e.g. for spreadsheets
+    Csv,
+}
+
+/// Data quality of the calendars
+///
+/// Reports issues with the scraped calendar data, grouped by building:
+/// - `overlap`: two entries booking the same room at the same time
+/// - `invalid_duration`: an entry ending before (or when) it starts
+/// - `unknown_room`: an entry in a room which we do not know
+/// - `stale`: a room with a calendar which was not scraped successfully within `stale_after_days`
+/// - `never_scraped`: a room with a calendar which the scraper has not tried yet
+///
+/// Only entries which have not yet ended are checked.
+/// With `format=csv`, the issues are returned as CSV instead, one issue per row.
+///
+/// The report is expensive to generate and only meant for maintainers.
+/// It requires the token configured on the server as `Authorization: Bearer <token>`.
+#[utoipa::path(
+    tags=["calendar"],
+    params(QualityQueryArgs),
+    responses(
+        (status = 200, description = "**Issues** with the calendar data", body = QualityReportResponse, content_type = "application/json"),
+        (status = 200, description = "**Issues** with the calendar data, if `format=csv`", body = String, content_type = "text/csv"),
+        (status = 400, description = "**Bad Request.** Make sure that `stale_after_days` is between 1 and 365", body = String, content_type = "text/plain", example = "stale_after_days has to be between 1 and 365"),
+        (status = 401, description = "**Unauthorized.** The `Authorization` header is missing or does not carry the configured token", body = String, content_type = "text/plain", example = "a valid token is required for the quality report"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+        (status = 503, description = "**Service unavailable.** No token is configured on this server, so the report is disabled", body = String, content_type = "text/plain"),
+    )
+)]
+#[get(
+    "/api/calendar/quality",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn quality_handler(
+    req: HttpRequest,
+    args: web::Query<QualityQueryArgs>,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    if let Some(rejection) = authorize(&req) {
+        return rejection;
+    }
+    if !(1..=365).contains(&args.stale_after_days) {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("stale_after_days has to be between 1 and 365");
+    }
+    let now = Utc::now();
+    let stale_since = now - TimeDelta::days(i64::from(args.stale_after_days));
+    let issues = match QualityIssue::fetch_all(&data.pool, stale_since).await {
+        Ok(issues) => issues,
+        Err(e) => {
+            error!(error = ?e, "could not get the calendar quality issues");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not get the calendar quality report, please try again later");
+        }
+    };
+    let mut response = HttpResponse::Ok();
+    // the report is only for whoever holds the token
+    response.insert_header(CacheControl(vec![
+        CacheDirective::Private,
+        CacheDirective::NoStore,
+    ]));
+    match args.format {
+        ReportFormat::Json => response.json(QualityReportResponse::new(
+            now,
+            args.stale_after_days,
+            issues,
+        )),
+        ReportFormat::Csv => match render_csv(&issues) {
+            Ok(csv) => response
+                .content_type("text/csv; charset=utf-8")
+                .insert_header(ContentDisposition {
+                    disposition: DispositionType::Attachment,
+                    parameters: vec![DispositionParam::Filename(
+                        "calendar-quality.csv".to_string(),
+                    )],
+                })
+                .body(csv),
+            Err(e) => {
+                error!(error = ?e, "could not render the calendar quality report");
+                HttpResponse::InternalServerError()
+                    .content_type("text/plain")
+                    .body("could not get the calendar quality report, please try again later")
+            }
+        },
+    }
+}
+
+/// Checks the bearer token against `CALENDAR_QUALITY_TOKEN`, returning the response to reject the request with
+fn authorize(req: &HttpRequest) -> Option<HttpResponse> {
+    let expected = env::var("CALENDAR_QUALITY_TOKEN").unwrap_or_default();
+    if expected.is_empty() {
+        return Some(
+            HttpResponse::ServiceUnavailable()
+                .content_type("text/plain")
+                .body("The calendar quality report is not configured on this server."),
+        );
+    }
+    let provided = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+    match provided {
+        Some(token) if tokens_match(token, &expected) => None,
+        _ => Some(
+            HttpResponse::Unauthorized()
+                .content_type("text/plain")
+                .body("a valid token is required for the quality report"),
+        ),
+    }
+}
+
+/// Compares without returning early, to not leak how much of the token was right
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn render_csv(issues: &[QualityIssue]) -> anyhow::Result<String> {
+    let format_time = |time: Option<DateTime<Utc>>| {
+        time.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .unwrap_or_default()
+    };
+    let format_id = |id: Option<i32>| id.map(|id| id.to_string()).unwrap_or_default();
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "building",
+        "room_code",
+        "kind",
+        "event_id",
+        "other_event_id",
+        "start_at",
+        "end_at",
+        "title",
+        "last_success_at",
+        "last_result",
+    ])?;
+    for issue in issues {
+        writer.write_record([
+            issue.building.as_str(),
+            &issue.room_code,
+            &issue.kind,
+            &format_id(issue.event_id),
+            &format_id(issue.other_event_id),
+            &format_time(issue.start_at),
+            &format_time(issue.end_at),
+            issue.title.as_deref().unwrap_or_default(),
+            &format_time(issue.last_success_at),
+            issue.last_result.as_deref().unwrap_or_default(),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct QualityReportResponse {
+    /// When the report was generated
+    #[schema(examples("2039-01-19T03:14:07Z"))]
+    generated_at: DateTime<Utc>,
+    /// Rooms not scraped successfully within this many days are `stale`
+    #[schema(examples(7))]
+    stale_after_days: u16,
+    summary: QualitySummaryResponse,
+    /// The buildings with issues, ordered by their id
+    buildings: Vec<BuildingIssuesResponse>,
+}
+
+impl QualityReportResponse {
+    fn new(generated_at: DateTime<Utc>, stale_after_days: u16, issues: Vec<QualityIssue>) -> Self {
+        let mut summary = QualitySummaryResponse::default();
+        let mut buildings: Vec<BuildingIssuesResponse> = Vec::new();
+        // the issues are ordered by building
+        for issue in issues {
+            match issue.kind.as_str() {
+                "overlap" => summary.overlaps += 1,
+                "invalid_duration" => summary.invalid_durations += 1,
+                "unknown_room" => summary.unknown_rooms += 1,
+                "never_scraped" => summary.never_scraped_rooms += 1,
+                _ => summary.stale_rooms += 1,
+            }
+            match buildings.last_mut() {
+                Some(building) if building.building == issue.building => {
+                    building.issues.push(IssueResponse::from(issue));
+                }
+                _ => buildings.push(BuildingIssuesResponse {
+                    building: issue.building.clone(),
+                    issues: vec![IssueResponse::from(issue)],
+                }),
+            }
+        }
+        Self {
+            generated_at,
+            stale_after_days,
+            summary,
+            buildings,
+        }
+    }
+}
+
+/// Number of issues per kind
+#[derive(Serialize, Default, Debug, PartialEq, Eq, utoipa::ToSchema)]
+struct QualitySummaryResponse {
+    #[schema(examples(3))]
+    overlaps: u32,
+    #[schema(examples(0))]
+    invalid_durations: u32,
+    #[schema(examples(1))]
+    unknown_rooms: u32,
+    #[schema(examples(12))]
+    stale_rooms: u32,
+    #[schema(examples(2))]
+    never_scraped_rooms: u32,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct BuildingIssuesResponse {
+    /// ID of the building.
+    /// For rooms which we do not know, the part of the room code before the first `.`
+    #[schema(examples("5602"))]
+    building: String,
+    issues: Vec<IssueResponse>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct IssueResponse {
+    /// Structured, globaly unique room code
+    #[schema(examples("5602.EG.001"))]
+    room_code: String,
+    kind: IssueKindResponse,
+    /// ID of the affected calendar entry, `null` for `stale` and `never_scraped` rooms
+    #[schema(examples(6424))]
+    event_id: Option<i32>,
+    /// ID of the calendar entry overlapping with `event_id`, only for `overlap`s
+    #[schema(examples(6425))]
+    other_event_id: Option<i32>,
+    /// start of the affected entry
+    #[schema(examples("2039-01-19T03:14:07Z"))]
+    start_at: Option<DateTime<Utc>>,
+    /// end of the affected entry
+    #[schema(examples("2039-01-19T04:44:07Z"))]
+    end_at: Option<DateTime<Utc>>,
+    /// German title of the affected entry
+    #[schema(examples("Quantenteleportation"))]
+    title: Option<String>,
+    /// Last successful scrape of a `stale` room, `null` if it never succeeded or for `never_scraped` rooms
+    #[schema(examples("2039-01-12T03:14:07Z"))]
+    last_success_at: Option<DateTime<Utc>>,
+    /// Result of the last scrape of a `stale` room
+    #[schema(examples("decode_error"))]
+    last_result: Option<String>,
+}
+
+impl From<QualityIssue> for IssueResponse {
+    fn from(value: QualityIssue) -> Self {
+        Self {
+            kind: IssueKindResponse::from(value.kind.as_str()),
+            room_code: value.room_code,
+            event_id: value.event_id,
+            other_event_id: value.other_event_id,
+            start_at: value.start_at,
+            end_at: value.end_at,
+            title: value.title,
+            last_success_at: value.last_success_at,
+            last_result: value.last_result,
+        }
+    }
+}
+
+/// What is wrong
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum IssueKindResponse {
+    /// The room is booked by `event_id` and `other_event_id` at the same time
+    Overlap,
+    /// The entry ends before (or when) it starts
+    InvalidDuration,
+    /// The entry is in a room which we do not know
+    UnknownRoom,
+    /// The calendar of the room was not scraped successfully within `stale_after_days`
+    Stale,
+    /// The calendar of the room was not scraped yet
+    NeverScraped,
+}
+
+impl From<&str> for IssueKindResponse {
+    fn from(value: &str) -> Self {
+        // the kinds are fixed by the query
+        match value {
+            "overlap" => Self::Overlap,
+            "invalid_duration" => Self::InvalidDuration,
+            "unknown_room" => Self::UnknownRoom,
+            "never_scraped" => Self::NeverScraped,
+            _ => Self::Stale,
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use chrono::TimeZone as _;
+
+    use super::*;
+
+    fn issue(building: &str, room_code: &str, kind: &str) -> QualityIssue {
+        let start_at = Utc.with_ymd_and_hms(2039, 4, 15, 8, 0, 0).unwrap();
+        let is_stale = kind == "stale" || kind == "never_scraped";
+        QualityIssue {
+            building: building.to_string(),
+            room_code: room_code.to_string(),
+            kind: kind.to_string(),
+            event_id: (!is_stale).then_some(6424),
+            other_event_id: (kind == "overlap").then_some(6425),
+            start_at: (!is_stale).then_some(start_at),
+            end_at: (!is_stale).then_some(start_at + TimeDelta::minutes(90)),
+            title: (!is_stale).then(|| "Analysis, Teil \"1\"".to_string()),
+            last_success_at: None,
+            last_result: (kind == "stale").then(|| "decode_error".to_string()),
+        }
+    }
+
+    fn issues() -> Vec<QualityIssue> {
+        vec![
+            issue("5602", "5602.EG.001", "overlap"),
+            issue("5602", "5602.EG.002", "stale"),
+            issue("5602", "5602.EG.003", "never_scraped"),
+            issue("9999", "9999.01.001", "unknown_room"),
+        ]
+    }
+
+    #[test]
+    fn issues_are_grouped_by_building() {
+        let now = Utc.with_ymd_and_hms(2039, 4, 14, 12, 0, 0).unwrap();
+        let report = QualityReportResponse::new(now, 7, issues());
+        let buildings = report
+            .buildings
+            .iter()
+            .map(|b| (b.building.as_str(), b.issues.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(buildings, [("5602", 3), ("9999", 1)]);
+        assert_eq!(
+            report.summary,
+            QualitySummaryResponse {
+                overlaps: 1,
+                invalid_durations: 0,
+                unknown_rooms: 1,
+                stale_rooms: 1,
+                never_scraped_rooms: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn tokens_have_to_match_exactly() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[test]
+    fn issues_are_exported_as_csv() {
+        insta::assert_snapshot!(render_csv(&issues()).unwrap());
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::iter;
+
+use chrono::{
+    DateTime, Datelike as _, NaiveDate, NaiveTime, TimeDelta, TimeZone as _, Utc, Weekday,
+};
+use chrono_tz::Europe::Berlin;
+use serde::Serialize;
+
+use super::{EventResponse, EventTypeResponse};
+use crate::db::calendar::Event;
+
+#[expect(
+    unused_imports,
+    reason = "has to be imported as otherwise utoipa generates incorrect code"
+)]
+use serde_json::json;
+
+/// Events recurring less often are not considered to be a series
+const MIN_OCCURRENCES: usize = 2;
+/// Events recurring more than this many weeks apart are not considered to be a series
+const MAX_INTERVAL_WEEKS: i64 = 4;
+
+/// Events recurring weekly at the same local time in the same room
+#[derive(Debug)]
+pub(super) struct Series {
+    /// The first occurrence
+    first: Event,
+    /// The later occurrences, ordered by `start_at`
+    later: Vec<Event>,
+    interval_weeks: i64,
+    /// Starts of the occurrences which are expected, but missing
+    exdates: Vec<DateTime<Utc>>,
+    /// Events replacing a missing occurrence, e.g. because they were moved to another day of the same week
+    exceptions: Vec<(DateTime<Utc>, Event)>,
+}
+
+/// Events split into [`Series`] and the events not belonging to any
+#[derive(Debug, Default)]
+pub(super) struct Grouped {
+    pub(super) series: Vec<Series>,
+    pub(super) events: Vec<Event>,
+}
+
+/// What makes events part of the same series.
+///
+/// Times are local, so that a series keeps its time across daylight saving time changes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SeriesKey {
+    room_code: String,
+    title_de: String,
+    stp_type: Option<String>,
+    weekday: u32,
+    start: NaiveTime,
+    duration: TimeDelta,
+}
+
+impl From<&Event> for SeriesKey {
+    fn from(event: &Event) -> Self {
+        let start_at = event.start_at.with_timezone(&Berlin);
+        Self {
+            room_code: event.room_code.clone(),
+            title_de: event.title_de.clone(),
+            stp_type: event.stp_type.clone(),
+            weekday: start_at.weekday().num_days_from_monday(),
+            start: start_at.time(),
+            duration: event.end_at - event.start_at,
+        }
+    }
+}
+
+fn local_date(time: DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&Berlin).date_naive()
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Detects recurring series within the `events` of one or more rooms
+pub(super) fn group_series(mut events: Vec<Event>) -> Grouped {
+    events.sort_by_key(|event| (event.start_at, event.id));
+    let mut candidates: HashMap<SeriesKey, Vec<Event>> = HashMap::new();
+    for event in events {
+        candidates
+            .entry(SeriesKey::from(&event))
+            .or_default()
+            .push(event);
+    }
+    let mut grouped = Grouped::default();
+    for (key, events) in candidates {
+        match Series::new(&key, events) {
+            Ok(series) => grouped.series.push(series),
+            Err(events) => grouped.events.extend(events),
+        }
+    }
+    grouped
+        .events
+        .sort_by_key(|event| (event.start_at, event.id));
+    grouped
+        .series
+        .sort_by_key(|series| (series.first.start_at, series.first.id));
+
+    let mut singles = Vec::with_capacity(grouped.events.len());
+    for event in grouped.events {
+        let series = grouped
+            .series
+            .iter_mut()
+            .find(|series| series.replaced_by(&event).is_some());
+        match series {
+            Some(series) => series.add_exception(event),
+            None => singles.push(event),
+        }
+    }
+    grouped.events = singles;
+    grouped
+}
+
+impl Series {
+    /// A series of the `events` sharing the `key`, ordered by `start_at`.
+    ///
+    /// Returns the `events` if they do not recur regularly.
+    fn new(key: &SeriesKey, events: Vec<Event>) -> Result<Self, Vec<Event>> {
+        if events.len() < MIN_OCCURRENCES {
+            return Err(events);
+        }
+        let first_date = events
+            .first()
+            .map_or(NaiveDate::MIN, |e| local_date(e.start_at));
+        let mut weeks = events
+            .iter()
+            .map(|event| (local_date(event.start_at) - first_date).num_weeks())
+            .collect::<Vec<_>>();
+        weeks.dedup();
+        if weeks.len() != events.len() {
+            // multiple occurrences on the same day are not a weekly series
+            return Err(events);
+        }
+        let interval_weeks = weeks.windows(2).fold(0, |interval, pair| match pair {
+            [previous, next] => gcd(next - previous, interval),
+            _ => interval,
+        });
+        if !(1..=MAX_INTERVAL_WEEKS).contains(&interval_weeks) {
+            return Err(events);
+        }
+        let last_week = weeks.last().copied().unwrap_or_default();
+        let exdates = (0..=last_week)
+            .step_by(usize::try_from(interval_weeks).unwrap_or(1))
+            .filter(|week| !weeks.contains(week))
+            .filter_map(|week| {
+                let date = first_date + TimeDelta::weeks(week);
+                Berlin
+                    .from_local_datetime(&date.and_time(key.start))
+                    .earliest()
+                    .map(|start_at| start_at.with_timezone(&Utc))
+            })
+            .collect();
+        let mut events = events.into_iter();
+        let Some(first) = events.next() else {
+            return Err(Vec::new());
+        };
+        Ok(Self {
+            first,
+            later: events.collect(),
+            interval_weeks,
+            exdates,
+            exceptions: Vec::new(),
+        })
+    }
+
+    /// The missing occurrence the `event` replaces, if it is the same event in the same week
+    fn replaced_by(&self, event: &Event) -> Option<DateTime<Utc>> {
+        let first = &self.first;
+        if first.room_code != event.room_code
+            || first.title_de != event.title_de
+            || first.stp_type != event.stp_type
+        {
+            return None;
+        }
+        let week = local_date(event.start_at).iso_week();
+        self.exdates
+            .iter()
+            .copied()
+            .find(|exdate| local_date(*exdate).iso_week() == week)
+    }
+
+    fn add_exception(&mut self, event: Event) {
+        if let Some(replaces) = self.replaced_by(&event) {
+            self.exdates.retain(|exdate| *exdate != replaces);
+            self.exceptions.push((replaces, event));
+        }
+    }
+}
+
+/// A weekly recurring series of calendar entries
+#[derive(Serialize, utoipa::ToSchema)]
+pub(super) struct SeriesResponse {
+    /// Structured, globaly unique room code
+    #[schema(examples("5602.EG.001"))]
+    room_code: String,
+    /// German title of the Entries
+    #[schema(examples("Quantenteleportation"))]
+    title_de: String,
+    /// English title of the Entries
+    #[schema(examples("Quantum teleportation"))]
+    title_en: String,
+    /// Lecture-type
+    #[schema(examples("Vorlesung mit Zentralübung"))]
+    stp_type: Option<String>,
+    entry_type: EventTypeResponse,
+    #[schema(examples("Abhaltung"))]
+    detailed_entry_type: String,
+    /// start of the first occurrence
+    #[schema(examples("2039-04-15T08:15:00Z"))]
+    start_at: DateTime<Utc>,
+    /// end of the first occurrence
+    #[schema(examples("2039-04-15T09:45:00Z"))]
+    end_at: DateTime<Utc>,
+    /// Recurrence of the series as an [RFC 5545](https://www.rfc-editor.org/rfc/rfc5545#section-3.3.10) `RRULE`.
+    ///
+    /// Occurrences are at the local time (`Europe/Berlin`) of `start_at`.
+    #[schema(examples("FREQ=WEEKLY;INTERVAL=1;BYDAY=MO;UNTIL=20390715T081500Z"))]
+    rrule: String,
+    /// Starts of occurrences which do not take place
+    #[schema(examples(json!(["2039-05-27T08:15:00Z"])))]
+    exdates: Vec<DateTime<Utc>>,
+    /// Entries replacing an occurrence, e.g. because they take place at another time of the same week
+    exceptions: Vec<SeriesExceptionResponse>,
+    /// IDs of the calendar entries of all regular occurrences
+    #[schema(examples(json!([6424, 6425, 6426])))]
+    event_ids: Vec<i32>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub(super) struct SeriesExceptionResponse {
+    /// Start of the occurrence this entry replaces
+    #[schema(examples("2039-05-27T08:15:00Z"))]
+    replaces: DateTime<Utc>,
+    event: EventResponse,
+}
+
+const fn rrule_weekday(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+impl From<Series> for SeriesResponse {
+    fn from(value: Series) -> Self {
+        let first = value.first;
+        let event_ids = iter::once(first.id)
+            .chain(value.later.iter().map(|event| event.id))
+            .collect();
+        let until = value
+            .later
+            .last()
+            .map_or(first.start_at, |event| event.start_at);
+        let rrule = format!(
+            "FREQ=WEEKLY;INTERVAL={interval};BYDAY={weekday};UNTIL={until}",
+            interval = value.interval_weeks,
+            weekday = rrule_weekday(first.start_at.with_timezone(&Berlin).weekday()),
+            until = until.format("%Y%m%dT%H%M%SZ"),
+        );
+        Self {
+            room_code: first.room_code,
+            title_de: first.title_de,
+            title_en: first.title_en,
+            stp_type: first.stp_type,
+            entry_type: EventTypeResponse::from(first.entry_type),
+            detailed_entry_type: first.detailed_entry_type,
+            start_at: first.start_at,
+            end_at: first.end_at,
+            rrule,
+            exdates: value.exdates,
+            exceptions: value
+                .exceptions
+                .into_iter()
+                .map(|(replaces, event)| SeriesExceptionResponse {
+                    replaces,
+                    event: EventResponse::from(event),
+                })
+                .collect(),
+            event_ids,
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use super::*;
+
+    fn only_series(grouped: Grouped) -> SeriesResponse {
+        assert_eq!(grouped.series.len(), 1);
+        grouped
+            .series
+            .into_iter()
+            .map(SeriesResponse::from)
+            .next()
+            .unwrap()
+    }
+
+    fn event(id: i32, title: &str, start_at: DateTime<Utc>) -> Event {
+        Event {
+            id,
+            room_code: "5602.EG.001".to_string(),
+            start_at,
+            end_at: start_at + TimeDelta::minutes(90),
+            title_de: title.to_string(),
+            title_en: title.to_string(),
+            stp_type: Some("Vorlesung".to_string()),
+            entry_type: "lecture".to_string(),
+            detailed_entry_type: "Abhaltung".to_string(),
+        }
+    }
+
+    /// 10:15 local time on the `day` of the `month` 2039
+    fn at(month: u32, day: u32) -> DateTime<Utc> {
+        Berlin
+            .with_ymd_and_hms(2039, month, day, 10, 15, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn weekly_lectures_are_a_series() {
+        // Mondays across the start of daylight saving time (2039-03-27), without 2039-04-11
+        let events = vec![
+            event(1, "Analysis 1", at(3, 21)),
+            event(2, "Analysis 1", at(3, 28)),
+            event(3, "Analysis 1", at(4, 4)),
+            event(4, "Analysis 1", at(4, 18)),
+            event(5, "Analysis 1", at(4, 25)),
+            event(6, "Lineare Algebra", at(3, 22)),
+        ];
+        let grouped = group_series(events);
+        assert_eq!(grouped.events.iter().map(|e| e.id).collect::<Vec<_>>(), [6]);
+        let series = only_series(grouped);
+        assert_eq!(series.event_ids, [1, 2, 3, 4, 5]);
+        assert_eq!(series.exdates, [at(4, 11)]);
+        assert_eq!(
+            series.rrule,
+            "FREQ=WEEKLY;INTERVAL=1;BYDAY=MO;UNTIL=20390425T081500Z"
+        );
+    }
+
+    #[test]
+    fn moved_occurrences_are_exceptions() {
+        let mut moved = event(4, "Analysis 1", at(4, 13));
+        moved.start_at += TimeDelta::hours(2);
+        moved.end_at += TimeDelta::hours(2);
+        let events = vec![
+            event(1, "Analysis 1", at(4, 4)),
+            event(2, "Analysis 1", at(4, 18)),
+            event(3, "Analysis 1", at(5, 2)),
+            moved,
+        ];
+        let grouped = group_series(events);
+        // biweekly, so no occurrence is missing which the moved event could replace
+        assert_eq!(grouped.events.iter().map(|e| e.id).collect::<Vec<_>>(), [4]);
+        let series = only_series(grouped);
+        assert!(series.rrule.starts_with("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO"));
+        assert!(series.exdates.is_empty());
+        assert!(series.exceptions.is_empty());
+
+        let events = vec![
+            event(1, "Analysis 1", at(4, 4)),
+            event(2, "Analysis 1", at(4, 11)),
+            event(3, "Analysis 1", at(4, 25)),
+            event(4, "Analysis 1", at(4, 20)),
+        ];
+        let grouped = group_series(events);
+        assert!(grouped.events.is_empty());
+        let series = only_series(grouped);
+        assert!(series.exdates.is_empty());
+        let exceptions = series
+            .exceptions
+            .iter()
+            .map(|e| (e.replaces, e.event.id))
+            .collect::<Vec<_>>();
+        assert_eq!(exceptions, [(at(4, 18), 4)]);
+    }
+}
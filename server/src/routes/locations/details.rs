@@ -1,12 +1,19 @@
-use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::http::header::{
+    CONTENT_LANGUAGE, CacheControl, CacheDirective, ETag, EntityTag, IfNoneMatch, VARY,
+};
 use actix_web::{HttpResponse, get, web};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::Error::RowNotFound;
 use sqlx::PgPool;
+use std::fmt::{self, Display, Formatter};
 use tracing::error;
+use xxhash_rust::xxh3::xxh3_64;
 
-use crate::db::location::LocationKeyAlias;
-use crate::localisation::{self, LanguageOptions};
+use super::removed::removed_location_response;
+use crate::db::location::{LocalisedLocation, LocationKeyAlias, LocationRevision};
+use crate::localisation::{LangQueryArgs, LanguagePreferences};
+use crate::opening_hours_evaluator::{Day, Schedule, State};
 
 #[expect(
     unused_imports,
@@ -14,12 +21,40 @@ use crate::localisation::{self, LanguageOptions};
 )]
 use serde_json::json;
 
+mod jsonld;
+
 #[derive(Deserialize, utoipa::IntoParams)]
 struct DetailsPathParams {
     /// ID of the location
     id: String,
 }
 
+#[derive(Deserialize, Default, Debug, utoipa::IntoParams)]
+#[serde(default)]
+struct DetailsQueryArgs {
+    /// The representation of the details.
+    #[param(inline)]
+    format: DetailsFormat,
+}
+
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum DetailsFormat {
+    /// Our own format, described by `LocationDetailsResponse`
+    #[default]
+    Json,
+    /// [schema.org](https://schema.org/Place) `Place` as JSON-LD, for embedding in websites
+    Jsonld,
+}
+impl Display for DetailsFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => f.write_str("json"),
+            Self::Jsonld => f.write_str("jsonld"),
+        }
+    }
+}
+
 /// Get entry-details
 ///
 /// This returns the full data available for the entry (room/building).
@@ -28,19 +63,36 @@ struct DetailsPathParams {
 /// Preloading this is not an issue on our end, but keep in mind bandwith constraints on your side.
 /// The data can be up to 50kB (using gzip) or 200kB unzipped.
 /// More about this data format is described in the NavigaTUM-data documentation
+///
+/// With `format=jsonld`, a [schema.org](https://schema.org/Place) `Place` is returned instead.
+/// It is intended to be embedded as `<script type="application/ld+json">` by websites linking to a location.
+///
+/// Entries are available in German and English, and some (e.g. the main buildings) in further languages.
+/// Untranslated content falls back to English and then German; `Content-Language` is the language served.
+///
+/// Responses carry a strong `ETag`. Please revalidate with `If-None-Match` instead of refetching.
 #[utoipa::path(
     tags=["locations"],
-    params(DetailsPathParams, localisation::LangQueryArgs),
+    params(DetailsPathParams, DetailsQueryArgs, LangQueryArgs),
     responses(
-        (status = 200, description = "**Details** about the **location**", body= LocationDetailsResponse, content_type="application/json"),
+        (status = 200, description = "**Details** about the **location**", content(
+            (LocationDetailsResponse = "application/json"),
+            (jsonld::PlaceJsonLd = "application/ld+json"),
+        )),
         (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 304, description = "**Not modified.** The `If-None-Match` header matches the current `ETag`"),
+        (status = 308, description = "**Permanent redirect.** The requested item was removed, but lives on under a successor (e.g. after a room was renumbered)"),
         (status = 404, description = "**Not found.** Make sure that requested item exists", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 410, description = "**Gone.** The requested item existed, but was removed without a successor", body = String, content_type = "text/plain", example = "Gone: 5602.EG.001 (MI HS 1) (5602.EG.001) was removed on 2026-05-03, for example because it was demolished, merged or renumbered. We do not know of a successor."),
     )
 )]
-#[get("/api/locations/{id}", wrap = "actix_middleware_etag::Etag::default()")]
+// No `actix_middleware_etag::Etag` here: it would hash the body, which is what `revision` avoids.
+#[get("/api/locations/{id}")]
 pub async fn get_handler(
     params: web::Path<DetailsPathParams>,
-    web::Query(args): web::Query<localisation::LangQueryArgs>,
+    web::Query(args): web::Query<DetailsQueryArgs>,
+    languages: LanguagePreferences,
+    if_none_match: Option<web::Header<IfNoneMatch>>,
     data: web::Data<crate::AppData>,
 ) -> HttpResponse {
     let id = params
@@ -51,25 +103,53 @@ pub async fn get_handler(
             .content_type("text/plain")
             .body("Invalid ID");
     }
-
     let Some((probable_id, redirect_url)) = get_alias_and_redirect(&data.pool, &id).await else {
+        if let Some(response) = removed_location_response(&data.pool, &id, |successor| {
+            format!(
+                "/api/locations/{successor}?lang={lang}&format={format}",
+                lang = languages.preferred(),
+                format = args.format
+            )
+        })
+        .await
+        {
+            return response;
+        }
         return HttpResponse::NotFound()
             .content_type("text/plain")
             .body("Not found");
     };
-    let result = if args.lang == LanguageOptions::En {
-        sqlx::query_scalar!("SELECT data FROM en WHERE key = $1", probable_id)
-            .fetch_optional(&data.pool)
-            .await
-    } else {
-        sqlx::query_scalar!("SELECT data FROM de WHERE key = $1", probable_id)
-            .fetch_optional(&data.pool)
-            .await
+    let now = Utc::now();
+    let (etag, max_age) = match revision(
+        &data.pool,
+        &probable_id,
+        &redirect_url,
+        &args,
+        &languages,
+        now,
+    )
+    .await
+    {
+        Ok(revision) => revision,
+        Err(response) => return response,
     };
+    let cache_control = CacheControl(vec![
+        CacheDirective::MaxAge(max_age),
+        CacheDirective::Public,
+    ]);
+    if if_none_match.is_some_and(|web::Header(if_none_match)| is_fresh(&if_none_match, &etag)) {
+        return HttpResponse::NotModified()
+            .insert_header(ETag(etag))
+            .insert_header((VARY, "Accept-Language"))
+            .insert_header(cache_control)
+            .finish();
+    }
+    let result =
+        LocalisedLocation::fetch_optional(&data.pool, &probable_id, &languages.tags()).await;
     match result {
         Ok(d) => {
             if let Some(d) = d {
-                let res = serde_json::from_value::<LocationDetailsResponse>(d);
+                let res = serde_json::from_value::<LocationDetailsResponse>(d.data);
                 match res {
                     Err(e) => {
                         error!(error = ?e, id,"cannot serialise detail");
@@ -79,12 +159,21 @@ pub async fn get_handler(
                     }
                     Ok(mut res) => {
                         res.redirect_url = redirect_url;
-                        HttpResponse::Ok()
-                            .insert_header(CacheControl(vec![
-                                CacheDirective::MaxAge(24 * 60 * 60), // valid for 1d
-                                CacheDirective::Public,
-                            ]))
-                            .json(res)
+                        if let Some(opening_hours) = &mut res.opening_hours {
+                            opening_hours.evaluate(res.coords.lat, res.coords.lon, now);
+                        }
+                        let mut response = HttpResponse::Ok();
+                        response
+                            .insert_header(ETag(etag))
+                            .insert_header(cache_control)
+                            .insert_header((VARY, "Accept-Language"))
+                            .insert_header((CONTENT_LANGUAGE, d.lang));
+                        match args.format {
+                            DetailsFormat::Json => response.json(res),
+                            DetailsFormat::Jsonld => response
+                                .content_type("application/ld+json")
+                                .json(jsonld::PlaceJsonLd::new(&res, languages.base())),
+                        }
                     }
                 }
             } else {
@@ -102,6 +191,79 @@ pub async fn get_handler(
     }
 }
 
+/// The strong `ETag` of a details response and how long it may be cached.
+///
+/// The data's `hash` covers everything but the opening hours, which depend on the current time.
+/// So we evaluate them from the (small) revision and include a digest of the result.
+/// The `redirect_url` depends on the aliases, which may change independently of the `hash`.
+async fn revision(
+    pool: &PgPool,
+    key: &str,
+    redirect_url: &str,
+    args: &DetailsQueryArgs,
+    languages: &LanguagePreferences,
+    now: DateTime<Utc>,
+) -> Result<(EntityTag, u32), HttpResponse> {
+    let location = match LocationRevision::fetch_optional(pool, key).await {
+        Ok(Some(location)) => location,
+        Ok(None) => {
+            return Err(HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found"));
+        }
+        Err(e) => {
+            error!(error = ?e, key, "Error requesting the revision");
+            return Err(HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error"));
+        }
+    };
+    let mut opening_hours = location
+        .opening_hours
+        .and_then(|value| serde_json::from_value::<OpeningHoursResponse>(value).ok());
+    let mut varying = redirect_url.as_bytes().to_vec();
+    // which fallback is served depends on the whole chain
+    varying.push(0);
+    varying.extend(languages.tags().join(",").as_bytes());
+    if let Some(opening_hours) = &mut opening_hours {
+        opening_hours.evaluate(location.lat, location.lon, now);
+        varying.push(0);
+        varying.extend(serde_json::to_vec(opening_hours).unwrap_or_default());
+    }
+    let tag = format!(
+        "{hash:x}-{lang}-{format}-{varying:x}",
+        hash = location.hash.unwrap_or_default(),
+        lang = languages.preferred(),
+        format = args.format,
+        varying = xxh3_64(&varying),
+    );
+    Ok((
+        EntityTag::new_strong(tag),
+        max_age_seconds(opening_hours.as_ref(), now),
+    ))
+}
+
+/// `If-None-Match` uses the weak comparison, see RFC 9110, section 13.1.2
+fn is_fresh(if_none_match: &IfNoneMatch, etag: &EntityTag) -> bool {
+    match if_none_match {
+        IfNoneMatch::Any => true,
+        IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+    }
+}
+
+/// How long a details response may be cached.
+///
+/// Usually a day, but `opening_hours.is_open_now` must not outlive the next change.
+fn max_age_seconds(opening_hours: Option<&OpeningHoursResponse>, now: DateTime<Utc>) -> u32 {
+    const ONE_DAY: u32 = 24 * 60 * 60;
+    let Some(next_change_at) = opening_hours.and_then(|opening_hours| opening_hours.next_change_at)
+    else {
+        return ONE_DAY;
+    };
+    let seconds_until_change = (next_change_at.with_timezone(&Utc) - now).num_seconds();
+    u32::try_from(seconds_until_change).map_or(60, |seconds| seconds.clamp(60, ONE_DAY))
+}
+
 #[serde_with::skip_serializing_none]
 #[derive(Deserialize, Serialize, Debug, Default, utoipa::ToSchema)]
 struct LocationDetailsResponse {
@@ -206,6 +368,111 @@ struct OpeningHoursResponse {
     /// several (e.g. a separate lending desk).
     #[schema(examples("Ausleihe"))]
     service: Option<String>,
+    /// Whether the location is open right now.
+    ///
+    /// Omitted when this is unknown: the schedule says so, is not valid right now, or could not be parsed.
+    is_open_now: Option<bool>,
+    /// When `is_open_now` changes next.
+    ///
+    /// Omitted if this does not happen within the days listed in `week`.
+    #[schema(examples("2026-05-04T22:00:00+02:00"))]
+    next_change_at: Option<DateTime<FixedOffset>>,
+    /// The evaluated schedule for today and the following six days in `Europe/Berlin` time.
+    ///
+    /// Empty if the schedule could not be parsed.
+    #[serde(default)]
+    week: Vec<OpeningHoursDayResponse>,
+    /// Problems we found while evaluating the schedule, e.g. an unparseable `osm` string.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+impl OpeningHoursResponse {
+    /// Fills in the fields which depend on the current time.
+    fn evaluate(&mut self, lat: f64, lon: f64, now: DateTime<Utc>) {
+        let evaluation = Schedule {
+            osm: &self.osm,
+            valid_from: self.valid_from.as_deref(),
+            valid_until: self.valid_until.as_deref(),
+            coords: Some((lat, lon)),
+        }
+        .evaluate(now);
+        self.is_open_now = evaluation.state.and_then(|state| match state {
+            State::Open => Some(true),
+            State::Closed => Some(false),
+            State::Unknown => None,
+        });
+        self.next_change_at = evaluation.next_change_at.map(|at| at.fixed_offset());
+        self.week = evaluation
+            .week
+            .into_iter()
+            .map(OpeningHoursDayResponse::from)
+            .collect();
+        self.warnings = evaluation.warnings;
+    }
+}
+
+/// The schedule of a single day
+#[derive(Deserialize, Serialize, Debug, utoipa::ToSchema)]
+struct OpeningHoursDayResponse {
+    /// `YYYY-MM-DD` date of this day
+    #[schema(examples("2026-05-04"))]
+    date: NaiveDate,
+    /// The periods in which the location is not closed, in chronological order.
+    ///
+    /// Empty on days the location is closed.
+    intervals: Vec<OpeningHoursIntervalResponse>,
+}
+
+impl From<Day> for OpeningHoursDayResponse {
+    fn from(day: Day) -> Self {
+        let intervals = day
+            .intervals
+            .into_iter()
+            .map(|interval| OpeningHoursIntervalResponse {
+                start: interval.range.start.format("%H:%M").to_string(),
+                end: if interval.range.end.date_naive() == day.date {
+                    interval.range.end.format("%H:%M").to_string()
+                } else {
+                    "24:00".to_string()
+                },
+                state: match interval.state {
+                    State::Open => OpeningHoursStateResponse::Open,
+                    State::Closed | State::Unknown => OpeningHoursStateResponse::Unknown,
+                },
+                comment: interval.comment,
+            })
+            .collect();
+        Self {
+            date: day.date,
+            intervals,
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Deserialize, Serialize, Debug, utoipa::ToSchema)]
+struct OpeningHoursIntervalResponse {
+    /// `HH:MM` local time at which this period starts
+    #[schema(examples("08:00"))]
+    start: String,
+    /// `HH:MM` local time at which this period ends.
+    ///
+    /// `24:00` if the period lasts until the end of the day.
+    #[schema(examples("22:00", "24:00"))]
+    end: String,
+    state: OpeningHoursStateResponse,
+    /// Comment of the rule active in this period
+    #[schema(examples("nur mit Termin"))]
+    comment: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum OpeningHoursStateResponse {
+    Open,
+    /// The schedule does not say whether the location is open, or is not valid on this day
+    Unknown,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, utoipa::ToSchema)]
@@ -482,11 +749,25 @@ struct PropsResponse {
     /// Empty (and omitted) for entries without coverage.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     iris_coverage_building_ids: Vec<String>,
+    /// Postal address, only used for the JSON-LD representation.
+    ///
+    /// The details show it as a `computed` prop instead.
+    #[serde(default, skip_serializing)]
+    #[schema(ignore)]
+    address: Option<AddressResponse>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct AddressResponse {
+    /// Street and house number
+    street: String,
+    /// Postal code followed by the place, e.g. `85748 Garching b. München`
+    plz_place: String,
 }
 
 #[serde_with::skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, utoipa::ToSchema)]
-struct FloorResponse {
+pub(super) struct FloorResponse {
     /// Virtual ID for sorting
     ///
     /// `0` represents the ground floor.
@@ -496,7 +777,7 @@ struct FloorResponse {
     /// This ID is not guaranteed to be stable.
     /// Not across buildings, nor within a building.
     #[schema(examples(-1, 0, 1, 2, 3))]
-    id: i32,
+    pub(super) id: i32,
     /// Short name of the floor
     #[schema(examples("-1", "0", "Z1"))]
     #[serde(rename(deserialize = "floor"))]
@@ -700,7 +981,7 @@ mod tests {
         let pg = PostgresTestContainer::new().await;
         pg.load_data_retrying().await;
 
-        let keys: Vec<String> = sqlx::query_scalar("SELECT key FROM de")
+        let keys: Vec<String> = sqlx::query_scalar("SELECT key FROM locations")
             .fetch_all(&pg.pool)
             .await
             .unwrap();
@@ -740,7 +1021,16 @@ mod tests {
         let body_box = resp.into_body();
         let body_bytes = actix_web::body::to_bytes(body_box).await.unwrap();
         let body_str = String::from_utf8(body_bytes.into_iter().collect()).unwrap();
-        let body_value: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+        let mut body_value: serde_json::Value = serde_json::from_str(&body_str).unwrap();
+        // evaluated against the current time, and omitted when unknown, so redacting them is not enough
+        if let Some(opening_hours) = body_value
+            .get_mut("opening_hours")
+            .and_then(serde_json::Value::as_object_mut)
+        {
+            for field in ["is_open_now", "next_change_at", "week"] {
+                opening_hours.remove(field);
+            }
+        }
 
         let mut settings = insta::Settings::clone_current();
         settings.set_sort_maps(true);
@@ -750,6 +1040,20 @@ mod tests {
         });
     }
 
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let etag = EntityTag::new_strong("2a-de-json-1".to_string());
+        let matching = IfNoneMatch::Items(vec![
+            EntityTag::new_strong("outdated".to_string()),
+            EntityTag::new_weak("2a-de-json-1".to_string()),
+        ]);
+        assert!(is_fresh(&matching, &etag));
+        assert!(is_fresh(&IfNoneMatch::Any, &etag));
+        let other_language =
+            IfNoneMatch::Items(vec![EntityTag::new_strong("2a-en-json-1".to_string())]);
+        assert!(!is_fresh(&other_language, &etag));
+    }
+
     #[test]
     fn building_overview_entry_round_trips_type() {
         let entry: BuildingsOverviewItemResponse = serde_json::from_value(serde_json::json!({
@@ -0,0 +1,343 @@
+//! [schema.org](https://schema.org) representation of a location as JSON-LD.
+//!
+//! Websites linking to a location (news, LMS, ...) embed this as
+//! `<script type="application/ld+json">` to get rich cards from search engines and link previews.
+
+use serde::Serialize;
+
+use super::{
+    AddressResponse, LocationDetailsResponse, LocationTypeResponse, OpeningHoursDayResponse,
+    OpeningHoursStateResponse, ParentLocationTypeResponse,
+};
+use crate::localisation::LanguageOptions;
+
+const WEBSITE: &str = "https://nav.tum.de";
+
+/// A location as a schema.org [`Place`](https://schema.org/Place)
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PlaceJsonLd {
+    #[serde(rename = "@context")]
+    #[schema(examples("https://schema.org"))]
+    context: &'static str,
+    #[serde(rename = "@type")]
+    r#type: PlaceTypeJsonLd,
+    /// URL of the page of this location
+    #[serde(rename = "@id")]
+    #[schema(examples("https://nav.tum.de/room/5606.EG.036"))]
+    id: String,
+    /// URL of the page of this location
+    #[schema(examples("https://nav.tum.de/room/5606.EG.036"))]
+    url: String,
+    /// ID of this location in our API
+    #[schema(examples("5606.EG.036"))]
+    identifier: String,
+    #[schema(examples("5606.EG.036 (Büro Fachschaft Mathe Physik Informatik Chemie / MPIC)"))]
+    name: String,
+    /// The type of the location in a human-readable form
+    #[schema(examples("Büro"))]
+    description: String,
+    geo: GeoCoordinatesJsonLd,
+    address: Option<PostalAddressJsonLd>,
+    /// URL of a preview image
+    #[schema(examples("https://nav.tum.de/api/locations/5606.EG.036/preview?lang=de"))]
+    image: String,
+    /// The evaluated opening hours for the next seven days.
+    ///
+    /// Omitted if the location does not have opening hours.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    opening_hours_specification: Vec<OpeningHoursSpecificationJsonLd>,
+    /// The immediate parent, which in turn is contained in its parent
+    contained_in_place: Option<ContainedPlaceJsonLd>,
+}
+
+impl PlaceJsonLd {
+    pub(super) fn new(details: &LocationDetailsResponse, lang: LanguageOptions) -> Self {
+        let url = format!(
+            "{WEBSITE}{prefix}{path}",
+            prefix = lang_prefix(lang),
+            path = details.redirect_url
+        );
+        let ancestors = details
+            .parents
+            .iter()
+            .zip(&details.parent_names)
+            .zip(&details.parent_types)
+            .filter(|(_, r#type)| **r#type != ParentLocationTypeResponse::Root);
+        let contained_in_place =
+            ancestors.fold(None, |contained_in_place, ((id, name), r#type)| {
+                Some(ContainedPlaceJsonLd {
+                    r#type: PlaceTypeJsonLd::from(*r#type),
+                    id: format!("{WEBSITE}{prefix}/view/{id}", prefix = lang_prefix(lang)),
+                    identifier: id.clone(),
+                    name: name.clone(),
+                    contained_in_place: contained_in_place.map(Box::new),
+                })
+            });
+        let opening_hours_specification = details
+            .opening_hours
+            .as_ref()
+            .map(|opening_hours| {
+                opening_hours
+                    .week
+                    .iter()
+                    .flat_map(OpeningHoursSpecificationJsonLd::from_day)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            context: "https://schema.org",
+            r#type: PlaceTypeJsonLd::from(&details.r#type),
+            id: url.clone(),
+            url,
+            identifier: details.id.clone(),
+            name: details.name.clone(),
+            description: details.type_common_name.clone(),
+            geo: GeoCoordinatesJsonLd {
+                r#type: GeoCoordinatesTypeJsonLd::GeoCoordinates,
+                latitude: details.coords.lat,
+                longitude: details.coords.lon,
+            },
+            address: details
+                .props
+                .address
+                .as_ref()
+                .map(PostalAddressJsonLd::from),
+            image: format!(
+                "{WEBSITE}/api/locations/{id}/preview?lang={lang}",
+                id = details.id
+            ),
+            opening_hours_specification,
+            contained_in_place,
+        }
+    }
+}
+
+fn lang_prefix(lang: LanguageOptions) -> &'static str {
+    match lang {
+        LanguageOptions::De => "",
+        LanguageOptions::En => "/en",
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, utoipa::ToSchema)]
+enum PlaceTypeJsonLd {
+    /// Buildings and the areas, campuses, and sites grouping them
+    CivicStructure,
+    /// Everything else, like rooms and points of interest
+    Place,
+}
+
+impl From<&LocationTypeResponse> for PlaceTypeJsonLd {
+    fn from(r#type: &LocationTypeResponse) -> Self {
+        match r#type {
+            LocationTypeResponse::Building
+            | LocationTypeResponse::JoinedBuilding
+            | LocationTypeResponse::Area
+            | LocationTypeResponse::Site
+            | LocationTypeResponse::Campus => Self::CivicStructure,
+            LocationTypeResponse::Room
+            | LocationTypeResponse::Poi
+            | LocationTypeResponse::Other => Self::Place,
+        }
+    }
+}
+
+impl From<ParentLocationTypeResponse> for PlaceTypeJsonLd {
+    fn from(r#type: ParentLocationTypeResponse) -> Self {
+        match r#type {
+            ParentLocationTypeResponse::Building
+            | ParentLocationTypeResponse::JoinedBuilding
+            | ParentLocationTypeResponse::Area
+            | ParentLocationTypeResponse::Site
+            | ParentLocationTypeResponse::Campus => Self::CivicStructure,
+            ParentLocationTypeResponse::Root
+            | ParentLocationTypeResponse::Room
+            | ParentLocationTypeResponse::VirtualRoom
+            | ParentLocationTypeResponse::Poi => Self::Place,
+        }
+    }
+}
+
+/// A parent of a location
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ContainedPlaceJsonLd {
+    #[serde(rename = "@type")]
+    r#type: PlaceTypeJsonLd,
+    /// URL of the page of this location
+    #[serde(rename = "@id")]
+    #[schema(examples("https://nav.tum.de/view/mi"))]
+    id: String,
+    /// ID of this location in our API
+    #[schema(examples("mi"))]
+    identifier: String,
+    #[schema(examples("Fakultät Mathematik & Informatik (FMI oder MI)"))]
+    name: String,
+    #[schema(no_recursion)]
+    contained_in_place: Option<Box<Self>>,
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct GeoCoordinatesJsonLd {
+    #[serde(rename = "@type")]
+    r#type: GeoCoordinatesTypeJsonLd,
+    #[schema(examples(48.26244490906312))]
+    latitude: f64,
+    #[schema(examples(11.668))]
+    longitude: f64,
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+enum GeoCoordinatesTypeJsonLd {
+    GeoCoordinates,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct PostalAddressJsonLd {
+    #[serde(rename = "@type")]
+    r#type: PostalAddressTypeJsonLd,
+    #[schema(examples("Boltzmannstr. 3"))]
+    street_address: String,
+    #[schema(examples("85748"))]
+    postal_code: Option<String>,
+    #[schema(examples("Garching b. München"))]
+    address_locality: String,
+    /// Always `DE`, as all our locations are in Germany
+    #[schema(examples("DE"))]
+    address_country: &'static str,
+}
+
+impl From<&AddressResponse> for PostalAddressJsonLd {
+    fn from(address: &AddressResponse) -> Self {
+        let (postal_code, address_locality) = match address.plz_place.split_once(' ') {
+            Some((plz, place)) if plz.len() == 5 && plz.chars().all(|c| c.is_ascii_digit()) => {
+                (Some(plz.to_string()), place.to_string())
+            }
+            _ => (None, address.plz_place.clone()),
+        };
+        Self {
+            r#type: PostalAddressTypeJsonLd::PostalAddress,
+            street_address: address.street.clone(),
+            postal_code,
+            address_locality,
+            address_country: "DE",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+enum PostalAddressTypeJsonLd {
+    PostalAddress,
+}
+
+/// A period in which the location is open
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct OpeningHoursSpecificationJsonLd {
+    #[serde(rename = "@type")]
+    r#type: OpeningHoursSpecificationTypeJsonLd,
+    /// `YYYY-MM-DD` date this period is on
+    #[schema(examples("2026-05-04"))]
+    valid_from: String,
+    /// `YYYY-MM-DD` date this period is on
+    #[schema(examples("2026-05-04"))]
+    valid_through: String,
+    /// `HH:MM` local time at which the location opens
+    #[schema(examples("08:00"))]
+    opens: String,
+    /// `HH:MM` local time at which the location closes.
+    ///
+    /// `23:59` if it is open until midnight.
+    #[schema(examples("22:00"))]
+    closes: String,
+}
+
+impl OpeningHoursSpecificationJsonLd {
+    /// The periods of a day in which the location is open.
+    ///
+    /// schema.org cannot express that we do not know if a location is open, so these periods are left out.
+    fn from_day(day: &OpeningHoursDayResponse) -> impl Iterator<Item = Self> {
+        let date = day.date.format("%Y-%m-%d").to_string();
+        day.intervals
+            .iter()
+            .filter(|interval| matches!(interval.state, OpeningHoursStateResponse::Open))
+            .map(move |interval| Self {
+                r#type: OpeningHoursSpecificationTypeJsonLd::OpeningHoursSpecification,
+                valid_from: date.clone(),
+                valid_through: date.clone(),
+                opens: interval.start.clone(),
+                closes: if interval.end == "24:00" {
+                    "23:59".to_string()
+                } else {
+                    interval.end.clone()
+                },
+            })
+    }
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+enum OpeningHoursSpecificationTypeJsonLd {
+    OpeningHoursSpecification,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postal_code_is_split_off() {
+        let address = PostalAddressJsonLd::from(&AddressResponse {
+            street: "Boltzmannstr. 3".to_string(),
+            plz_place: "85748 Garching b. München".to_string(),
+        });
+        assert_eq!(address.postal_code.as_deref(), Some("85748"));
+        assert_eq!(address.address_locality, "Garching b. München");
+
+        let address = PostalAddressJsonLd::from(&AddressResponse {
+            street: "Arcisstr. 21".to_string(),
+            plz_place: "München".to_string(),
+        });
+        assert_eq!(address.postal_code, None);
+        assert_eq!(address.address_locality, "München");
+    }
+
+    #[test]
+    fn parents_are_nested_innermost_first() {
+        let details = LocationDetailsResponse {
+            id: "5606.EG.036".to_string(),
+            name: "5606.EG.036 (Büro)".to_string(),
+            redirect_url: "/room/5606.EG.036".to_string(),
+            parents: vec!["root".to_string(), "garching".to_string(), "mi".to_string()],
+            parent_names: vec![
+                "Standorte".to_string(),
+                "Garching Forschungszentrum".to_string(),
+                "Fakultät Mathematik & Informatik (FMI oder MI)".to_string(),
+            ],
+            parent_types: vec![
+                ParentLocationTypeResponse::Root,
+                ParentLocationTypeResponse::Campus,
+                ParentLocationTypeResponse::JoinedBuilding,
+            ],
+            ..LocationDetailsResponse::default()
+        };
+        let place = PlaceJsonLd::new(&details, LanguageOptions::En);
+        assert_eq!(place.url, "https://nav.tum.de/en/room/5606.EG.036");
+        assert_eq!(place.r#type, PlaceTypeJsonLd::Place);
+
+        let parent = place.contained_in_place.expect("the room has parents");
+        assert_eq!(parent.identifier, "mi");
+        assert_eq!(parent.id, "https://nav.tum.de/en/view/mi");
+        assert_eq!(parent.r#type, PlaceTypeJsonLd::CivicStructure);
+        let grandparent = parent.contained_in_place.expect("mi is on a campus");
+        assert_eq!(grandparent.identifier, "garching");
+        assert!(
+            grandparent.contained_in_place.is_none(),
+            "root is not a place"
+        );
+    }
+}
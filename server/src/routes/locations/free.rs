@@ -0,0 +1,294 @@
+use actix_web::http::header::{CacheControl, CacheDirective, VARY};
+use actix_web::{HttpResponse, get, web};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::db::free_room::{FreeRoom, FreeRoomFilter};
+use crate::db::location::LocationKeyAlias;
+use crate::localisation::{LangQueryArgs, LanguagePreferences};
+
+/// Calendars are rescraped hourly; anything older means scraping is lagging behind
+const STALE_AFTER: TimeDelta = TimeDelta::hours(2);
+/// Longest time span which can be queried
+const MAX_SPAN: TimeDelta = TimeDelta::days(7);
+/// Used if `to` is not given
+const DEFAULT_SPAN: TimeDelta = TimeDelta::hours(1);
+
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+struct FreeRoomsQueryArgs {
+    /// ID of the location the rooms should be in, e.g. a building
+    #[param(example = "5602")]
+    r#in: String,
+    /// Start of the time span the rooms have to be free in. Defaults to now.
+    #[param(example = "2039-01-19T14:00:00+01:00")]
+    from: Option<DateTime<Utc>>,
+    /// End of the time span the rooms have to be free in. Defaults to one hour after `from`.
+    ///
+    /// Spans of more than 7 days are rejected.
+    #[param(example = "2039-01-19T16:00:00+01:00")]
+    to: Option<DateTime<Utc>>,
+    /// Only include rooms with this usage (in german or english, case-insensitive)
+    #[param(example = "Seminarraum")]
+    usage: Option<String>,
+    /// Only include rooms with at least this many seats
+    #[param(minimum = 0, example = 30)]
+    min_seats: Option<i32>,
+}
+
+impl FreeRoomsQueryArgs {
+    fn time_span(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>), HttpResponse> {
+        let from = self.from.unwrap_or(now);
+        let to = self.to.unwrap_or(from + DEFAULT_SPAN);
+        if to <= from {
+            return Err(HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body("`to` has to be after `from`"));
+        }
+        if to - from > MAX_SPAN {
+            return Err(HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body("The time span may be at most 7 days"));
+        }
+        Ok((from, to))
+    }
+}
+
+/// Find free rooms
+///
+/// Lists the rooms below a location (e.g. a building) which have no booking in their calendar
+/// between `from` and `to`.
+/// Only rooms with a calendar in `TUMonline` are considered.
+///
+/// Rooms are sorted by how long they stay free, rooms without any further booking first.
+/// Since bookings are scraped from `TUMonline`, `freshness_warning` is set if the calendars may be outdated.
+#[utoipa::path(
+    tags=["locations"],
+    params(FreeRoomsQueryArgs, LangQueryArgs),
+    responses(
+        (status = 200, description = "**Free rooms** in the requested time span", body = FreeRoomsResponse, content_type = "application/json"),
+        (status = 400, description = "**Bad request.** Make sure that `in` is not empty and not longer than 255 characters and that the time span is valid", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 404, description = "**Not found.** Make sure that `in` exists", body = String, content_type = "text/plain", example = "Not found"),
+    )
+)]
+#[get("/api/locations/free", wrap = "actix_middleware_etag::Etag::default()")]
+pub async fn free_rooms_handler(
+    web::Query(args): web::Query<FreeRoomsQueryArgs>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let id = args
+        .r#in
+        .replace(|c: char| c.is_whitespace() || c.is_control(), "");
+    if id.is_empty() || id.len() > 255 {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid ID");
+    }
+    let now = Utc::now();
+    let (from, to) = match args.time_span(now) {
+        Ok(span) => span,
+        Err(e) => return e,
+    };
+    let key = match LocationKeyAlias::fetch_optional(&data.pool, &id).await {
+        Ok(Some(alias)) => alias.key,
+        Ok(None) => id,
+        Err(e) => {
+            error!(error = ?e, id, "error requesting alias");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    let filter = FreeRoomFilter {
+        within: &key,
+        from,
+        to,
+        usage: args.usage.as_deref(),
+        min_seats: args.min_seats,
+    };
+    let rooms = match FreeRoom::fetch_all(&data.pool, &filter, &languages.tags()).await {
+        Ok(Some(rooms)) => rooms,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found");
+        }
+        Err(e) => {
+            error!(error = ?e, ?filter, "Could not get free rooms");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(5 * 60), // valid for 5m
+            CacheDirective::Public,
+        ]))
+        .insert_header((VARY, "Accept-Language"))
+        .json(FreeRoomsResponse::new(rooms, from, to, now))
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct FreeRoomsResponse {
+    /// Start of the time span the rooms are free in
+    #[schema(examples("2039-01-19T13:00:00Z"))]
+    from: DateTime<Utc>,
+    /// End of the time span the rooms are free in
+    #[schema(examples("2039-01-19T15:00:00Z"))]
+    to: DateTime<Utc>,
+    /// Rooms without a booking in the time span, sorted by how long they stay free
+    rooms: Vec<FreeRoomResponse>,
+    /// Oldest scrape of the calendars of the listed rooms
+    #[schema(examples("2039-01-19T12:14:07Z"))]
+    oldest_calendar_scrape_at: Option<DateTime<Utc>>,
+    /// Set if the calendars may be outdated, so rooms could be booked in the meantime
+    #[schema(examples(
+        "Some calendars were last scraped more than 2 hours ago and may miss recent bookings"
+    ))]
+    freshness_warning: Option<String>,
+}
+
+impl FreeRoomsResponse {
+    /// Rooms whose calendar was never scraped are dropped, as we know nothing about their bookings.
+    fn new(
+        rooms: Vec<FreeRoom>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let (scraped, unscraped): (Vec<_>, Vec<_>) = rooms
+            .into_iter()
+            .partition(|r| r.last_calendar_scrape_at.is_some());
+        let oldest_calendar_scrape_at = scraped
+            .iter()
+            .filter_map(|r| r.last_calendar_scrape_at)
+            .min();
+        let mut warnings = Vec::new();
+        if oldest_calendar_scrape_at.is_some_and(|at| now - at > STALE_AFTER) {
+            warnings.push(format!(
+                "Some calendars were last scraped more than {hours} hours ago and may miss recent bookings",
+                hours = STALE_AFTER.num_hours()
+            ));
+        }
+        if !unscraped.is_empty() {
+            warnings.push(format!(
+                "{count} rooms were omitted, as their calendars were not scraped yet",
+                count = unscraped.len()
+            ));
+        }
+        Self {
+            from,
+            to,
+            rooms: scraped.into_iter().map(FreeRoomResponse::from).collect(),
+            oldest_calendar_scrape_at,
+            freshness_warning: (!warnings.is_empty()).then(|| warnings.join(". ")),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct FreeRoomResponse {
+    /// ID of the room
+    #[schema(examples("5602.EG.001"))]
+    id: String,
+    /// name of the room in a human-readable form
+    #[schema(examples("5602.EG.001 (MI HS 1, Friedrich L. Bauer Hörsaal)"))]
+    name: String,
+    /// Usage of the room
+    #[schema(examples("Hörsaal", "Seminarraum"))]
+    usage: Option<String>,
+    /// Number of seats, if known
+    #[schema(examples(30))]
+    seats: Option<i32>,
+    /// Start of the next booking after the time span.
+    /// `null` if there is no further booking in the calendar.
+    #[schema(examples("2039-01-19T17:00:00Z"))]
+    free_until: Option<DateTime<Utc>>,
+}
+
+impl From<FreeRoom> for FreeRoomResponse {
+    fn from(room: FreeRoom) -> Self {
+        Self {
+            id: room.key,
+            name: room.name,
+            usage: room.usage,
+            seats: room.seats,
+            free_until: room.free_until,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(key: &str, last_calendar_scrape_at: Option<DateTime<Utc>>) -> FreeRoom {
+        FreeRoom {
+            key: key.to_string(),
+            name: key.to_string(),
+            usage: None,
+            seats: None,
+            free_until: None,
+            last_calendar_scrape_at,
+        }
+    }
+
+    #[test]
+    fn freshness_warning() {
+        let now = Utc::now();
+        let (from, to) = (now, now + DEFAULT_SPAN);
+
+        let fresh = FreeRoomsResponse::new(vec![room("a", Some(now))], from, to, now);
+        assert_eq!(fresh.rooms.len(), 1);
+        assert_eq!(fresh.freshness_warning, None);
+
+        let rooms = vec![
+            room("a", Some(now)),
+            room("b", Some(now - TimeDelta::hours(3))),
+            room("c", None),
+        ];
+        let stale = FreeRoomsResponse::new(rooms, from, to, now);
+        assert_eq!(
+            stale
+                .rooms
+                .iter()
+                .map(|r| r.id.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(
+            stale.oldest_calendar_scrape_at,
+            Some(now - TimeDelta::hours(3))
+        );
+        let warning = stale.freshness_warning.unwrap_or_default();
+        assert!(warning.contains("more than 2 hours"), "{warning}");
+        assert!(warning.contains("1 rooms were omitted"), "{warning}");
+    }
+
+    #[test]
+    fn time_span_is_validated() {
+        let now = Utc::now();
+        let args = |from, to| FreeRoomsQueryArgs {
+            r#in: "5602".to_string(),
+            from,
+            to,
+            usage: None,
+            min_seats: None,
+        };
+        assert_eq!(
+            args(None, None).time_span(now).ok(),
+            Some((now, now + DEFAULT_SPAN))
+        );
+        assert!(args(Some(now), Some(now)).time_span(now).is_err());
+        assert!(
+            args(Some(now), Some(now + TimeDelta::days(8)))
+                .time_span(now)
+                .is_err()
+        );
+    }
+}
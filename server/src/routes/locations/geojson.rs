@@ -0,0 +1,273 @@
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION, VARY};
+use actix_web::{HttpResponse, get, web};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::error;
+
+use super::removed::removed_location_response;
+use crate::db::geojson::LocationFeature;
+use crate::db::location::LocationKeyAlias;
+use crate::localisation::{LangQueryArgs, LanguagePreferences};
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct GeoJsonPathParams {
+    /// ID of a location
+    id: String,
+}
+
+#[derive(Deserialize, Default, Debug, utoipa::IntoParams)]
+#[serde(default)]
+struct GeoJsonQueryArgs {
+    /// Include everything below the location (e.g. all rooms of a building)
+    descendants: bool,
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_possible_redirect_url(
+    pool: &PgPool,
+    query: &str,
+    args: &GeoJsonQueryArgs,
+    languages: &LanguagePreferences,
+) -> Option<String> {
+    let result = LocationKeyAlias::fetch_optional(pool, query).await;
+    match result {
+        Ok(Some(d)) => Some(geojson_url(&d.key, args, languages)),
+        Ok(None) => None,
+        Err(e) => {
+            error!(error = ?e, query, "error requesting alias");
+            None
+        }
+    }
+}
+
+fn geojson_url(key: &str, args: &GeoJsonQueryArgs, languages: &LanguagePreferences) -> String {
+    format!(
+        "/api/locations/{key}.geojson?lang={lang}&descendants={descendants}",
+        lang = languages.preferred(),
+        descendants = args.descendants
+    )
+}
+
+/// Get a location as `GeoJSON`
+///
+/// Returns the location and, if `descendants=true`, everything below it as a `GeoJSON` `FeatureCollection`.
+/// This is intended for importing into GIS tools like QGIS.
+///
+/// Rooms which are mapped indoors are exported as their polygon, everything else as a point.
+#[utoipa::path(
+    tags=["locations"],
+    params(GeoJsonPathParams, GeoJsonQueryArgs, LangQueryArgs),
+    responses(
+        (status = 200, description = "The location as a **`GeoJSON` `FeatureCollection`**", body = FeatureCollectionResponse, content_type = "application/geo+json"),
+        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 308, description = "**Permanent redirect.** The requested item is an alias, or was removed but lives on under a successor"),
+        (status = 404, description = "**Not found.** Make sure that requested item exists", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 410, description = "**Gone.** The requested item existed, but was removed without a successor", body = String, content_type = "text/plain"),
+    )
+)]
+#[get(
+    "/api/locations/{id}.geojson",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn geojson_handler(
+    params: web::Path<GeoJsonPathParams>,
+    args: web::Query<GeoJsonQueryArgs>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let id = params
+        .id
+        .replace(|c: char| c.is_whitespace() || c.is_control(), "");
+    if id.is_empty() || id.len() > 255 {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid ID");
+    }
+
+    if let Some(redirect_url) = get_possible_redirect_url(&data.pool, &id, &args, &languages).await
+    {
+        return HttpResponse::PermanentRedirect()
+            .insert_header((LOCATION, redirect_url))
+            .finish();
+    }
+    let features =
+        match LocationFeature::fetch_subtree(&data.pool, &id, args.descendants, &languages.tags())
+            .await
+        {
+            Ok(features) if !features.is_empty() => features,
+            Ok(_) => {
+                if let Some(response) = removed_location_response(&data.pool, &id, |successor| {
+                    geojson_url(successor, &args, &languages)
+                })
+                .await
+                {
+                    return response;
+                }
+                return HttpResponse::NotFound()
+                    .content_type("text/plain")
+                    .body("Not found");
+            }
+            Err(e) => {
+                error!(error = ?e, id, "Could not get the location subtree");
+                return HttpResponse::InternalServerError()
+                    .content_type("text/plain")
+                    .body("Could not get data for location, please try again later");
+            }
+        };
+    HttpResponse::Ok()
+        .content_type("application/geo+json")
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(24 * 60 * 60), // valid for 1d
+            CacheDirective::Public,
+        ]))
+        .insert_header((VARY, "Accept-Language"))
+        .json(FeatureCollectionResponse::from(features))
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct FeatureCollectionResponse {
+    r#type: FeatureCollectionType,
+    /// The requested location first, followed by its descendants (if requested)
+    features: Vec<FeatureResponse>,
+}
+
+impl From<Vec<LocationFeature>> for FeatureCollectionResponse {
+    fn from(features: Vec<LocationFeature>) -> Self {
+        Self {
+            r#type: FeatureCollectionType::FeatureCollection,
+            features: features.into_iter().map(FeatureResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+enum FeatureCollectionType {
+    FeatureCollection,
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct FeatureResponse {
+    r#type: FeatureType,
+    /// ID of the location
+    #[schema(examples("5606.EG.036"))]
+    id: String,
+    /// `GeoJSON` geometry in WGS84.
+    ///
+    /// A `Polygon` or `MultiPolygon` for rooms which are mapped indoors, a `Point` otherwise.
+    #[schema(value_type = Object, examples(json!({"type": "Point", "coordinates": [11.66842, 48.26244]})))]
+    geometry: serde_json::Value,
+    properties: FeaturePropertiesResponse,
+}
+
+impl From<LocationFeature> for FeatureResponse {
+    fn from(feature: LocationFeature) -> Self {
+        let geometry = feature.polygon.unwrap_or_else(|| {
+            json!({
+                "type": "Point",
+                "coordinates": [feature.lon, feature.lat],
+            })
+        });
+        Self {
+            r#type: FeatureType::Feature,
+            id: feature.key.clone(),
+            geometry,
+            properties: FeaturePropertiesResponse {
+                id: feature.key,
+                name: feature.name,
+                r#type: feature.r#type,
+                usage: feature.usage,
+                level: feature.level,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+enum FeatureType {
+    Feature,
+}
+
+/// Properties of a feature.
+///
+/// QGIS does not expose the feature-level `id`, so it is repeated here.
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct FeaturePropertiesResponse {
+    /// ID of the location
+    #[schema(examples("5606.EG.036"))]
+    id: String,
+    /// Name of the location
+    #[schema(examples("5606.EG.036 (MI HS 3, Hörsaal)"))]
+    name: String,
+    /// Type of the location
+    #[schema(examples("room", "building"))]
+    r#type: String,
+    /// Usage of the room
+    #[schema(examples("Hörsaal"))]
+    usage: Option<String>,
+    /// Short name of the floor, if the location is on exactly one floor
+    #[schema(examples("0", "-1", "Z1"))]
+    level: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::dev::{Path, ResourceDef};
+
+    fn feature(polygon: Option<serde_json::Value>) -> LocationFeature {
+        LocationFeature {
+            key: "5606.EG.036".to_string(),
+            name: "5606.EG.036 (MI HS 3, Hörsaal)".to_string(),
+            r#type: "room".to_string(),
+            usage: Some("Hörsaal".to_string()),
+            level: Some("0".to_string()),
+            lat: 48.26,
+            lon: 11.67,
+            polygon,
+        }
+    }
+
+    #[test]
+    fn unmapped_rooms_fall_back_to_points() {
+        let response = serde_json::to_value(FeatureCollectionResponse::from(vec![feature(None)]))
+            .expect("serialising the response should work");
+        assert_eq!(
+            response,
+            json!({
+                "type": "FeatureCollection",
+                "features": [{
+                    "type": "Feature",
+                    "id": "5606.EG.036",
+                    "geometry": {"type": "Point", "coordinates": [11.67, 48.26]},
+                    "properties": {
+                        "id": "5606.EG.036",
+                        "name": "5606.EG.036 (MI HS 3, Hörsaal)",
+                        "type": "room",
+                        "usage": "Hörsaal",
+                        "level": "0",
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn mapped_rooms_are_polygons() {
+        let polygon = json!({
+            "type": "Polygon",
+            "coordinates": [[[11.0, 48.0], [11.1, 48.0], [11.1, 48.1], [11.0, 48.0]]],
+        });
+        let response = FeatureResponse::from(feature(Some(polygon.clone())));
+        assert_eq!(response.geometry, polygon);
+        assert_eq!(response.properties.level.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn extension_is_split_off_the_id() {
+        let resource = ResourceDef::new("/api/locations/{id}.geojson");
+        let mut path = Path::new("/api/locations/5606.EG.036.geojson");
+        assert!(resource.capture_match_info(&mut path));
+        assert_eq!(path.get("id"), Some("5606.EG.036"));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION, VARY};
+use actix_web::{HttpResponse, get, web};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::details::FloorResponse;
+use super::removed::removed_location_response;
+use crate::db::indoor::IndoorLevel;
+use crate::db::location::{LocationFloors, LocationKeyAlias};
+use crate::localisation::{LangQueryArgs, LanguagePreferences};
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct LevelsPathParams {
+    /// ID of the building
+    id: String,
+}
+
+/// List the indoor levels of a building
+///
+/// Lists the levels for which rooms are mapped within the footprint of the building, lowest first.
+/// `level` is what the indoor map layers are filtered by, so clients can offer exactly the levels which exist.
+///
+/// If a level corresponds to a floor from the details of the building, its names are included.
+#[utoipa::path(
+    tags=["locations"],
+    params(LevelsPathParams, LangQueryArgs),
+    responses(
+        (status = 200, description = "**Levels** of the building", body = LevelsResponse, content_type = "application/json"),
+        (status = 308, description = "**Permanent redirect.** The requested item is an alias, or was removed but lives on under a successor"),
+        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 404, description = "**Not found.** Make sure that requested item exists and is a building", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 410, description = "**Gone.** The requested item existed, but was removed without a successor", body = String, content_type = "text/plain"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+    )
+)]
+#[get(
+    "/api/buildings/{id}/levels",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn levels_handler(
+    params: web::Path<LevelsPathParams>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let id = params
+        .id
+        .replace(|c: char| c.is_whitespace() || c.is_control(), "");
+    if id.is_empty() || id.len() > 255 {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid ID");
+    }
+    let levels_url = |key: &str| {
+        format!(
+            "/api/buildings/{key}/levels?lang={lang}",
+            lang = languages.preferred()
+        )
+    };
+    match LocationKeyAlias::fetch_optional(&data.pool, &id).await {
+        Ok(Some(alias)) => {
+            return HttpResponse::PermanentRedirect()
+                .insert_header((LOCATION, levels_url(&alias.key)))
+                .finish();
+        }
+        // not an alias, so the id is the key
+        Ok(None) => {}
+        Err(e) => {
+            error!(error = ?e, id, "error requesting alias");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    }
+    let floors = match LocationFloors::fetch_optional(&data.pool, &id, &languages.tags()).await {
+        Ok(Some(floors)) if matches!(floors.r#type.as_str(), "building" | "joined_building") => {
+            floors.floors
+        }
+        Ok(Some(_)) => {
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found");
+        }
+        Ok(None) => {
+            if let Some(response) = removed_location_response(&data.pool, &id, levels_url).await {
+                return response;
+            }
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found");
+        }
+        Err(e) => {
+            error!(error = ?e, id, "Could not get the floors");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    let floors: Vec<FloorResponse> = match floors.map(serde_json::from_value).transpose() {
+        Ok(floors) => floors.unwrap_or_default(),
+        Err(e) => {
+            error!(error = ?e, id, "Could not parse the floors, listing the levels without names");
+            Vec::new()
+        }
+    };
+    let levels = match IndoorLevel::fetch_all(&data.pool, &id).await {
+        Ok(levels) => levels,
+        Err(e) => {
+            error!(error = ?e, id, "Could not get the levels");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(24 * 60 * 60), // valid for 1d
+            CacheDirective::Public,
+        ]))
+        .insert_header((VARY, "Accept-Language"))
+        .json(LevelsResponse::new(levels, floors))
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct LevelsResponse {
+    /// Levels with mapped rooms, lowest first
+    levels: Vec<LevelResponse>,
+}
+
+impl LevelsResponse {
+    /// Pairs each level with the floor of the same `id`.
+    ///
+    /// Half levels (e.g. `0.5`) have no such floor.
+    fn new(levels: Vec<IndoorLevel>, floors: Vec<FloorResponse>) -> Self {
+        let mut floors: HashMap<i32, FloorResponse> =
+            floors.into_iter().map(|floor| (floor.id, floor)).collect();
+        let levels = levels
+            .into_iter()
+            .map(|level| {
+                #[expect(
+                    clippy::cast_possible_truncation,
+                    reason = "levels are small whole numbers here"
+                )]
+                let floor_id = (level.level.fract() == 0.0).then_some(level.level as i32);
+                LevelResponse {
+                    level: level.level,
+                    floor: floor_id.and_then(|id| floors.remove(&id)),
+                    room_count: level.room_count,
+                    bbox: [level.min_lon, level.min_lat, level.max_lon, level.max_lat],
+                }
+            })
+            .collect();
+        Self { levels }
+    }
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct LevelResponse {
+    /// The level as tagged in OpenStreetMap, `0` being the ground floor.
+    ///
+    /// This is what the indoor map layers are filtered by.
+    #[schema(examples(-1.0, 0.0, 0.5, 1.0))]
+    level: f32,
+    /// The floor of the building at this level, if known
+    floor: Option<FloorResponse>,
+    /// Number of rooms mapped on this level
+    #[schema(examples(42))]
+    room_count: i64,
+    /// Bounding box of everything mapped on this level as `[min_lon, min_lat, max_lon, max_lat]`
+    #[schema(examples(json!([11.6665, 48.2618, 11.6707, 48.2634])))]
+    bbox: [f64; 4],
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use actix_web::App;
+    use actix_web::test as web_test;
+
+    use super::*;
+    use crate::AppData;
+    use crate::setup::tests::PostgresTestContainer;
+
+    fn level(level: f32, room_count: i64) -> IndoorLevel {
+        IndoorLevel {
+            level,
+            room_count,
+            min_lon: 11.666,
+            min_lat: 48.261,
+            max_lon: 11.670,
+            max_lat: 48.263,
+        }
+    }
+
+    #[test]
+    fn levels_are_named_after_their_floor() {
+        let floors: Vec<FloorResponse> = serde_json::from_value(serde_json::json!([
+            {"id": -1, "floor": "-1", "name": "1st basement floor", "tumonline": "U1", "type": "basement"},
+            {"id": 0, "floor": "0", "name": "Ground floor", "tumonline": "EG", "type": "ground"},
+            {"id": 1, "floor": "1", "name": "1st upper floor", "tumonline": "01", "type": "upper"},
+        ]))
+        .unwrap();
+        let response =
+            LevelsResponse::new(vec![level(0.0, 12), level(0.5, 1), level(1.0, 8)], floors);
+        let named: Vec<_> = response
+            .levels
+            .iter()
+            .map(|l| (l.level, l.floor.as_ref().map(|f| f.id), l.room_count))
+            .collect();
+        assert_eq!(
+            named,
+            [(0.0, Some(0), 12), (0.5, None, 1), (1.0, Some(1), 8)]
+        );
+    }
+
+    #[actix_web::test]
+    async fn buildings_are_found_by_their_key_and_aliases() {
+        let pg = PostgresTestContainer::new().await;
+        for (key, r#type) in [("5602", "building"), ("5602.EG.001", "room")] {
+            let data = serde_json::json!({
+                "name": key,
+                "type": r#type,
+                "type_common_name": r#type,
+                "coords": {"lat": 48.262, "lon": 11.668, "source": "navigatum"},
+                "props": {"floors": [{"id": 0, "floor": "0", "name": "Ground floor", "tumonline": "EG", "type": "ground"}]},
+            });
+            sqlx::query("INSERT INTO locations (key) VALUES ($1)")
+                .bind(key)
+                .execute(&pg.pool)
+                .await
+                .unwrap();
+            sqlx::query(
+                "INSERT INTO location_data (key, lang, data) VALUES ($1, 'de', $2), ($1, 'en', $2)",
+            )
+            .bind(key)
+            .bind(&data)
+            .execute(&pg.pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query("INSERT INTO aliases(alias, key, type, visible_id) VALUES ('mi', '5602', 'building', '5602')")
+            .execute(&pg.pool)
+            .await
+            .unwrap();
+        let app = web_test::init_service(
+            App::new()
+                .app_data(web::Data::new(AppData::from(pg.pool.clone())))
+                .service(levels_handler),
+        )
+        .await;
+        let get = |id: &str| {
+            web_test::TestRequest::get()
+                .uri(&format!("/api/buildings/{id}/levels"))
+                .to_request()
+        };
+
+        let resp = web_test::call_service(&app, get("5602")).await;
+        assert_eq!(resp.status(), 200);
+        // the rooms are imported by osm2pgsql, which is not loaded in the tests
+        let levels: serde_json::Value = web_test::read_body_json(resp).await;
+        assert_eq!(levels, serde_json::json!({"levels": []}));
+
+        let resp = web_test::call_service(&app, get("mi")).await;
+        assert_eq!(resp.status(), 308);
+        assert_eq!(
+            resp.headers().get(LOCATION).unwrap(),
+            "/api/buildings/5602/levels?lang=de"
+        );
+
+        for id in ["5602.EG.001", "9999"] {
+            let resp = web_test::call_service(&app, get(id)).await;
+            assert_eq!(resp.status(), 404, "{id} is not a building");
+        }
+    }
+}
@@ -1,4 +1,11 @@
 pub mod details;
+pub mod free;
+pub mod geojson;
+pub mod levels;
 pub mod nearby;
+pub mod occupancy;
 pub mod preview;
+pub mod preview_cache;
 pub mod qr_code;
+mod removed;
+pub mod sign;
@@ -0,0 +1,606 @@
+use std::collections::HashMap;
+use std::iter;
+
+use actix_web::http::header::{CacheControl, CacheDirective, VARY};
+use actix_web::{HttpResponse, get, web};
+use chrono::{
+    DateTime, Datelike as _, Days, DurationRound as _, NaiveTime, TimeDelta, TimeZone as _, Utc,
+};
+use chrono_tz::Europe::Berlin;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::db::location::LocationKeyAlias;
+use crate::db::occupancy::{Booking, OccupancyRoom};
+use crate::localisation::{LangQueryArgs, LanguagePreferences};
+
+/// Longest time span which can be queried, a bit more than a year
+const MAX_SPAN: TimeDelta = TimeDelta::days(400);
+/// Most buckets a response may have, e.g. a semester in hours
+const MAX_BUCKETS: usize = 10_000;
+/// How many of the busiest rooms are listed
+const BUSIEST_ROOMS: usize = 10;
+
+/// A booked time, as `(start_at, end_at)`
+type Interval = (DateTime<Utc>, DateTime<Utc>);
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct OccupancyPathParams {
+    /// ID of the location (e.g. a building or an area) whose rooms should be aggregated
+    id: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, Copy, Clone, PartialEq, Eq, utoipa::ToSchema)]
+enum OccupancyBucket {
+    #[serde(rename = "15m")]
+    QuarterHour,
+    #[serde(rename = "30m")]
+    HalfHour,
+    #[default]
+    #[serde(rename = "1h")]
+    Hour,
+    #[serde(rename = "1d")]
+    Day,
+    #[serde(rename = "1w")]
+    Week,
+}
+impl OccupancyBucket {
+    fn duration(self) -> TimeDelta {
+        match self {
+            Self::QuarterHour => TimeDelta::minutes(15),
+            Self::HalfHour => TimeDelta::minutes(30),
+            Self::Hour => TimeDelta::hours(1),
+            Self::Day => TimeDelta::days(1),
+            Self::Week => TimeDelta::weeks(1),
+        }
+    }
+
+    /// The start of the bucket `at` is in.
+    ///
+    /// Days and weeks (from Monday) start at midnight in Munich, not in UTC.
+    fn start_of(self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let local = at.with_timezone(&Berlin).date_naive();
+        let date = match self {
+            // Munich is a whole number of hours off UTC, so these start at the same time in both
+            Self::QuarterHour | Self::HalfHour | Self::Hour => {
+                return at.duration_trunc(self.duration()).unwrap_or(at);
+            }
+            Self::Day => local,
+            Self::Week => local - Days::new(u64::from(local.weekday().num_days_from_monday())),
+        };
+        Berlin
+            .from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest()
+            .map_or(at, |start| start.with_timezone(&Utc))
+    }
+
+    /// The start of the bucket after the one starting at `start_at`.
+    ///
+    /// Days and weeks are stepped in Munich's local time, so a day lasts 23 or 25 hours when DST changes.
+    fn next(self, start_at: DateTime<Utc>) -> DateTime<Utc> {
+        let days = match self {
+            Self::QuarterHour | Self::HalfHour | Self::Hour => {
+                return start_at + self.duration();
+            }
+            Self::Day => Days::new(1),
+            Self::Week => Days::new(7),
+        };
+        let local = start_at.with_timezone(&Berlin).naive_local() + days;
+        // a start within the skipped hour of the DST change does not exist, then the bucket just has its nominal length
+        Berlin
+            .from_local_datetime(&local)
+            .earliest()
+            .map_or(start_at + self.duration(), |next| next.with_timezone(&Utc))
+    }
+}
+#[derive(Deserialize, Debug, Default, utoipa::IntoParams)]
+#[serde(default)]
+struct OccupancyQueryArgs {
+    /// Start of the time span. Defaults to the start of the current bucket.
+    /// Days and weeks (from Monday) start at midnight in `Europe/Berlin`.
+    ///
+    /// Buckets start at `from`, so pass a full hour or midnight for aligned buckets.
+    #[param(example = "2039-04-15T00:00:00+02:00")]
+    from: Option<DateTime<Utc>>,
+    /// End of the time span. Defaults to 7 days after `from`.
+    ///
+    /// Spans of more than 400 days or 10000 buckets are rejected.
+    #[param(example = "2039-07-20T00:00:00+02:00")]
+    to: Option<DateTime<Utc>>,
+    /// Length of a bucket
+    #[param(inline)]
+    bucket: OccupancyBucket,
+}
+
+impl OccupancyQueryArgs {
+    fn time_span(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>), HttpResponse> {
+        let from = self.from.unwrap_or_else(|| self.bucket.start_of(now));
+        // a week in Munich's local time, as with the buckets
+        let to = self.to.unwrap_or_else(|| OccupancyBucket::Week.next(from));
+        if to <= from {
+            return Err(HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body("`to` has to be after `from`"));
+        }
+        if to - from > MAX_SPAN {
+            return Err(HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body("The time span may be at most 400 days"));
+        }
+        if bucket_bounds(from, to, self.bucket)
+            .nth(MAX_BUCKETS)
+            .is_some()
+        {
+            return Err(HttpResponse::BadRequest().content_type("text/plain").body(
+                "The time span may have at most 10000 buckets, please use a larger `bucket`",
+            ));
+        }
+        Ok((from, to))
+    }
+}
+
+/// The buckets between `from` and `to`, the last one cut at `to`
+fn bucket_bounds(
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    bucket: OccupancyBucket,
+) -> impl Iterator<Item = Interval> {
+    iter::successors(Some(from), move |&start_at| Some(bucket.next(start_at)))
+        .take_while(move |&start_at| start_at < to)
+        .map(move |start_at| (start_at, bucket.next(start_at).min(to)))
+}
+
+/// Get the occupancy of a location
+///
+/// Aggregates the bookings of all rooms with a calendar below a location (e.g. a building or an area) into time buckets.
+/// Per bucket, the share of rooms which are booked at some point, the booked room-hours and (where seat counts are known) the booked seat-hours are reported.
+/// The busiest rooms of the whole time span are listed as well.
+///
+/// Blocked times (e.g. for maintenance) do not count as bookings.
+/// Since bookings are scraped from `TUMonline`, rooms whose calendar was never scraped are left out.
+#[utoipa::path(
+    tags=["locations"],
+    params(OccupancyPathParams, OccupancyQueryArgs, LangQueryArgs),
+    responses(
+        (status = 200, description = "**Occupancy** in the requested time span", body = OccupancyResponse, content_type = "application/json"),
+        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters and that the time span is valid", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 404, description = "**Not found.** Make sure that requested item exists", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+    )
+)]
+#[get(
+    "/api/locations/{id}/occupancy",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn occupancy_handler(
+    params: web::Path<OccupancyPathParams>,
+    web::Query(args): web::Query<OccupancyQueryArgs>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let id = params
+        .id
+        .replace(|c: char| c.is_whitespace() || c.is_control(), "");
+    if id.is_empty() || id.len() > 255 {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid ID");
+    }
+    let (from, to) = match args.time_span(Utc::now()) {
+        Ok(span) => span,
+        Err(e) => return e,
+    };
+    let key = match LocationKeyAlias::fetch_optional(&data.pool, &id).await {
+        Ok(Some(alias)) => alias.key,
+        Ok(None) => id,
+        Err(e) => {
+            error!(error = ?e, id, "error requesting alias");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    let rooms = match OccupancyRoom::fetch_all(&data.pool, &key, &languages.tags()).await {
+        Ok(Some(rooms)) => rooms,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found");
+        }
+        Err(e) => {
+            error!(error = ?e, key, "Could not get the rooms");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    let rooms = rooms
+        .into_iter()
+        .filter(|room| room.last_calendar_scrape_at.is_some())
+        .collect::<Vec<_>>();
+    let room_codes = rooms.iter().map(|r| r.key.clone()).collect::<Vec<_>>();
+    let bookings = match Booking::fetch_all(&data.pool, &room_codes, from, to).await {
+        Ok(bookings) => bookings,
+        Err(e) => {
+            error!(error = ?e, key, "Could not get the bookings");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(60 * 60), // valid for 1h
+            CacheDirective::Public,
+        ]))
+        .insert_header((VARY, "Accept-Language"))
+        .json(OccupancyResponse::new(
+            &rooms,
+            &bookings,
+            from,
+            to,
+            args.bucket,
+        ))
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct OccupancyResponse {
+    /// Start of the time span
+    #[schema(examples("2039-04-14T22:00:00Z"))]
+    from: DateTime<Utc>,
+    /// End of the time span
+    #[schema(examples("2039-07-19T22:00:00Z"))]
+    to: DateTime<Utc>,
+    /// Length of a bucket
+    bucket: OccupancyBucket,
+    /// Number of rooms with a calendar, which the occupancy is aggregated over
+    #[schema(examples(42))]
+    room_count: usize,
+    /// Number of these rooms whose seat count is known
+    #[schema(examples(30))]
+    rooms_with_seats: usize,
+    /// The occupancy per bucket, in chronological order
+    buckets: Vec<OccupancyBucketResponse>,
+    /// The rooms booked the longest during the time span, busiest first
+    busiest_rooms: Vec<BusyRoomResponse>,
+    /// Oldest scrape of the calendars of the rooms
+    #[schema(examples("2039-01-19T12:14:07Z"))]
+    oldest_calendar_scrape_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, PartialEq, utoipa::ToSchema)]
+struct OccupancyBucketResponse {
+    #[schema(examples("2039-04-15T08:00:00Z"))]
+    start_at: DateTime<Utc>,
+    /// The last bucket may be shorter, if the time span is not a multiple of the bucket
+    #[schema(examples("2039-04-15T09:00:00Z"))]
+    end_at: DateTime<Utc>,
+    /// Number of rooms booked at some point in this bucket
+    #[schema(examples(21))]
+    booked_rooms: usize,
+    /// `booked_rooms` as a share of `room_count`, between 0 and 1
+    #[schema(examples(0.5))]
+    booked_share: f64,
+    /// Sum of the hours each room is booked in this bucket
+    #[schema(examples(17.5))]
+    booked_room_hours: f64,
+    /// Sum of the hours each room is booked in this bucket, weighted by its seats.
+    ///
+    /// Only rooms whose seat count is known are included, `null` if there are none.
+    #[schema(examples(1234.5))]
+    booked_seat_hours: Option<f64>,
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct BusyRoomResponse {
+    /// ID of the room
+    #[schema(examples("5602.EG.001"))]
+    key: String,
+    /// Name of the room
+    #[schema(examples("5602.EG.001 (MI HS 1, Friedrich L. Bauer Hörsaal)"))]
+    name: String,
+    /// Number of seats, if known
+    #[schema(examples(754))]
+    seats: Option<i32>,
+    /// Hours the room is booked during the time span
+    #[schema(examples(312.5))]
+    booked_hours: f64,
+    /// `booked_hours` as a share of the time span, between 0 and 1
+    #[schema(examples(0.14))]
+    booked_share: f64,
+}
+
+impl OccupancyResponse {
+    fn new(
+        rooms: &[OccupancyRoom],
+        bookings: &[Booking],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: OccupancyBucket,
+    ) -> Self {
+        let mut buckets = bucket_bounds(from, to, bucket)
+            .map(|(start_at, end_at)| OccupancyBucketResponse {
+                start_at,
+                end_at,
+                booked_rooms: 0,
+                booked_share: 0.0,
+                booked_room_hours: 0.0,
+                booked_seat_hours: None,
+            })
+            .collect::<Vec<_>>();
+        let mut bookings_by_room: HashMap<&str, Vec<Interval>> = HashMap::new();
+        for booking in bookings {
+            bookings_by_room
+                .entry(booking.room_code.as_str())
+                .or_default()
+                .push((booking.start_at.max(from), booking.end_at.min(to)));
+        }
+        let has_seats = rooms.iter().any(|room| room.seats.is_some());
+        let mut busiest_rooms = Vec::new();
+        for room in rooms {
+            let booked = bookings_by_room
+                .remove(room.key.as_str())
+                .map(merge_overlapping)
+                .unwrap_or_default();
+            let mut booked_hours = 0.0;
+            // a room is counted once per bucket, even if booked several times in it
+            let mut last_counted_bucket = None;
+            for (start_at, end_at) in booked {
+                booked_hours += hours(end_at - start_at);
+                let mut index = buckets.partition_point(|bucket| bucket.end_at <= start_at);
+                while let Some(bucket) = buckets.get_mut(index)
+                    && bucket.start_at < end_at
+                {
+                    let overlap = hours(end_at.min(bucket.end_at) - start_at.max(bucket.start_at));
+                    bucket.booked_room_hours += overlap;
+                    if let Some(seats) = room.seats {
+                        *bucket.booked_seat_hours.get_or_insert(0.0) += overlap * f64::from(seats);
+                    }
+                    if last_counted_bucket != Some(index) {
+                        bucket.booked_rooms += 1;
+                        last_counted_bucket = Some(index);
+                    }
+                    index += 1;
+                }
+            }
+            if booked_hours > 0.0 {
+                busiest_rooms.push(BusyRoomResponse {
+                    key: room.key.clone(),
+                    name: room.name.clone(),
+                    seats: room.seats,
+                    booked_hours,
+                    booked_share: booked_hours / hours(to - from),
+                });
+            }
+        }
+        #[expect(clippy::cast_precision_loss, reason = "room counts are far below 2^52")]
+        let room_count = rooms.len() as f64;
+        for bucket in &mut buckets {
+            if room_count > 0.0 {
+                #[expect(clippy::cast_precision_loss, reason = "room counts are far below 2^52")]
+                let booked_rooms = bucket.booked_rooms as f64;
+                bucket.booked_share = booked_rooms / room_count;
+            }
+            if has_seats && bucket.booked_seat_hours.is_none() {
+                bucket.booked_seat_hours = Some(0.0);
+            }
+        }
+        busiest_rooms.sort_by(|a, b| {
+            b.booked_hours
+                .total_cmp(&a.booked_hours)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        busiest_rooms.truncate(BUSIEST_ROOMS);
+        Self {
+            from,
+            to,
+            bucket,
+            room_count: rooms.len(),
+            rooms_with_seats: rooms.iter().filter(|room| room.seats.is_some()).count(),
+            buckets,
+            busiest_rooms,
+            oldest_calendar_scrape_at: rooms
+                .iter()
+                .filter_map(|room| room.last_calendar_scrape_at)
+                .min(),
+        }
+    }
+}
+
+/// Merges overlapping or touching bookings, so that double bookings are not counted twice
+fn merge_overlapping(mut bookings: Vec<Interval>) -> Vec<Interval> {
+    bookings.sort_unstable();
+    let mut merged: Vec<Interval> = Vec::with_capacity(bookings.len());
+    for (start_at, end_at) in bookings {
+        if start_at >= end_at {
+            continue;
+        }
+        match merged.last_mut() {
+            Some((_, last_end_at)) if start_at <= *last_end_at => {
+                *last_end_at = (*last_end_at).max(end_at);
+            }
+            _ => merged.push((start_at, end_at)),
+        }
+    }
+    merged
+}
+
+#[expect(
+    clippy::cast_precision_loss,
+    reason = "durations are far below 2^52 seconds"
+)]
+fn hours(duration: TimeDelta) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2039, 4, 15, hour, minute, 0).unwrap()
+    }
+
+    fn room(key: &str, seats: Option<i32>) -> OccupancyRoom {
+        OccupancyRoom {
+            key: key.to_string(),
+            name: key.to_string(),
+            seats,
+            last_calendar_scrape_at: Some(at(0, 0)),
+        }
+    }
+
+    fn booking(room_code: &str, start_at: DateTime<Utc>, end_at: DateTime<Utc>) -> Booking {
+        Booking {
+            room_code: room_code.to_string(),
+            start_at,
+            end_at,
+        }
+    }
+
+    #[test]
+    fn bookings_are_aggregated_into_buckets() {
+        let rooms = [
+            room("hs1", Some(100)),
+            room("sr1", None),
+            room("sr2", Some(20)),
+        ];
+        let bookings = [
+            // spans two buckets
+            booking("hs1", at(8, 30), at(10, 0)),
+            // double booked, must not be counted twice
+            booking("sr1", at(8, 0), at(9, 0)),
+            booking("sr1", at(8, 15), at(8, 45)),
+            // starts before the time span
+            booking("sr2", at(6, 0), at(8, 30)),
+        ];
+        let response = OccupancyResponse::new(
+            &rooms,
+            &bookings,
+            at(8, 0),
+            at(10, 0),
+            OccupancyBucket::Hour,
+        );
+        let buckets = response
+            .buckets
+            .iter()
+            .map(|b| {
+                (
+                    b.booked_rooms,
+                    b.booked_share,
+                    b.booked_room_hours,
+                    b.booked_seat_hours,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            [(3, 1.0, 2.0, Some(60.0)), (1, 1.0 / 3.0, 1.0, Some(100.0))]
+        );
+        assert_eq!(response.rooms_with_seats, 2);
+        let busiest = response
+            .busiest_rooms
+            .iter()
+            .map(|r| (r.key.as_str(), r.booked_hours))
+            .collect::<Vec<_>>();
+        assert_eq!(busiest, [("hs1", 1.5), ("sr1", 1.0), ("sr2", 0.5)]);
+    }
+
+    #[test]
+    fn the_last_bucket_is_cut_at_to() {
+        let response = OccupancyResponse::new(
+            &[room("hs1", None)],
+            &[booking("hs1", at(9, 0), at(12, 0))],
+            at(8, 0),
+            at(9, 30),
+            OccupancyBucket::Hour,
+        );
+        let buckets = response
+            .buckets
+            .iter()
+            .map(|b| (b.end_at, b.booked_room_hours, b.booked_seat_hours))
+            .collect::<Vec<_>>();
+        assert_eq!(buckets, [(at(9, 0), 0.0, None), (at(9, 30), 0.5, None)]);
+    }
+
+    #[test]
+    fn days_and_weeks_start_at_midnight_in_munich() {
+        // a Friday, 12:00 in Munich
+        let now = at(10, 0);
+        assert_eq!(
+            OccupancyBucket::Hour.start_of(now + TimeDelta::minutes(20)),
+            now
+        );
+        assert_eq!(
+            OccupancyBucket::Day.start_of(now),
+            Utc.with_ymd_and_hms(2039, 4, 14, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            OccupancyBucket::Week.start_of(now),
+            Utc.with_ymd_and_hms(2039, 4, 10, 22, 0, 0).unwrap()
+        );
+        // before midnight in UTC, but already the next day in Munich
+        assert_eq!(
+            OccupancyBucket::Day.start_of(Utc.with_ymd_and_hms(2039, 1, 9, 23, 30, 0).unwrap()),
+            Utc.with_ymd_and_hms(2039, 1, 9, 23, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn days_follow_the_dst_changes() {
+        let bounds = |from, to, bucket| {
+            bucket_bounds(from, to, bucket)
+                .map(|(start_at, end_at)| (start_at, hours(end_at - start_at)))
+                .collect::<Vec<_>>()
+        };
+        // the 27th of March 2039 has 23 hours in Munich
+        let spring = Utc.with_ymd_and_hms(2039, 3, 25, 23, 0, 0).unwrap();
+        assert_eq!(
+            bounds(spring, spring + TimeDelta::hours(71), OccupancyBucket::Day),
+            [
+                (spring, 24.0),
+                (Utc.with_ymd_and_hms(2039, 3, 26, 23, 0, 0).unwrap(), 23.0),
+                (Utc.with_ymd_and_hms(2039, 3, 27, 22, 0, 0).unwrap(), 24.0),
+            ]
+        );
+        // the 30th of October 2039 has 25 hours in Munich
+        let autumn = Utc.with_ymd_and_hms(2039, 10, 29, 22, 0, 0).unwrap();
+        assert_eq!(
+            bounds(autumn, autumn + TimeDelta::hours(49), OccupancyBucket::Day),
+            [
+                (autumn, 25.0),
+                (Utc.with_ymd_and_hms(2039, 10, 30, 23, 0, 0).unwrap(), 24.0),
+            ]
+        );
+        // a week spanning the change is an hour shorter, the next one starts at midnight again
+        let week = Utc.with_ymd_and_hms(2039, 3, 20, 23, 0, 0).unwrap();
+        assert_eq!(
+            bounds(week, week + TimeDelta::weeks(2), OccupancyBucket::Week),
+            [
+                (week, 167.0),
+                (Utc.with_ymd_and_hms(2039, 3, 27, 22, 0, 0).unwrap(), 168.0),
+                (Utc.with_ymd_and_hms(2039, 4, 3, 22, 0, 0).unwrap(), 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_default_span_is_a_week_from_the_current_local_day() {
+        let args = OccupancyQueryArgs {
+            bucket: OccupancyBucket::Day,
+            ..OccupancyQueryArgs::default()
+        };
+        // a Friday before the DST change on Sunday
+        let now = Utc.with_ymd_and_hms(2039, 3, 25, 10, 0, 0).unwrap();
+        assert_eq!(
+            args.time_span(now).ok(),
+            Some((
+                Utc.with_ymd_and_hms(2039, 3, 24, 23, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2039, 3, 31, 22, 0, 0).unwrap(),
+            ))
+        );
+    }
+}
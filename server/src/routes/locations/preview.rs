@@ -1,70 +1,107 @@
-use std::fmt::{self, Display, Formatter};
+use std::env;
+use std::fmt::{self, Display, Formatter, Write as _};
 use std::io::Cursor;
 
-use crate::db::location::{Location, LocationKeyAlias};
+use super::preview_cache::{PreviewCache, RenderedPreview};
+use super::removed::removed_location_response;
+use crate::db::geojson::LocationFeature;
+use crate::db::location::{
+    Location, LocationContext, LocationKeyAlias, LocationRevision, TopRankedLocation,
+};
 use crate::limited::vec::LimitedVec;
-use crate::localisation::LanguageOptions;
-use crate::overlays::map::OverlayMapTask;
+use crate::localisation::{LangQueryArgs, Language, LanguageOptions, LanguagePreferences};
+use crate::overlays::map::{Basemap, OverlayMapTask};
 use crate::overlays::text::{CANTARELL_BOLD, CANTARELL_REGULAR, OverlayText};
-use actix_web::http::header::{CacheControl, CacheDirective, LOCATION};
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION, VARY};
 use actix_web::{HttpResponse, get, web};
-use image::{ImageBuffer, Rgba, imageops};
+use bytes::Bytes;
+use image::codecs::avif::AvifEncoder;
+use image::imageops::FilterType;
+use image::{ImageBuffer, ImageFormat, Rgba, imageops};
 use serde::Deserialize;
+use serde_json::Value;
 use sqlx::PgPool;
-use tracing::{error, warn};
-use unicode_truncate::UnicodeTruncateStr as _;
+use tracing::{error, info, warn};
 use utoipa::ToSchema;
 
-#[tracing::instrument]
+/// Height of the panel with the logo and name at the bottom
+const BOTTOM_PANEL_HEIGHT: u32 = 125;
+/// Additional height of the bottom panel, if the breadcrumb is shown
+const BREADCRUMB_HEIGHT: u32 = 35;
+/// Limits for `width` and `height`
+const MIN_SIZE: u32 = 300;
+const MAX_SIZE: u32 = 2400;
+const TUM_BLUE: Rgba<u8> = Rgba([0x30, 0x70, 0xB3, 0xFF]);
+
+/// Everything drawn onto the preview
+#[derive(Debug)]
+struct PreviewContent {
+    location: Location,
+    /// The parents, as shown above the name
+    breadcrumb: Option<String>,
+    floor_name: Option<String>,
+    /// `GeoJSON` geometry of the room, outlined on the map
+    highlight: Option<Value>,
+    /// Level of the indoor map, the floor of the highlighted room or the ground floor
+    level: f32,
+}
+
+#[tracing::instrument(skip(pool))]
 async fn construct_image_from_data(
-    data: Location,
-    format: PreviewFormat,
-) -> Option<LimitedVec<u8>> {
-    let mut img = match format {
-        PreviewFormat::OpenGraph => image::RgbaImage::new(1200, 630),
-        PreviewFormat::Square => image::RgbaImage::new(1200, 1200),
+    pool: &PgPool,
+    content: PreviewContent,
+    args: &QueryArgs,
+    (width, height): (u32, u32),
+) -> Option<(image::RgbaImage, Basemap)> {
+    let mut img = image::RgbaImage::new(width, height);
+    let panel_height = if content.breadcrumb.is_some() {
+        BOTTOM_PANEL_HEIGHT + BREADCRUMB_HEIGHT
+    } else {
+        BOTTOM_PANEL_HEIGHT
     };
 
     // add the map
-    if !OverlayMapTask::new(&data.r#type, data.lat, data.lon)
-        .draw_onto(&mut img)
-        .await
-    {
-        return None;
+    let map = OverlayMapTask::new(
+        &content.location.r#type,
+        content.location.lat,
+        content.location.lon,
+    );
+    let basemap = map
+        .draw_onto(pool, &mut img, panel_height, content.level)
+        .await?;
+    if let Some(highlight) = &content.highlight {
+        map.draw_highlight(&mut img, panel_height, highlight, TUM_BLUE);
     }
-    draw_pin(&mut img);
+    draw_pin(&mut img, panel_height);
 
-    draw_bottom(&data, &mut img);
-    Some(wrap_image_in_response(&img))
+    draw_bottom(&content, args.theme, panel_height, &mut img);
+    Some((img, basemap))
 }
 
 /// add the location pin image to the center
 #[tracing::instrument(skip(img),level = tracing::Level::DEBUG, )]
-fn draw_pin(img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
+fn draw_pin(img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>, panel_height: u32) {
     let pin = image::load_from_memory(include_bytes!("static/pin.png"))
         .expect("static pin.png must decode at compile time");
     imageops::overlay(
         img,
         &pin,
         i64::from(img.width()) / 2 - i64::from(pin.width()) / 2,
-        (i64::from(img.height()) - 125) / 2 - i64::from(pin.height()),
+        (i64::from(img.height()) - i64::from(panel_height)) / 2 - i64::from(pin.height()),
     );
 }
 
-fn wrap_image_in_response(img: &image::RgbaImage) -> LimitedVec<u8> {
-    let mut w = Cursor::new(Vec::new());
-    img.write_to(&mut w, image::ImageFormat::Png)
-        .expect("writing PNG to an in-memory cursor is infallible");
-    LimitedVec(w.into_inner())
-}
-const WHITE_PIXEL: Rgba<u8> = Rgba([255, 255, 255, 255]);
-
 #[tracing::instrument(skip(img),level = tracing::Level::DEBUG)]
-fn draw_bottom(data: &Location, img: &mut image::RgbaImage) {
-    // draw background white
+fn draw_bottom(
+    content: &PreviewContent,
+    theme: PreviewTheme,
+    panel_height: u32,
+    img: &mut image::RgbaImage,
+) {
+    // draw background
     for x in 0..img.width() {
-        for y in img.height() - 125..img.height() {
-            img.put_pixel(x, y, WHITE_PIXEL);
+        for y in img.height() - panel_height..img.height() {
+            img.put_pixel(x, y, theme.background());
         }
     }
     // add our logo so the bottom
@@ -74,44 +111,51 @@ fn draw_bottom(data: &Location, img: &mut image::RgbaImage) {
         img,
         &logo,
         15,
-        i64::from(img.height()) - (125 / 2) - (i64::from(logo.height()) / 2) + 9,
+        i64::from(img.height()) - (i64::from(panel_height) / 2) - (i64::from(logo.height()) / 2)
+            + 9,
     );
-    let name = if data.name.chars().count() >= 45 {
-        format!("{}...", data.name.unicode_truncate(45).0)
-    } else {
-        data.name.clone()
-    };
-    OverlayText::with(&name, &CANTARELL_BOLD)
+    // texts are right-aligned, so they must not overlap the logo
+    let text_width = img.width().saturating_sub(15 + logo.width() + 30);
+    let location = &content.location;
+    OverlayText::with(&location.name, &CANTARELL_BOLD)
+        .color(theme.text())
+        .truncated_to(text_width)
         .at(10, 125 - 10)
         .draw_onto(img);
-    OverlayText::with(&data.type_common_name, &CANTARELL_REGULAR)
+    let type_name = match &content.floor_name {
+        Some(floor) => format!("{} · {floor}", location.type_common_name),
+        None => location.type_common_name.clone(),
+    };
+    OverlayText::with(&type_name, &CANTARELL_REGULAR)
+        .color(theme.text())
+        .truncated_to(text_width)
         .at(10, 125 - 50)
         .draw_onto(img);
+    if let Some(breadcrumb) = &content.breadcrumb {
+        OverlayText::with(breadcrumb, &CANTARELL_REGULAR)
+            .size(24.0)
+            .color(theme.secondary_text())
+            .truncated_to(text_width)
+            .at(10, (125 + BREADCRUMB_HEIGHT - 10).cast_signed())
+            .draw_onto(img);
+    }
 }
 
-fn load_default_image() -> LimitedVec<u8> {
+fn load_default_image((width, height): (u32, u32)) -> image::RgbaImage {
     warn!(
-        "Loading default preview image, as map rendering failed. Check the connection to the tileserver"
+        "Loading default preview image, as map rendering failed. Check the connection to the tileserver and the indoor tables"
     );
-    let img = image::load_from_memory(include_bytes!("static/logo-card.png"))
-        .expect("static logo-card.png must decode at compile time");
-    // encode the image as PNG
-    let mut w = Cursor::new(Vec::new());
-    img.write_to(&mut w, image::ImageFormat::Png)
-        .expect("writing PNG to an in-memory cursor is infallible");
-    LimitedVec(w.into_inner())
+    image::load_from_memory(include_bytes!("static/logo-card.png"))
+        .expect("static logo-card.png must decode at compile time")
+        .resize_to_fill(width, height, FilterType::Lanczos3)
+        .into_rgba8()
 }
 
 #[tracing::instrument(skip(pool))]
 async fn get_possible_redirect_url(pool: &PgPool, query: &str, args: &QueryArgs) -> Option<String> {
     let result = LocationKeyAlias::fetch_optional(pool, query).await;
     match result {
-        Ok(Some(d)) => Some(format!(
-            "https://nav.tum.de/api/locations/{key}/preview?lang={lang}&format={format}",
-            key = d.key,
-            lang = args.lang,
-            format = args.format
-        )),
+        Ok(Some(d)) => Some(preview_url(&d.key, args)),
         Ok(None) => None,
         Err(e) => {
             error!(error = ?e, query, "error requesting alias");
@@ -120,13 +164,52 @@ async fn get_possible_redirect_url(pool: &PgPool, query: &str, args: &QueryArgs)
     }
 }
 
+/// Relative, so that redirects stay on the deployment which was asked, like the ones of the details
+fn preview_url(key: &str, args: &QueryArgs) -> String {
+    let mut url = format!(
+        "/api/locations/{key}/preview?lang={lang}&format={format}",
+        lang = args.languages.preferred(),
+        format = args.format
+    );
+    let write_error = "writing to a String is infallible";
+    if let Some(width) = args.width {
+        write!(url, "&width={width}").expect(write_error);
+    }
+    if let Some(height) = args.height {
+        write!(url, "&height={height}").expect(write_error);
+    }
+    if args.encoding != PreviewEncoding::default() {
+        write!(url, "&encoding={encoding}", encoding = args.encoding).expect(write_error);
+    }
+    if args.theme != PreviewTheme::default() {
+        write!(url, "&theme={theme}", theme = args.theme).expect(write_error);
+    }
+    if args.breadcrumb {
+        url.push_str("&breadcrumb=true");
+    }
+    if args.highlight_floor {
+        url.push_str("&highlight_floor=true");
+    }
+    url
+}
+
 #[derive(Deserialize, Default, Debug, Copy, Clone, ToSchema)]
 #[serde(rename_all = "snake_case")]
 enum PreviewFormat {
+    /// 1200x630px
     #[default]
     OpenGraph,
+    /// 1200x1200px
     Square,
 }
+impl PreviewFormat {
+    fn size(self) -> (u32, u32) {
+        match self {
+            Self::OpenGraph => (1200, 630),
+            Self::Square => (1200, 1200),
+        }
+    }
+}
 impl Display for PreviewFormat {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
@@ -136,14 +219,130 @@ impl Display for PreviewFormat {
     }
 }
 
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum PreviewEncoding {
+    #[default]
+    Png,
+    /// Lossless `WebP`
+    Webp,
+    Avif,
+}
+impl PreviewEncoding {
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+            Self::Avif => "image/avif",
+        }
+    }
+
+    fn encode(self, img: &image::RgbaImage) -> anyhow::Result<LimitedVec<u8>> {
+        let mut w = Cursor::new(Vec::new());
+        match self {
+            Self::Png => img.write_to(&mut w, ImageFormat::Png)?,
+            Self::Webp => img.write_to(&mut w, ImageFormat::WebP)?,
+            Self::Avif => {
+                // the fastest speed, as encoding happens while the client waits
+                let encoder = AvifEncoder::new_with_speed_quality(&mut w, 10, 80);
+                img.write_with_encoder(encoder)?;
+            }
+        }
+        Ok(LimitedVec(w.into_inner()))
+    }
+}
+impl Display for PreviewEncoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Png => f.write_str("png"),
+            Self::Webp => f.write_str("webp"),
+            Self::Avif => f.write_str("avif"),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum PreviewTheme {
+    #[default]
+    Light,
+    Dark,
+}
+impl PreviewTheme {
+    fn background(self) -> Rgba<u8> {
+        match self {
+            Self::Light => Rgba([0xFF, 0xFF, 0xFF, 0xFF]),
+            Self::Dark => Rgba([0x1B, 0x1E, 0x23, 0xFF]),
+        }
+    }
+    fn text(self) -> Rgba<u8> {
+        match self {
+            Self::Light => Rgba([0x00, 0x00, 0x00, 0xFF]),
+            Self::Dark => Rgba([0xFF, 0xFF, 0xFF, 0xFF]),
+        }
+    }
+    fn secondary_text(self) -> Rgba<u8> {
+        match self {
+            Self::Light => Rgba([0x5A, 0x5F, 0x66, 0xFF]),
+            Self::Dark => Rgba([0xB0, 0xB6, 0xBF, 0xFF]),
+        }
+    }
+}
+impl Display for PreviewTheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Light => f.write_str("light"),
+            Self::Dark => f.write_str("dark"),
+        }
+    }
+}
+
 #[derive(Deserialize, Default, Debug, utoipa::IntoParams)]
 #[serde(default)]
 struct QueryArgs {
-    #[serde(default)]
-    #[param(inline)]
-    lang: LanguageOptions,
+    /// The languages of the name, type and breadcrumb.
+    ///
+    /// Negotiated from `lang` and `Accept-Language`, see [`LangQueryArgs`]
+    #[serde(skip)]
+    #[param(ignore)]
+    languages: LanguagePreferences,
     #[param(inline)]
     format: PreviewFormat,
+    /// Width in pixels, overriding the width of `format`
+    #[param(minimum = 300, maximum = 2400)]
+    width: Option<u32>,
+    /// Height in pixels, overriding the height of `format`
+    #[param(minimum = 300, maximum = 2400)]
+    height: Option<u32>,
+    #[param(inline)]
+    encoding: PreviewEncoding,
+    /// Colors of the panel below the map
+    #[param(inline)]
+    theme: PreviewTheme,
+    /// Show the parents of the location (e.g. campus and building) above its name
+    breadcrumb: bool,
+    /// For rooms: outline the room on the map and name its floor
+    highlight_floor: bool,
+}
+impl QueryArgs {
+    fn size(&self) -> Result<(u32, u32), HttpResponse> {
+        let (default_width, default_height) = self.format.size();
+        let size = (
+            self.width.unwrap_or(default_width),
+            self.height.unwrap_or(default_height),
+        );
+        if [size.0, size.1]
+            .iter()
+            .any(|s| !(MIN_SIZE..=MAX_SIZE).contains(s))
+        {
+            return Err(HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body(format!(
+                    "width and height have to be between {MIN_SIZE} and {MAX_SIZE} pixels"
+                )));
+        }
+        Ok(size)
+    }
 }
 
 #[derive(Deserialize, utoipa::IntoParams)]
@@ -153,16 +352,20 @@ struct MapsPathParams {
 
 /// Get a entry-preview
 ///
-/// This returns a 1200x630px preview for the location (room/building/..).
+/// This returns a preview for the location (room/building/..): a map with the name of the location below.
+/// The size defaults to 1200x630px (`format=open_graph`) and can be adjusted via `format`, `width` and `height`.
 ///
-/// This is usefully for implementing custom `OpenGraph` images for detail previews.
+/// This is usefully for implementing custom `OpenGraph` images for detail previews, or for digital signage.
 #[utoipa::path(
     tags=["locations"],
-    params(MapsPathParams, QueryArgs),
+    params(MapsPathParams, QueryArgs, LangQueryArgs),
     responses(
-        (status = 200, description = "**Preview image**", content_type="image/png"),
-        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 200, description = "**Preview image**", content(("image/png"), ("image/webp"), ("image/avif"))),
+        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters, and that the size is within the limits", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 308, description = "**Permanent redirect.** The requested item is an alias, or was removed but lives on under a successor"),
         (status = 404, description = "**Not found.** Make sure that requested item exists", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 410, description = "**Gone.** The requested item existed, but was removed without a successor", body = String, content_type = "text/plain"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
     )
 )]
 #[get(
@@ -171,9 +374,11 @@ struct MapsPathParams {
 )]
 pub async fn maps_handler(
     params: web::Path<MapsPathParams>,
-    args: web::Query<QueryArgs>,
+    web::Query(mut args): web::Query<QueryArgs>,
+    languages: LanguagePreferences,
     data: web::Data<crate::AppData>,
 ) -> HttpResponse {
+    args.languages = languages;
     let id = params
         .id
         .replace(|c: char| c.is_whitespace() || c.is_control(), "");
@@ -182,35 +387,274 @@ pub async fn maps_handler(
             .content_type("text/plain")
             .body("Invalid ID");
     }
+    let size = match args.size() {
+        Ok(size) => size,
+        Err(e) => return e,
+    };
 
     if let Some(redirect_url) = get_possible_redirect_url(&data.pool, &id, &args).await {
         return HttpResponse::PermanentRedirect()
             .insert_header((LOCATION, redirect_url))
             .finish();
     }
-    let data =
-        match Location::fetch_optional(&data.pool, &id, args.lang == LanguageOptions::En).await {
-            Ok(Some(data)) => data,
-            Ok(None) => {
-                return HttpResponse::NotFound()
-                    .content_type("text/plain")
-                    .body("Not found");
+    let location = match Location::fetch_optional(&data.pool, &id, &args.languages.tags()).await {
+        Ok(Some(location)) => location,
+        Ok(None) => {
+            if let Some(response) = removed_location_response(&data.pool, &id, |successor| {
+                preview_url(successor, &args)
+            })
+            .await
+            {
+                return response;
+            }
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found");
+        }
+        Err(e) => {
+            error!(error = ?e, "Error preparing statement");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Could not get data for location, please try again later");
+        }
+    };
+    let url = preview_url(&id, &args);
+    // boxed, as rendering keeps large buffers across awaits
+    let render = Box::pin(render_preview(&data.pool, &id, location, &args, size));
+    // custom sizes are arbitrary, so caching them would only fill the cache
+    let rendered = if args.width.is_some() || args.height.is_some() {
+        render.await.map(|preview| preview.image)
+    } else {
+        match LocationRevision::fetch_optional(&data.pool, &id).await {
+            Ok(revision) => {
+                let hash = revision.and_then(|r| r.hash).unwrap_or_default();
+                data.preview_cache.get_or_render(&url, hash, render).await
             }
             Err(e) => {
-                error!(error = ?e, "Error preparing statement");
-                return HttpResponse::InternalServerError()
-                    .content_type("text/plain")
-                    .body("Could not get data for location, please try again later");
+                error!(error = ?e, id, "Could not get the revision, rendering without the cache");
+                render.await.map(|preview| preview.image)
             }
-        };
-    let img = construct_image_from_data(data, args.format)
-        .await
-        .unwrap_or_else(load_default_image);
+        }
+    };
+    let Some(img) = rendered else {
+        return HttpResponse::InternalServerError()
+            .content_type("text/plain")
+            .body("Failed to encode the preview");
+    };
     HttpResponse::Ok()
-        .content_type("image/png")
+        .content_type(args.encoding.content_type())
         .insert_header(CacheControl(vec![
             CacheDirective::MaxAge(2 * 24 * 60 * 60), // valid for 2d
             CacheDirective::Public,
         ]))
-        .body(img.0)
+        .insert_header((VARY, "Accept-Language"))
+        .body(img)
+}
+
+/// Renders and encodes the preview, `None` if encoding failed.
+///
+/// If no basemap could be rendered, the logo is used instead.
+#[tracing::instrument(skip(pool, location))]
+async fn render_preview(
+    pool: &PgPool,
+    key: &str,
+    location: Location,
+    args: &QueryArgs,
+    size: (u32, u32),
+) -> Option<RenderedPreview> {
+    let content = preview_content(pool, key, location, args).await;
+    let (img, is_fallback) = match construct_image_from_data(pool, content, args, size).await {
+        Some((img, basemap)) => (img, basemap == Basemap::Local),
+        None => (load_default_image(size), true),
+    };
+    match args.encoding.encode(&img) {
+        Ok(img) => Some(RenderedPreview {
+            image: Bytes::from(img.0),
+            is_fallback,
+        }),
+        Err(e) => {
+            error!(error = ?e, encoding = %args.encoding, "Failed to encode the preview");
+            None
+        }
+    }
+}
+
+/// Renders the default previews of the top-ranked entries into `cache`.
+///
+/// Links to them are the most likely to be shared, so unfurls after a data update do not have to wait for rendering.
+/// The number of entries is configured via `PREVIEW_PRERENDER_COUNT` (default 100).
+#[tracing::instrument(skip(pool, cache))]
+pub async fn prerender_top_ranked(pool: &PgPool, cache: &PreviewCache) {
+    let count = env::var("PREVIEW_PRERENDER_COUNT")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(100);
+    let top_ranked = match TopRankedLocation::fetch_all(pool, count).await {
+        Ok(top_ranked) => top_ranked,
+        Err(e) => {
+            error!(error = ?e, "Could not get the top-ranked entries to pre-render");
+            return;
+        }
+    };
+    let mut rendered = 0;
+    for entry in &top_ranked {
+        for lang in [LanguageOptions::De, LanguageOptions::En] {
+            let args = QueryArgs {
+                languages: Language::from(lang).into(),
+                ..QueryArgs::default()
+            };
+            let location = match Location::fetch_optional(pool, &entry.key, &args.languages.tags())
+                .await
+            {
+                Ok(Some(location)) => location,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!(error = ?e, key = entry.key, "Could not get the entry to pre-render");
+                    continue;
+                }
+            };
+            let render = Box::pin(render_preview(
+                pool,
+                &entry.key,
+                location,
+                &args,
+                args.format.size(),
+            ));
+            let url = preview_url(&entry.key, &args);
+            if cache
+                .get_or_render(&url, entry.hash.unwrap_or_default(), render)
+                .await
+                .is_some()
+            {
+                rendered += 1;
+            }
+        }
+    }
+    info!(
+        rendered,
+        entries = top_ranked.len(),
+        "pre-rendered previews"
+    );
+}
+
+/// Fetches the optional parts of the preview.
+///
+/// They are decorative, so failing to get them only degrades the preview.
+#[tracing::instrument(skip(pool, location))]
+async fn preview_content(
+    pool: &PgPool,
+    key: &str,
+    location: Location,
+    args: &QueryArgs,
+) -> PreviewContent {
+    let languages = args.languages.tags();
+    let context = if args.breadcrumb || args.highlight_floor {
+        LocationContext::fetch_optional(pool, key, &languages)
+            .await
+            .unwrap_or_else(|e| {
+                error!(error = ?e, key, "Could not get the context of the location");
+                None
+            })
+    } else {
+        None
+    };
+    let highlights_room = args.highlight_floor && location.r#type == "room";
+    let highlight = if highlights_room {
+        match LocationFeature::fetch_subtree(pool, key, false, &languages).await {
+            Ok(features) => features.into_iter().next().and_then(|f| f.polygon),
+            Err(e) => {
+                error!(error = ?e, key, "Could not get the polygon of the room");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let (breadcrumb, floor_name, level) = match context {
+        Some(context) => (
+            args.breadcrumb
+                .then(|| breadcrumb(&context.parent_names))
+                .flatten(),
+            context.floor_name.filter(|_| highlights_room),
+            context.floor_level.filter(|_| highlights_room),
+        ),
+        None => (None, None, None),
+    };
+    PreviewContent {
+        location,
+        breadcrumb,
+        floor_name,
+        highlight,
+        level: level.unwrap_or_default(),
+    }
+}
+
+/// The parents joined as a breadcrumb, without the root which every location shares
+fn breadcrumb(parent_names: &[String]) -> Option<String> {
+    let parents = parent_names.get(1..).unwrap_or_default();
+    (!parents.is_empty()).then(|| parents.join(" › "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breadcrumb_skips_the_root() {
+        let names = [
+            "Standorte",
+            "Garching Forschungszentrum",
+            "Mathematik / Informatik",
+        ]
+        .map(String::from);
+        assert_eq!(
+            breadcrumb(&names).as_deref(),
+            Some("Garching Forschungszentrum › Mathematik / Informatik")
+        );
+        assert_eq!(breadcrumb(&names[..1]), None);
+        assert_eq!(breadcrumb(&[]), None);
+    }
+
+    #[test]
+    fn sizes_are_limited() {
+        let args = |width, height| QueryArgs {
+            width,
+            height,
+            format: PreviewFormat::Square,
+            ..QueryArgs::default()
+        };
+        assert_eq!(args(None, None).size().ok(), Some((1200, 1200)));
+        assert_eq!(args(Some(800), None).size().ok(), Some((800, 1200)));
+        assert!(args(Some(100), None).size().is_err());
+        assert!(args(None, Some(4000)).size().is_err());
+    }
+
+    #[test]
+    fn redirects_keep_the_options() {
+        let args = QueryArgs {
+            width: Some(800),
+            encoding: PreviewEncoding::Avif,
+            theme: PreviewTheme::Dark,
+            highlight_floor: true,
+            ..QueryArgs::default()
+        };
+        assert_eq!(
+            preview_url("mi", &args),
+            "/api/locations/mi/preview?lang=de&format=open_graph&width=800&encoding=avif&theme=dark&highlight_floor=true"
+        );
+    }
+
+    #[test]
+    fn all_encodings_work() {
+        let img = load_default_image((400, 300));
+        assert_eq!(img.dimensions(), (400, 300));
+        for encoding in [
+            PreviewEncoding::Png,
+            PreviewEncoding::Webp,
+            PreviewEncoding::Avif,
+        ] {
+            let encoded = encoding.encode(&img).map(|e| e.0);
+            assert!(encoded.is_ok_and(|e| !e.is_empty()), "{encoding}");
+        }
+    }
 }
@@ -0,0 +1,380 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use std::{env, fs, io};
+
+use bytes::Bytes;
+use moka::Expiry;
+use moka::future::Cache;
+use tokio::task::spawn_blocking;
+use tracing::{debug, warn};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Memory tier limit, previews are ~100-500KiB each
+const MAX_MEMORY_BYTES: u64 = 256 * 1024 * 1024;
+/// Disk tier limit, the least recently used previews are removed beyond it
+const MAX_DISK_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+/// Previews depend on more than the entry itself (the basemap, parents), so they are rerendered eventually
+const TIME_TO_LIVE: Duration = Duration::from_hours(7 * 24);
+/// Fallbacks are only cached to not retry an unavailable renderer on every request
+const FALLBACK_TIME_TO_LIVE: Duration = Duration::from_mins(10);
+
+/// A rendered and encoded preview, as returned by the render passed to [`PreviewCache::get_or_render`]
+#[derive(Debug, Clone)]
+pub struct RenderedPreview {
+    pub image: Bytes,
+    /// Rendered without the remote basemap (the local one or the logo)
+    pub is_fallback: bool,
+}
+
+/// A rendered and encoded preview
+#[derive(Debug, Clone)]
+struct CachedPreview {
+    /// `hash` of the entry the preview was rendered from
+    hash: i64,
+    preview: RenderedPreview,
+}
+
+/// Expires fallbacks after [`FALLBACK_TIME_TO_LIVE`], everything else after [`TIME_TO_LIVE`]
+struct PreviewExpiry;
+impl Expiry<String, CachedPreview> for PreviewExpiry {
+    fn expire_after_create(
+        &self,
+        _url: &String,
+        cached: &CachedPreview,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        if cached.preview.is_fallback {
+            Some(FALLBACK_TIME_TO_LIVE)
+        } else {
+            Some(TIME_TO_LIVE)
+        }
+    }
+}
+
+/// The disk tier, bounded by `max_bytes`
+#[derive(Debug, Clone)]
+struct DiskTier {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// Size of the stored previews, approximate until it is recounted during the next eviction
+    used_bytes: Arc<AtomicU64>,
+}
+
+/// Cache of rendered previews, in memory and (if `PREVIEW_CACHE_DIR` is set) on disk.
+///
+/// Entries are keyed by the canonical preview url (which covers id, language, size, encoding, ...)
+/// and are only valid for the `hash` of the entry they were rendered from.
+/// Fallbacks are only kept in memory, and only briefly.
+#[derive(Debug, Clone)]
+pub struct PreviewCache {
+    memory: Cache<String, CachedPreview>,
+    disk: Option<DiskTier>,
+}
+
+impl PreviewCache {
+    /// Creates the cache, storing previews on disk in `PREVIEW_CACHE_DIR` if that is set
+    pub fn from_env() -> Self {
+        let disk = env::var("PREVIEW_CACHE_DIR").ok().map(PathBuf::from);
+        if let Some(dir) = &disk
+            && let Err(e) = fs::create_dir_all(dir)
+        {
+            warn!(error = ?e, ?dir, "could not create the preview cache directory, caching in memory only");
+            return Self::new(None, MAX_DISK_BYTES);
+        }
+        Self::new(disk, MAX_DISK_BYTES)
+    }
+
+    fn new(disk: Option<PathBuf>, max_disk_bytes: u64) -> Self {
+        let memory = Cache::builder()
+            .weigher(|_, cached: &CachedPreview| {
+                u32::try_from(cached.preview.image.len()).unwrap_or(u32::MAX)
+            })
+            .max_capacity(MAX_MEMORY_BYTES)
+            .expire_after(PreviewExpiry)
+            .build();
+        let disk = disk.map(|dir| {
+            let used_bytes = entries(&dir).iter().map(|entry| entry.len).sum();
+            DiskTier {
+                dir,
+                max_bytes: max_disk_bytes,
+                used_bytes: Arc::new(AtomicU64::new(used_bytes)),
+            }
+        });
+        Self { memory, disk }
+    }
+
+    /// Returns the cached preview for `url` if it was rendered from `hash`, otherwise renders it.
+    ///
+    /// Concurrent requests for the same preview (e.g. link unfurls arriving in bursts) wait for a single render.
+    /// If `render` returns `None`, nothing is cached.
+    #[tracing::instrument(skip(self, render))]
+    pub async fn get_or_render(
+        &self,
+        url: &str,
+        hash: i64,
+        render: impl Future<Output = Option<RenderedPreview>>,
+    ) -> Option<Bytes> {
+        if let Some(cached) = self.memory.get(url).await {
+            if cached.hash == hash {
+                return Some(cached.preview.image);
+            }
+            debug!("entry changed, invalidating the cached preview");
+            self.memory.invalidate(url).await;
+        }
+        let preview = self
+            .memory
+            .optionally_get_with(url.to_string(), async {
+                if let Some(image) = self.read_disk(url, hash).await {
+                    let preview = RenderedPreview {
+                        image,
+                        is_fallback: false,
+                    };
+                    return Some(CachedPreview { hash, preview });
+                }
+                let preview = render.await?;
+                if !preview.is_fallback {
+                    self.write_disk(url, hash, preview.image.clone()).await;
+                }
+                Some(CachedPreview { hash, preview })
+            })
+            .await?;
+        Some(preview.preview.image)
+    }
+
+    fn disk_path(&self, url: &str) -> Option<PathBuf> {
+        let disk = self.disk.as_ref()?;
+        Some(disk.dir.join(format!("{:016x}", xxh3_64(url.as_bytes()))))
+    }
+
+    /// Reads a preview from disk, removing it if it was rendered from another `hash`
+    async fn read_disk(&self, url: &str, hash: i64) -> Option<Bytes> {
+        let path = self.disk_path(url)?;
+        let read = spawn_blocking(move || read_entry(&path, hash)).await;
+        match read {
+            Ok(Ok(image)) => image,
+            Ok(Err(e)) if e.kind() == io::ErrorKind::NotFound => None,
+            Ok(Err(e)) => {
+                warn!(error = ?e, "could not read a cached preview");
+                None
+            }
+            Err(e) => {
+                warn!(error = ?e, "reading a cached preview panicked");
+                None
+            }
+        }
+    }
+
+    /// Writes a preview to disk, evicting the least recently used ones beyond the budget
+    async fn write_disk(&self, url: &str, hash: i64, image: Bytes) {
+        let (Some(disk), Some(path)) = (self.disk.clone(), self.disk_path(url)) else {
+            return;
+        };
+        let written = spawn_blocking(move || {
+            let mut content = Vec::with_capacity(8 + image.len());
+            content.extend_from_slice(&hash.to_le_bytes());
+            content.extend_from_slice(&image);
+            let replaced = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+            // written to a temporary file first, so that readers never see half-written previews
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, &content)?;
+            fs::rename(tmp, path)?;
+            let written = u64::try_from(content.len()).unwrap_or(u64::MAX);
+            let account = |used: u64| used.saturating_add(written).saturating_sub(replaced);
+            let previous = disk
+                .used_bytes
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                    Some(account(used))
+                })
+                .unwrap_or_default();
+            let used = account(previous);
+            if used > disk.max_bytes {
+                disk.evict();
+            }
+            io::Result::Ok(())
+        })
+        .await;
+        match written {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!(error = ?e, "could not write a cached preview"),
+            Err(e) => warn!(error = ?e, "writing a cached preview panicked"),
+        }
+    }
+}
+
+impl DiskTier {
+    /// Removes the least recently used previews until a tenth of the budget is free again
+    fn evict(&self) {
+        let mut entries = entries(&self.dir);
+        entries.sort_unstable_by_key(|entry| entry.used_at);
+        let mut used: u64 = entries.iter().map(|entry| entry.len).sum();
+        let target = self.max_bytes - self.max_bytes / 10;
+        let mut removed = 0_usize;
+        for entry in entries {
+            if used <= target {
+                break;
+            }
+            match fs::remove_file(&entry.path) {
+                Ok(()) => {
+                    used = used.saturating_sub(entry.len);
+                    removed += 1;
+                }
+                Err(e) => warn!(error = ?e, path = ?entry.path, "could not evict a cached preview"),
+            }
+        }
+        debug!(removed, used, "evicted cached previews");
+        self.used_bytes.store(used, Ordering::Relaxed);
+    }
+}
+
+/// A stored preview
+struct DiskEntry {
+    path: PathBuf,
+    len: u64,
+    /// When the preview was last written or read
+    used_at: SystemTime,
+}
+
+/// The previews stored in `dir`, skipping everything which cannot be read
+fn entries(dir: &Path) -> Vec<DiskEntry> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    read_dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| DiskEntry {
+                path: entry.path(),
+                len: metadata.len(),
+                used_at: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            })
+        })
+        .collect()
+}
+
+/// An entry on disk is the little-endian `hash` followed by the encoded image
+fn read_entry(path: &Path, hash: i64) -> io::Result<Option<Bytes>> {
+    let content = fs::read(path)?;
+    let (stored_hash, image) = content.split_at_checked(8).unwrap_or_default();
+    if stored_hash == hash.to_le_bytes() {
+        // the modification time orders the eviction, so reads count as a use
+        if let Err(e) = fs::File::options()
+            .append(true)
+            .open(path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            debug!(error = ?e, ?path, "could not mark a cached preview as used");
+        }
+        return Ok(Some(Bytes::copy_from_slice(image)));
+    }
+    debug!(?path, "entry changed, removing the cached preview");
+    fs::remove_file(path)?;
+    Ok(None)
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::time::sleep;
+
+    use super::*;
+
+    fn render(counter: &AtomicUsize, image: &'static [u8]) -> RenderedPreview {
+        counter.fetch_add(1, Ordering::SeqCst);
+        RenderedPreview {
+            image: Bytes::from_static(image),
+            is_fallback: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn previews_are_rerendered_when_the_entry_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::new(Some(dir.path().to_path_buf()), MAX_DISK_BYTES);
+        let renders = AtomicUsize::new(0);
+        let url = "https://nav.tum.de/api/locations/mi/preview?lang=de&format=open_graph";
+
+        let first = cache
+            .get_or_render(url, 1, async { Some(render(&renders, b"v1")) })
+            .await;
+        let second = cache
+            .get_or_render(url, 1, async { Some(render(&renders, b"v1")) })
+            .await;
+        assert_eq!(first.as_deref(), Some(b"v1".as_slice()));
+        assert_eq!(second, first);
+        assert_eq!(renders.load(Ordering::SeqCst), 1);
+
+        let changed = cache
+            .get_or_render(url, 2, async { Some(render(&renders, b"v2")) })
+            .await;
+        assert_eq!(changed.as_deref(), Some(b"v2".as_slice()));
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+
+        // a restarted server only has the disk tier
+        let restarted = PreviewCache::new(Some(dir.path().to_path_buf()), MAX_DISK_BYTES);
+        let from_disk = restarted
+            .get_or_render(url, 2, async { Some(render(&renders, b"v3")) })
+            .await;
+        assert_eq!(from_disk.as_deref(), Some(b"v2".as_slice()));
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_renders_are_not_cached() {
+        let cache = PreviewCache::new(None, MAX_DISK_BYTES);
+        let url = "https://nav.tum.de/api/locations/mi/preview?lang=en&format=square";
+        assert_eq!(cache.get_or_render(url, 1, async { None }).await, None);
+        let renders = AtomicUsize::new(0);
+        assert!(
+            cache
+                .get_or_render(url, 1, async { Some(render(&renders, b"v1")) })
+                .await
+                .is_some()
+        );
+        assert_eq!(renders.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn fallbacks_are_not_stored_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::new(Some(dir.path().to_path_buf()), MAX_DISK_BYTES);
+        let url = "https://nav.tum.de/api/locations/mi/preview?lang=de&format=square";
+        let fallback = RenderedPreview {
+            image: Bytes::from_static(b"logo"),
+            is_fallback: true,
+        };
+        let rendered = cache.get_or_render(url, 1, async { Some(fallback) }).await;
+        assert_eq!(rendered.as_deref(), Some(b"logo".as_slice()));
+        assert!(entries(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_previews_are_evicted_beyond_the_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        // each entry takes 8 bytes of hash and 100 bytes of image
+        let cache = PreviewCache::new(Some(dir.path().to_path_buf()), 250);
+        let renders = AtomicUsize::new(0);
+        let image: &'static [u8] = &[0; 100];
+        for id in ["mi", "mw", "ph"] {
+            let url = format!("https://nav.tum.de/api/locations/{id}/preview");
+            cache
+                .get_or_render(&url, 1, async { Some(render(&renders, image)) })
+                .await
+                .unwrap();
+            // modification times need to differ for a deterministic order
+            sleep(Duration::from_millis(20)).await;
+        }
+        let remaining = entries(dir.path());
+        assert_eq!(remaining.len(), 2, "the oldest preview is evicted");
+        assert!(
+            cache
+                .disk_path("https://nav.tum.de/api/locations/mi/preview")
+                .is_some_and(|path| !path.exists())
+        );
+        assert_eq!(cache.disk.unwrap().used_bytes.load(Ordering::Relaxed), 216);
+    }
+}
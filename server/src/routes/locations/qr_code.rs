@@ -1,53 +1,291 @@
-use crate::db::location::LocationKeyAlias;
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::io::Cursor;
+
+use super::removed::removed_location_response;
+use crate::db::location::{Location, LocationKeyAlias};
 use crate::limited::vec::LimitedVec;
+use crate::localisation::LanguageOptions;
+use crate::overlays::sign::pdf_with_jpeg;
 use actix_web::http::header::{CacheControl, CacheDirective};
-use actix_web::{HttpResponse, get, web};
+use actix_web::{HttpRequest, HttpResponse, get, web};
 use base64::Engine as _;
 use base64::prelude::BASE64_STANDARD;
-use fast_qr::convert::{Builder as _, Shape, image::ImageBuilder};
-use fast_qr::qr::QRBuilder;
+use fast_qr::ECL;
+use fast_qr::convert::{Builder, Shape, image::ImageBuilder, svg::SvgBuilder};
+use fast_qr::qr::{QRBuilder, QRCode};
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage, imageops, load_from_memory};
 use serde::Deserialize;
 use tracing::{debug, error, warn};
+use url::form_urlencoded;
+use utoipa::ToSchema;
+
+/// Limits for `size`
+const MIN_SIZE: u32 = 100;
+const MAX_SIZE: u32 = 2000;
+/// Resolution at which `size` is printed in PDFs
+const PDF_DPI: f32 = 150.0;
 
 #[derive(Deserialize, utoipa::IntoParams)]
 struct QrCodePathParams {
     id: String,
 }
 
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum QrCodeFormat {
+    #[default]
+    Png,
+    Svg,
+    /// A single page, sized to print `size` at 150dpi
+    Pdf,
+}
+impl QrCodeFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Svg => "image/svg+xml",
+            Self::Pdf => "application/pdf",
+        }
+    }
+}
+
+/// How much of the QR code can be damaged (or covered by the logo) while staying readable
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCorrection {
+    /// 7%
+    L,
+    /// 15%
+    M,
+    /// 25%
+    Q,
+    /// 30%
+    H,
+}
+impl From<ErrorCorrection> for ECL {
+    fn from(ecl: ErrorCorrection) -> Self {
+        match ecl {
+            ErrorCorrection::L => Self::L,
+            ErrorCorrection::M => Self::M,
+            ErrorCorrection::Q => Self::Q,
+            ErrorCorrection::H => Self::H,
+        }
+    }
+}
+
+/// What the QR code links to
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum QrCodeTarget {
+    /// The details page of the location
+    #[default]
+    Details,
+    /// A route to the location, starting at `from` if given
+    Navigate,
+    /// The calendar of the location
+    Calendar,
+    /// The map, centered on the location and showing the floor `level`
+    FloorMap,
+}
+impl Display for QrCodeTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Details => f.write_str("details"),
+            Self::Navigate => f.write_str("navigate"),
+            Self::Calendar => f.write_str("calendar"),
+            Self::FloorMap => f.write_str("floor_map"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+#[serde(default)]
+struct QrCodeQueryArgs {
+    #[param(inline)]
+    format: QrCodeFormat,
+    /// Width and height in pixels
+    #[param(minimum = 100, maximum = 2000, default = 500)]
+    size: u32,
+    /// Error correction level, defaults to `q`.
+    ///
+    /// The logo covers some modules, so small codes with a logo should use `h`.
+    #[param(inline)]
+    ecl: Option<ErrorCorrection>,
+    /// Color of the modules as hex `RRGGBB` or `RRGGBBAA`
+    #[param(default = "3070B3", example = "000000")]
+    color: String,
+    /// Color of the background as hex `RRGGBB` or `RRGGBBAA`
+    #[param(default = "ffffff", example = "ffffff00")]
+    background: String,
+    /// Show the location pin in the center
+    #[param(default = true)]
+    logo: bool,
+    #[param(inline)]
+    target: QrCodeTarget,
+    /// For `target=navigate`: where the route starts, e.g. an ID or coordinates
+    #[param(example = "mi")]
+    from: Option<String>,
+    /// For `target=floor_map`: the floor to show, defaults to the ground floor
+    #[param(example = 1)]
+    level: Option<f32>,
+}
+impl Default for QrCodeQueryArgs {
+    fn default() -> Self {
+        Self {
+            format: QrCodeFormat::default(),
+            size: 500,
+            ecl: None,
+            color: "3070B3".to_string(),
+            background: "ffffff".to_string(),
+            logo: true,
+            target: QrCodeTarget::default(),
+            from: None,
+            level: None,
+        }
+    }
+}
+impl QrCodeQueryArgs {
+    fn validate(&self) -> Result<(), String> {
+        if !(MIN_SIZE..=MAX_SIZE).contains(&self.size) {
+            return Err(format!(
+                "size has to be between {MIN_SIZE} and {MAX_SIZE} pixels"
+            ));
+        }
+        for color in [&self.color, &self.background] {
+            let is_hex = color.chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hex || !matches!(color.len(), 6 | 8) {
+                return Err(format!(
+                    "{color} is not a hex color, use RRGGBB or RRGGBBAA"
+                ));
+            }
+        }
+        if self.from.as_ref().is_some_and(|from| from.len() > 255) {
+            return Err("from may be at most 255 characters".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Base of the links in the QR codes, configured via `QR_CODE_BASE_URL` for self-hosted instances
+fn base_url() -> String {
+    let url = env::var("QR_CODE_BASE_URL").unwrap_or_else(|_| "https://nav.tum.de".to_string());
+    url.trim_end_matches('/').to_string()
+}
+
+/// The link encoded in the QR code.
+///
+/// `details_path` is the path of the details page and `position` the `(lat, lon)` of the location,
+/// which is only needed for [`QrCodeTarget::FloorMap`].
+fn target_url(
+    base: &str,
+    key: &str,
+    details_path: &str,
+    args: &QrCodeQueryArgs,
+    position: Option<(f64, f64)>,
+) -> String {
+    match args.target {
+        QrCodeTarget::Details => format!("{base}{details_path}"),
+        QrCodeTarget::Calendar => format!("{base}/calendar/{key}"),
+        QrCodeTarget::Navigate => {
+            let mut query = form_urlencoded::Serializer::new(String::new());
+            if let Some(from) = &args.from {
+                query.append_pair("from", from);
+            }
+            query.append_pair("to", key);
+            format!("{base}/navigate?{query}", query = query.finish())
+        }
+        QrCodeTarget::FloorMap => {
+            let level = args.level.unwrap_or_default();
+            match position {
+                Some((lat, lon)) => format!("{base}/map?level={level}#19/{lat}/{lon}"),
+                None => format!("{base}/map?level={level}"),
+            }
+        }
+    }
+}
+
+fn configure<B: Builder>(builder: &mut B, args: &QrCodeQueryArgs) {
+    builder
+        .margin(1)
+        .shape(Shape::RoundedSquare)
+        .module_color(format!("#{}", args.color))
+        .background_color(format!("#{}", args.background));
+    if args.logo {
+        let pin = include_bytes!("static/pin.png");
+        let pin_base64 = BASE64_STANDARD.encode(pin);
+        builder.image(format!("data:image/png;base64,{pin_base64}"));
+    }
+}
+
+fn render_png(qrcode: &QRCode, args: &QrCodeQueryArgs) -> anyhow::Result<Vec<u8>> {
+    let mut builder = ImageBuilder::default();
+    configure(&mut builder, args);
+    builder
+        .fit_width(args.size)
+        .fit_height(args.size)
+        .to_bytes(qrcode)
+        .map_err(|e| anyhow::anyhow!("cannot build QR code: {e}"))
+}
+
 #[tracing::instrument]
-fn generate_qr_code(url: &str) -> anyhow::Result<LimitedVec<u8>> {
+fn generate_qr_code(url: &str, args: &QrCodeQueryArgs) -> anyhow::Result<LimitedVec<u8>> {
     // Build the QR code
-    let qrcode = QRBuilder::new(url)
+    let mut qrcode = QRBuilder::new(url);
+    if let Some(ecl) = args.ecl {
+        qrcode.ecl(ecl.into());
+    }
+    let qrcode = qrcode
         .build()
         .map_err(|e| anyhow::anyhow!("Failed to build QR code: {e}"))?;
 
-    // Generate the image with rounded corners
-    let pin = include_bytes!("static/pin.png");
-    let pin_base64 = BASE64_STANDARD.encode(pin);
-    let png_code = ImageBuilder::default()
-        .margin(1)
-        .shape(Shape::RoundedSquare)
-        .module_color("#3070B3")
-        .background_color("#ffffff")
-        .fit_width(500)
-        .fit_height(500)
-        .image(format!("data:image/png;base64,{pin_base64}"))
-        .to_bytes(&qrcode)
-        .map_err(|e| anyhow::anyhow!("cannot build QR code: {e}"))?;
-
-    Ok(LimitedVec(png_code))
+    let bytes = match args.format {
+        QrCodeFormat::Png => render_png(&qrcode, args)?,
+        QrCodeFormat::Svg => {
+            let mut builder = SvgBuilder::default();
+            configure(&mut builder, args);
+            let size = args.size;
+            builder
+                .to_str(&qrcode)
+                .replacen(
+                    "<svg ",
+                    &format!(r#"<svg width="{size}" height="{size}" "#),
+                    1,
+                )
+                .into_bytes()
+        }
+        QrCodeFormat::Pdf => {
+            // JPEG has no alpha channel, transparent backgrounds become black otherwise
+            let png = load_from_memory(&render_png(&qrcode, args)?)?;
+            let mut flattened =
+                RgbaImage::from_pixel(png.width(), png.height(), Rgba([0xFF, 0xFF, 0xFF, 0xFF]));
+            imageops::overlay(&mut flattened, &png, 0, 0);
+            let img = DynamicImage::ImageRgba8(flattened).into_rgb8();
+            let mut jpeg = Cursor::new(Vec::new());
+            img.write_to(&mut jpeg, ImageFormat::Jpeg)?;
+            #[expect(clippy::cast_precision_loss, reason = "size is at most 2000")]
+            let points = args.size as f32 * 72.0 / PDF_DPI;
+            pdf_with_jpeg(&jpeg.into_inner(), img.dimensions(), (points, points))
+        }
+    };
+    Ok(LimitedVec(bytes))
 }
 
 /// Get a QR code for a location
 ///
-/// This returns a QR code image (PNG) that links to the location's detail page.
-/// The QR code uses TUM blue (#0065bd) as foreground color with white background and rounded corners.
+/// This returns a QR code that links to the location's detail page.
+/// By default it is a 500x500px PNG in TUM blue (#3070B3) on white with rounded corners and the location pin in the center.
+///
+/// Via `target`, the QR code can instead link to a route to the location, its calendar or the floor map.
 #[utoipa::path(
     tags=["locations"],
-    params(QrCodePathParams),
+    params(QrCodePathParams, QrCodeQueryArgs),
     responses(
-        (status = 200, description = "**QR code image**", content_type="image/png"),
-        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 200, description = "**QR code**", content(("image/png"), ("image/svg+xml"), ("application/pdf"))),
+        (status = 308, description = "**Permanent redirect.** The requested item was removed, but lives on under a successor"),
+        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters, and that the options are valid", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 404, description = "**Not found.** `target=floor_map` needs the location to exist", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 410, description = "**Gone.** The requested item existed, but was removed without a successor", body = String, content_type = "text/plain"),
         (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
     )
 )]
@@ -56,7 +294,9 @@ fn generate_qr_code(url: &str) -> anyhow::Result<LimitedVec<u8>> {
     wrap = "actix_middleware_etag::Etag::default()"
 )]
 pub async fn qr_code_handler(
+    req: HttpRequest,
     params: web::Path<QrCodePathParams>,
+    args: web::Query<QrCodeQueryArgs>,
     data: web::Data<crate::AppData>,
 ) -> HttpResponse {
     let id = params
@@ -67,26 +307,68 @@ pub async fn qr_code_handler(
             .content_type("text/plain")
             .body("Invalid ID");
     }
+    if let Err(e) = args.validate() {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body(e);
+    }
 
-    let url = match LocationKeyAlias::fetch_optional(&data.pool, &id).await {
-        Ok(Some(id)) => format!("https://nav.tum.de/{}", id.redirect_exact_match()),
-        Ok(None) => format!("https://nav.tum.de/view/{id}"),
+    let (key, details_path) = match LocationKeyAlias::fetch_optional(&data.pool, &id).await {
+        Ok(Some(alias)) => {
+            let details_path = alias.redirect_exact_match();
+            (alias.key, details_path)
+        }
+        Ok(None) => {
+            // relative, as the redirect stays on this API, whichever base the encoded links use
+            if let Some(response) =
+                removed_location_response(&data.pool, &id, |successor| match req.query_string() {
+                    "" => format!("/api/locations/{successor}/qr-code"),
+                    query => {
+                        format!("/api/locations/{successor}/qr-code?{query}")
+                    }
+                })
+                .await
+            {
+                return response;
+            }
+            let details_path = format!("/view/{id}");
+            (id, details_path)
+        }
         Err(e) => {
             warn!(error = %e,%id,  "Failed to fetch location key alias. Assuming it is legitimate, since the generated links are a 404 in the worst case");
-            format!("https://nav.tum.de/view/{id}")
+            let details_path = format!("/view/{id}");
+            (id, details_path)
+        }
+    };
+    let position = if args.target == QrCodeTarget::FloorMap {
+        match Location::fetch_optional(&data.pool, &key, &[LanguageOptions::De.to_string()]).await {
+            Ok(Some(location)) => Some((location.lat, location.lon)),
+            Ok(None) => {
+                return HttpResponse::NotFound()
+                    .content_type("text/plain")
+                    .body("Not found");
+            }
+            Err(e) => {
+                error!(error = ?e, key, "Failed to fetch the location for the floor map");
+                return HttpResponse::InternalServerError()
+                    .content_type("text/plain")
+                    .body("Failed to generate QR code");
+            }
         }
+    } else {
+        None
     };
+    let url = target_url(&base_url(), &key, &details_path, &args, position);
     debug!(%url, "generating QR code");
 
-    // Location exists, generate QR code
-    match generate_qr_code(&url) {
-        Ok(qr_image) => HttpResponse::Ok()
-            .content_type("image/png")
+    match generate_qr_code(&url, &args) {
+        Ok(qr_code) => HttpResponse::Ok()
+            .content_type(args.format.content_type())
             .insert_header(CacheControl(vec![
                 CacheDirective::MaxAge(7 * 24 * 60 * 60), // valid for 7d
                 CacheDirective::Public,
             ]))
-            .body(qr_image.0),
+            .body(qr_code.0),
         Err(e) => {
             error!(error = %e, "Failed to generate QR code");
             HttpResponse::InternalServerError()
@@ -109,7 +391,10 @@ mod tests {
 
     #[test]
     fn test_generate_qr_code() {
-        let result = generate_qr_code("https://nav.tum.de/view/5510.00.001");
+        let result = generate_qr_code(
+            "https://nav.tum.de/view/5510.00.001",
+            &QrCodeQueryArgs::default(),
+        );
 
         assert!(result.is_ok(), "QR code generation should succeed");
         let qr_image = result.unwrap();
@@ -132,8 +417,8 @@ mod tests {
     /// Tests that the same ID always generates the same QR code
     fn test_qr_code_consistency() {
         let url = "https://nav.tum.de/view/mi";
-        let result1 = generate_qr_code(url).unwrap();
-        let result2 = generate_qr_code(url).unwrap();
+        let result1 = generate_qr_code(url, &QrCodeQueryArgs::default()).unwrap();
+        let result2 = generate_qr_code(url, &QrCodeQueryArgs::default()).unwrap();
 
         assert_eq!(
             result1.0, result2.0,
@@ -144,12 +429,94 @@ mod tests {
     #[test]
     /// Test that different IDs generate different QR codes
     fn test_qr_code_different_ids() {
-        let result1 = generate_qr_code("https://nav.tum.de/view/5510.01.001").unwrap();
-        let result2 = generate_qr_code("https://nav.tum.de/view/5602.EG.001").unwrap();
+        let result1 = generate_qr_code(
+            "https://nav.tum.de/view/5510.01.001",
+            &QrCodeQueryArgs::default(),
+        )
+        .unwrap();
+        let result2 = generate_qr_code(
+            "https://nav.tum.de/view/5602.EG.001",
+            &QrCodeQueryArgs::default(),
+        )
+        .unwrap();
 
         assert_ne!(
             result1.0, result2.0,
             "Different IDs should generate different QR codes"
         );
     }
+
+    #[test]
+    fn all_formats_work() {
+        for (format, magic) in [
+            (QrCodeFormat::Png, b"\x89PNG".as_slice()),
+            (QrCodeFormat::Svg, b"<svg".as_slice()),
+            (QrCodeFormat::Pdf, b"%PDF".as_slice()),
+        ] {
+            let args = QrCodeQueryArgs {
+                format,
+                size: 200,
+                ecl: Some(ErrorCorrection::H),
+                color: "000000".to_string(),
+                background: "ffffff00".to_string(),
+                logo: false,
+                ..QrCodeQueryArgs::default()
+            };
+            let qr_code = generate_qr_code("https://nav.tum.de/view/mi", &args).unwrap();
+            assert!(qr_code.0.starts_with(magic), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn options_are_validated() {
+        assert!(QrCodeQueryArgs::default().validate().is_ok());
+        let args = |size, color: &str| QrCodeQueryArgs {
+            size,
+            color: color.to_string(),
+            ..QrCodeQueryArgs::default()
+        };
+        assert!(args(50, "3070B3").validate().is_err());
+        assert!(args(500, "blue").validate().is_err());
+        assert!(args(500, "#3070B3").validate().is_err());
+        assert!(args(500, "3070B3ff").validate().is_ok());
+    }
+
+    #[test]
+    fn targets_link_to_the_right_page() {
+        let url = |target, from: Option<&str>, level| {
+            let args = QrCodeQueryArgs {
+                target,
+                from: from.map(String::from),
+                level,
+                ..QrCodeQueryArgs::default()
+            };
+            target_url(
+                "https://navigatum.example",
+                "5602.EG.001",
+                "/room/5602.EG.001",
+                &args,
+                Some((48.262, 11.668)),
+            )
+        };
+        assert_eq!(
+            url(QrCodeTarget::Details, None, None),
+            "https://navigatum.example/room/5602.EG.001"
+        );
+        assert_eq!(
+            url(QrCodeTarget::Calendar, None, None),
+            "https://navigatum.example/calendar/5602.EG.001"
+        );
+        assert_eq!(
+            url(
+                QrCodeTarget::Navigate,
+                Some("Garching Forschungszentrum"),
+                None
+            ),
+            "https://navigatum.example/navigate?from=Garching+Forschungszentrum&to=5602.EG.001"
+        );
+        assert_eq!(
+            url(QrCodeTarget::FloorMap, None, Some(-1.0)),
+            "https://navigatum.example/map?level=-1#19/48.262/11.668"
+        );
+    }
 }
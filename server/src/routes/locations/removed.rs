@@ -0,0 +1,80 @@
+use actix_web::HttpResponse;
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION};
+use sqlx::PgPool;
+use tracing::error;
+
+use crate::db::location::{LocationKeyAlias, Tombstone};
+
+/// Answers a request for an id which is neither a key nor an alias anymore.
+///
+/// If the id belonged to an entry which was removed, this either redirects to its successor
+/// (built via `successor_url` from the successor's key) or explains via `410 Gone` what happened.
+/// Returns `None` for ids we have never seen, so that callers can fall back to their usual 404.
+#[tracing::instrument(skip(pool, successor_url))]
+pub(super) async fn removed_location_response(
+    pool: &PgPool,
+    id: &str,
+    successor_url: impl FnOnce(&str) -> String,
+) -> Option<HttpResponse> {
+    let tombstone = match Tombstone::fetch_optional(pool, id).await {
+        Ok(tombstone) => tombstone?,
+        Err(e) => {
+            error!(error = ?e, id, "Error requesting tombstone");
+            return None;
+        }
+    };
+    if let Some(successor) = &tombstone.successor {
+        match LocationKeyAlias::fetch_all(pool, successor).await {
+            Ok(aliases) if !aliases.is_empty() => {
+                return Some(
+                    HttpResponse::PermanentRedirect()
+                        .insert_header((LOCATION, successor_url(successor)))
+                        .finish(),
+                );
+            }
+            Ok(_) => {}
+            Err(e) => error!(error = ?e, successor, "Error requesting successor"),
+        }
+    }
+    Some(
+        HttpResponse::Gone()
+            .content_type("text/plain")
+            .insert_header(CacheControl(vec![
+                CacheDirective::MaxAge(24 * 60 * 60), // valid for 1d
+                CacheDirective::Public,
+            ]))
+            .body(gone_explanation(&tombstone)),
+    )
+}
+
+fn gone_explanation(tombstone: &Tombstone) -> String {
+    format!(
+        "Gone: {name} ({key}) was removed on {removed_at}, for example because it was demolished, merged or renumbered. We do not know of a successor.",
+        name = tombstone.name,
+        key = tombstone.key,
+        removed_at = tombstone.removed_at.format("%Y-%m-%d"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, reason = "tests assert via unwrap")]
+    use chrono::{TimeZone as _, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn gone_explanation_names_the_removed_entry() {
+        let tombstone = Tombstone {
+            key: "5602.EG.001".to_string(),
+            name: "5602.EG.001 (MI HS 1)".to_string(),
+            removed_at: Utc.with_ymd_and_hms(2026, 5, 3, 12, 0, 0).unwrap(),
+            successor: None,
+        };
+        assert_eq!(
+            gone_explanation(&tombstone),
+            "Gone: 5602.EG.001 (MI HS 1) (5602.EG.001) was removed on 2026-05-03, for example because it was demolished, merged or renumbered. We do not know of a successor."
+        );
+    }
+}
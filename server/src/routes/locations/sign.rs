@@ -0,0 +1,401 @@
+use std::fmt::{self, Display, Formatter};
+
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION, VARY};
+use actix_web::{HttpResponse, get, web};
+use chrono::{DateTime, Datelike as _, Days, NaiveDate, NaiveTime, TimeZone as _, Utc};
+use chrono_tz::Europe::Berlin;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::error;
+
+use super::removed::removed_location_response;
+use crate::db::calendar::{CalendarLocation, Event, LocationEvents};
+use crate::db::location::{Location, LocationKeyAlias, ParentBuilding};
+use crate::localisation::{LangQueryArgs, LanguageOptions, LanguagePreferences};
+use crate::overlays::sign::{DoorSign, Paper, SignBookings};
+
+#[derive(Deserialize, Debug, Copy, Clone, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum SignFormat {
+    Png,
+    Pdf,
+    Svg,
+}
+impl Display for SignFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Png => f.write_str("png"),
+            Self::Pdf => f.write_str("pdf"),
+            Self::Svg => f.write_str("svg"),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug, Copy, Clone, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum PaperSize {
+    #[default]
+    A4,
+    A5,
+}
+impl Display for PaperSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A4 => f.write_str("a4"),
+            Self::A5 => f.write_str("a5"),
+        }
+    }
+}
+impl From<PaperSize> for Paper {
+    fn from(value: PaperSize) -> Self {
+        match value {
+            PaperSize::A4 => Self::A4,
+            PaperSize::A5 => Self::A5,
+        }
+    }
+}
+
+/// Which bookings should be printed on the sign
+#[derive(Deserialize, Default, Debug, Copy, Clone, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum SignBookingRange {
+    #[default]
+    None,
+    Today,
+    Week,
+}
+impl Display for SignBookingRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Today => f.write_str("today"),
+            Self::Week => f.write_str("week"),
+        }
+    }
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct SignPathParams {
+    /// ID of a location
+    id: String,
+    /// The file format of the sign
+    #[param(inline)]
+    format: SignFormat,
+}
+
+#[derive(Deserialize, Default, Debug, utoipa::IntoParams)]
+#[serde(default)]
+struct SignQueryArgs {
+    /// Negotiated from `lang` and `Accept-Language`, see [`LangQueryArgs`]
+    #[serde(skip)]
+    #[param(ignore)]
+    lang: LanguageOptions,
+    #[param(inline)]
+    paper: PaperSize,
+    /// Print the bookings of today or of this week from the calendar.
+    ///
+    /// Ignored for locations without a calendar.
+    #[param(inline)]
+    bookings: SignBookingRange,
+}
+
+fn sign_url(key: &str, format: SignFormat, args: &SignQueryArgs) -> String {
+    format!(
+        "https://nav.tum.de/api/locations/{key}/sign.{format}?lang={lang}&paper={paper}&bookings={bookings}",
+        lang = args.lang,
+        paper = args.paper,
+        bookings = args.bookings
+    )
+}
+
+/// Get a printable door sign
+///
+/// Renders a door sign with the room code, name and building, and a QR code linking to the location's page.
+/// Optionally, today's or this week's bookings from the calendar are listed.
+///
+/// Signs are A4 or A5 and available as PNG (150 dpi), PDF or SVG.
+#[utoipa::path(
+    tags=["locations"],
+    params(SignPathParams, SignQueryArgs, LangQueryArgs),
+    responses(
+        (status = 200, description = "**Door sign**", content(("image/png"), ("application/pdf"), ("image/svg+xml"))),
+        (status = 400, description = "**Bad request.** Make sure that requested item ID is not empty and not longer than 255 characters", body = String, content_type = "text/plain", example = "Invalid ID"),
+        (status = 308, description = "**Permanent redirect.** The requested item is an alias, or was removed but lives on under a successor"),
+        (status = 404, description = "**Not found.** Make sure that requested item exists", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 410, description = "**Gone.** The requested item existed, but was removed without a successor", body = String, content_type = "text/plain"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+    )
+)]
+#[get(
+    "/api/locations/{id}/sign.{format}",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn sign_handler(
+    params: web::Path<SignPathParams>,
+    web::Query(mut args): web::Query<SignQueryArgs>,
+    languages: LanguagePreferences,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    args.lang = languages.base();
+    let id = params
+        .id
+        .replace(|c: char| c.is_whitespace() || c.is_control(), "");
+    if id.is_empty() || id.len() > 255 {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid ID");
+    }
+    match LocationKeyAlias::fetch_optional(&data.pool, &id).await {
+        Ok(Some(alias)) => {
+            return HttpResponse::PermanentRedirect()
+                .insert_header((LOCATION, sign_url(&alias.key, params.format, &args)))
+                .finish();
+        }
+        Ok(None) => {}
+        Err(e) => error!(error = ?e, id, "error requesting alias"),
+    }
+    let sign = match door_sign(&data.pool, &id, &args, Utc::now()).await {
+        Ok(Some(sign)) => sign,
+        Ok(None) => {
+            if let Some(response) = removed_location_response(&data.pool, &id, |successor| {
+                sign_url(successor, params.format, &args)
+            })
+            .await
+            {
+                return response;
+            }
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found");
+        }
+        Err(e) => {
+            error!(error = ?e, id, "Could not get data for the door sign");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Could not get data for location, please try again later");
+        }
+    };
+    let paper = Paper::from(args.paper);
+    let rendered = match params.format {
+        SignFormat::Png => sign.render_png(paper).map(|png| ("image/png", png)),
+        SignFormat::Pdf => sign.render_pdf(paper).map(|pdf| ("application/pdf", pdf)),
+        SignFormat::Svg => sign
+            .render_svg(paper)
+            .map(|svg| ("image/svg+xml", svg.into_bytes())),
+    };
+    let max_age = if args.bookings == SignBookingRange::None {
+        7 * 24 * 60 * 60 // valid for 7d
+    } else {
+        60 * 60 // valid for 1h, as bookings change
+    };
+    match rendered {
+        Ok((content_type, body)) => HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header(CacheControl(vec![
+                CacheDirective::MaxAge(max_age),
+                CacheDirective::Public,
+            ]))
+            .insert_header((VARY, "Accept-Language"))
+            .body(body),
+        Err(e) => {
+            error!(error = ?e, id, "Failed to render the door sign");
+            HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Failed to render the door sign")
+        }
+    }
+}
+
+/// Collects everything printed on the sign, `None` if the location does not exist
+#[tracing::instrument(skip(pool))]
+async fn door_sign(
+    pool: &PgPool,
+    key: &str,
+    args: &SignQueryArgs,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Option<DoorSign>> {
+    // the sign itself is only available in `de` and `en`, so its content is kept in the same language
+    let languages = [args.lang.to_string()];
+    let Some(location) = Location::fetch_optional(pool, key, &languages).await? else {
+        return Ok(None);
+    };
+    let building = ParentBuilding::fetch_optional(pool, key, &languages).await?;
+    let path = LocationKeyAlias {
+        key: key.to_string(),
+        visible_id: key.to_string(),
+        r#type: location.r#type,
+    }
+    .redirect_exact_match();
+    let url = match args.lang {
+        LanguageOptions::De => format!("https://nav.tum.de{path}"),
+        LanguageOptions::En => format!("https://nav.tum.de/en{path}"),
+    };
+    let bookings = match args.bookings {
+        SignBookingRange::None => None,
+        range => fetch_bookings(pool, key, range, args.lang, now).await?,
+    };
+    // room names repeat the code, e.g. `5602.EG.001 (MI HS 1, Friedrich L. Bauer Hörsaal)`
+    let name = location
+        .name
+        .strip_prefix(&format!("{key} ("))
+        .and_then(|n| n.strip_suffix(')'))
+        .map_or_else(|| location.name.clone(), str::to_string);
+    Ok(Some(DoorSign {
+        code: key.to_string(),
+        name,
+        building: building.map(|b| b.name),
+        url,
+        bookings,
+    }))
+}
+
+/// The bookings in `range`, `None` if the location has no (scraped) calendar
+async fn fetch_bookings(
+    pool: &PgPool,
+    key: &str,
+    range: SignBookingRange,
+    lang: LanguageOptions,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Option<SignBookings>> {
+    let locations = CalendarLocation::get_locations(pool, &[key.to_string()]).await?;
+    let Some(location) = locations.0.into_iter().next() else {
+        return Ok(None);
+    };
+    if location.calendar_url.is_none() || location.last_calendar_scrape_at.is_none() {
+        return Ok(None);
+    }
+    let (start, end) = booking_span(range, now);
+    let mut events = LocationEvents::get_from_db(pool, vec![location], &start, &end)
+        .await?
+        .0
+        .remove(key)
+        .map(|e| e.events.0)
+        .unwrap_or_default();
+    events.sort_by_key(|e| e.start_at);
+    Ok(Some(format_bookings(&events, range, lang)))
+}
+
+/// The local day or week (starting on monday) `now` is in
+fn booking_span(range: SignBookingRange, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let today = now.with_timezone(&Berlin).date_naive();
+    let (first, days) = match range {
+        SignBookingRange::Week => (
+            today - Days::new(u64::from(today.weekday().num_days_from_monday())),
+            7,
+        ),
+        SignBookingRange::None | SignBookingRange::Today => (today, 1),
+    };
+    let start_of = |date: NaiveDate| {
+        Berlin
+            .from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest()
+            .map_or(now, |d| d.with_timezone(&Utc))
+    };
+    (start_of(first), start_of(first + Days::new(days)))
+}
+
+fn format_bookings(
+    events: &[Event],
+    range: SignBookingRange,
+    lang: LanguageOptions,
+) -> SignBookings {
+    const WEEKDAYS_DE: [&str; 7] = ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"];
+    const WEEKDAYS_EN: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    let (heading, empty, weekdays) = match (range, lang) {
+        (SignBookingRange::Week, LanguageOptions::De) => {
+            ("Diese Woche", "Keine Buchungen", WEEKDAYS_DE)
+        }
+        (SignBookingRange::Week, LanguageOptions::En) => ("This week", "No bookings", WEEKDAYS_EN),
+        (_, LanguageOptions::De) => ("Heute", "Keine Buchungen", WEEKDAYS_DE),
+        (_, LanguageOptions::En) => ("Today", "No bookings", WEEKDAYS_EN),
+    };
+    let mut lines: Vec<String> = events
+        .iter()
+        .map(|event| {
+            let start = event.start_at.with_timezone(&Berlin);
+            let end = event.end_at.with_timezone(&Berlin);
+            let title = match lang {
+                LanguageOptions::De => &event.title_de,
+                LanguageOptions::En => &event.title_en,
+            };
+            let time = format!("{}–{}", start.format("%H:%M"), end.format("%H:%M"));
+            match range {
+                SignBookingRange::Week => {
+                    let weekday = weekdays
+                        .get(start.weekday().num_days_from_monday() as usize)
+                        .copied()
+                        .unwrap_or_default();
+                    format!(
+                        "{weekday} {date} {time}  {title}",
+                        date = start.format("%d.%m.")
+                    )
+                }
+                SignBookingRange::None | SignBookingRange::Today => format!("{time}  {title}"),
+            }
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(empty.to_string());
+    }
+    SignBookings {
+        heading: heading.to_string(),
+        lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        reason = "the timestamps are hard-coded and valid"
+    )]
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn event(start: DateTime<Utc>, hours: i64) -> Event {
+        Event {
+            id: 1,
+            room_code: "5602.EG.001".to_string(),
+            start_at: start,
+            end_at: start + TimeDelta::hours(hours),
+            title_de: "Analysis 1".to_string(),
+            title_en: "Analysis 1 (en)".to_string(),
+            stp_type: None,
+            entry_type: "lecture".to_string(),
+            detailed_entry_type: "Abhaltung".to_string(),
+        }
+    }
+
+    #[test]
+    fn spans_follow_local_time() {
+        // a thursday, shortly after midnight in Munich
+        let now = Utc.with_ymd_and_hms(2026, 10, 14, 22, 30, 0).unwrap();
+        assert_eq!(
+            booking_span(SignBookingRange::Today, now),
+            (
+                Utc.with_ymd_and_hms(2026, 10, 14, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2026, 10, 15, 22, 0, 0).unwrap()
+            )
+        );
+        assert_eq!(
+            booking_span(SignBookingRange::Week, now),
+            (
+                Utc.with_ymd_and_hms(2026, 10, 11, 22, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2026, 10, 18, 22, 0, 0).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn bookings_are_localised() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 15, 12, 15, 0).unwrap();
+        let events = [event(start, 2)];
+        let week = format_bookings(&events, SignBookingRange::Week, LanguageOptions::De);
+        assert_eq!(week.heading, "Diese Woche");
+        assert_eq!(week.lines, ["Do 15.10. 14:15–16:15  Analysis 1"]);
+        let today = format_bookings(&events, SignBookingRange::Today, LanguageOptions::En);
+        assert_eq!(today.lines, ["14:15–16:15  Analysis 1 (en)"]);
+        let empty = format_bookings(&[], SignBookingRange::Today, LanguageOptions::En);
+        assert_eq!(empty.lines, ["No bookings"]);
+    }
+}
@@ -1 +1,2 @@
 pub mod route;
+pub mod tiles;
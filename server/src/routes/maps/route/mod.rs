@@ -1,6 +1,7 @@
 use std::fmt;
 
-use crate::localisation::LanguageOptions;
+use crate::localisation::{LangQueryArgs, LanguageOptions, LanguagePreferences};
+use actix_web::http::header::VARY;
 use actix_web::{HttpResponse, get, web};
 use chrono::{DateTime, TimeDelta, Utc};
 use motis_openapi_progenitor::types::{Itinerary, PedestrianProfile, PlanResponse};
@@ -100,8 +101,8 @@ impl RequestedLocation {
                 let coords = sqlx::query_as!(
                     Coordinate,
                     r#"SELECT lat,lon
-                    FROM de
-                    WHERE key = $1 and
+                    FROM location_data
+                    WHERE key = $1 and lang = 'de' and
                           lat IS NOT NULL and
                           lon IS NOT NULL"#,
                     key
@@ -305,8 +306,9 @@ impl Router {
 
 #[derive(Deserialize, Debug, utoipa::ToSchema, utoipa::IntoParams)]
 struct RoutingRequest {
-    #[serde(default)]
-    #[param(inline)]
+    /// Negotiated from `lang` and `Accept-Language`, see [`LangQueryArgs`]
+    #[serde(skip)]
+    #[param(ignore)]
     lang: LanguageOptions,
     /// Start of the route
     #[param(inline)]
@@ -436,7 +438,7 @@ enum PoweredTwoWheeledRestrictionRequest {
 /// - [Motis](https://github.com/motis-project/motis)
 #[utoipa::path(
     tags=["maps"],
-    params(RoutingRequest),
+    params(RoutingRequest, LangQueryArgs),
     responses(
         (status = 200, description = "**Routing solution**", body=RoutingResponse, content_type = "application/json"),
         (status = 404, description = "**Not found.** The requested location does not exist", body = String, content_type = "text/plain", example = "Not found"),
@@ -444,9 +446,11 @@ enum PoweredTwoWheeledRestrictionRequest {
 )]
 #[get("/api/maps/route", wrap = "actix_middleware_etag::Etag::default()")]
 pub async fn route_handler(
-    args: web::Query<RoutingRequest>,
+    web::Query(mut args): web::Query<RoutingRequest>,
+    languages: LanguagePreferences,
     data: web::Data<crate::AppData>,
 ) -> HttpResponse {
+    args.lang = languages.base();
     let from = args.from.try_resolve_coordinates(&data.pool).await;
     let to = args.to.try_resolve_coordinates(&data.pool).await;
     let (from, to) = match (from, to) {
@@ -475,12 +479,16 @@ pub async fn route_handler(
     debug!(routing_solution=?router,"got routing solution");
 
     match router {
-        Router::Motis(response) => HttpResponse::Ok().json(RoutingResponse::Motis(
-            motis::MotisRoutingResponse::from(*response),
-        )),
-        Router::Valhalla(response) => HttpResponse::Ok().json(RoutingResponse::Valhalla(
-            valhalla::ValhallaRoutingResponse::from(*response),
-        )),
+        Router::Motis(response) => HttpResponse::Ok()
+            .insert_header((VARY, "Accept-Language"))
+            .json(RoutingResponse::Motis(motis::MotisRoutingResponse::from(
+                *response,
+            ))),
+        Router::Valhalla(response) => HttpResponse::Ok()
+            .insert_header((VARY, "Accept-Language"))
+            .json(RoutingResponse::Valhalla(
+                valhalla::ValhallaRoutingResponse::from(*response),
+            )),
     }
 }
 
@@ -0,0 +1,270 @@
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{HttpResponse, get, web};
+use bytes::Bytes;
+use moka::future::Cache;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::db::indoor::MvtFunction;
+
+/// Highest zoom level the indoor layers are rendered at, as configured for Martin
+const MAX_ZOOM: u32 = 30;
+/// Levels are whole floors or half ones (mezzanines), more precision would only split the cache
+const LEVEL_STEP: f32 = 0.5;
+/// Beyond any building, as in the level sanitising of `osm2pgsql`
+const MAX_LEVEL: f32 = 100.0;
+/// Tiles are usually a few KiB, so this keeps a few thousand of them
+const MAX_CACHE_BYTES: u64 = 32 * 1024 * 1024;
+/// `osm2pgsql` replication updates the indoor tables continuously
+const CACHE_TIME_TO_LIVE: Duration = Duration::from_hours(1);
+
+/// Layers which are rendered by the functions in the database
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum TileLayer {
+    IndoorRooms,
+    IndoorDoors,
+    IndoorWalls,
+    IndoorPois,
+    /// Not filtered by `level`
+    CardValidators,
+}
+impl TileLayer {
+    /// Lowest zoom level the layer is shown at, as in the `minzoom` of the basemap style
+    fn min_zoom(self) -> u32 {
+        match self {
+            Self::IndoorRooms | Self::IndoorDoors | Self::IndoorWalls | Self::IndoorPois => 16,
+            Self::CardValidators => 13,
+        }
+    }
+
+    fn function(self) -> MvtFunction {
+        match self {
+            Self::IndoorRooms => MvtFunction {
+                name: "indoor_rooms",
+                tables: &["rooms"],
+                has_levels: true,
+            },
+            Self::IndoorDoors => MvtFunction {
+                name: "indoor_doors",
+                tables: &["doors"],
+                has_levels: true,
+            },
+            Self::IndoorWalls => MvtFunction {
+                name: "indoor_walls",
+                tables: &["rooms", "doors"],
+                has_levels: true,
+            },
+            Self::IndoorPois => MvtFunction {
+                name: "indoor_pois",
+                tables: &["pois"],
+                has_levels: true,
+            },
+            Self::CardValidators => MvtFunction {
+                name: "card_validators",
+                tables: &["pois"],
+                has_levels: false,
+            },
+        }
+    }
+}
+impl Display for TileLayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.function().name)
+    }
+}
+
+/// Cache key for rendered tiles
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct TileCacheKey {
+    layer: TileLayer,
+    z: u32,
+    x: u32,
+    y: u32,
+    /// bits of the `f32` level, as floats are not `Hash`
+    level: Option<u32>,
+}
+
+/// Cache of rendered tiles, weighted by their size
+///
+/// `None` unless `SERVE_INDOOR_TILES=true`, as production serves the tiles via Martin.
+/// The database functions rendering them are always present, since Martin uses them as well.
+#[must_use]
+pub fn tile_cache() -> Option<Cache<TileCacheKey, Bytes>> {
+    if env::var("SERVE_INDOOR_TILES") != Ok("true".to_string()) {
+        return None;
+    }
+    let cache = Cache::builder()
+        .weigher(|_, tile: &Bytes| u32::try_from(tile.len()).unwrap_or(u32::MAX))
+        .max_capacity(MAX_CACHE_BYTES)
+        .time_to_live(CACHE_TIME_TO_LIVE)
+        .build();
+    Some(cache)
+}
+
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+struct TilePathParams {
+    #[param(inline)]
+    layer: TileLayer,
+    /// Zoom level
+    #[param(maximum = 30, example = 17)]
+    z: u32,
+    #[param(example = 69_879)]
+    x: u32,
+    #[param(example = 45_437)]
+    y: u32,
+}
+impl TilePathParams {
+    fn is_valid(&self) -> bool {
+        let tiles_per_axis = 1_u64 << self.z.min(MAX_ZOOM);
+        self.z <= MAX_ZOOM
+            && u64::from(self.x) < tiles_per_axis
+            && u64::from(self.y) < tiles_per_axis
+    }
+}
+
+#[derive(Deserialize, Debug, Default, utoipa::IntoParams)]
+#[serde(default)]
+struct TileQueryArgs {
+    /// The level (`level=*` in OpenStreetMap) to render, defaults to the ground floor.
+    ///
+    /// Rounded to half levels.
+    #[param(minimum = -100, maximum = 100, example = 1)]
+    level: Option<f32>,
+}
+impl TileQueryArgs {
+    /// The level rounded to [`LEVEL_STEP`], `Err` if it is outside of any building
+    fn level(&self) -> Result<Option<f32>, ()> {
+        match self.level {
+            Some(level) if !level.is_finite() || level.abs() > MAX_LEVEL => Err(()),
+            level => Ok(level.map(|level| (level / LEVEL_STEP).round() * LEVEL_STEP)),
+        }
+    }
+}
+
+/// Get an indoor vector tile
+///
+/// Renders a Mapbox Vector Tile of an indoor layer, identical to the tiles served by our Martin deployment.
+/// This allows small deployments and local development to run without Martin.
+/// Only available if the server is started with `SERVE_INDOOR_TILES=true`.
+///
+/// Empty tiles are answered with `204 No Content`, as are tiles below the zoom level the layer is shown at.
+#[utoipa::path(
+    tags=["maps"],
+    params(TilePathParams, TileQueryArgs),
+    responses(
+        (status = 200, description = "**Vector tile**", content_type = "application/x-protobuf"),
+        (status = 204, description = "**Empty tile.** Nothing is mapped here, the layer is not shown at this zoom level or the indoor data is not loaded"),
+        (status = 400, description = "**Bad request.** Make sure that the tile coordinates exist at the zoom level and that the level is within `-100..=100`", body = String, content_type = "text/plain", example = "Invalid tile"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+        (status = 503, description = "**Not configured.** This server does not serve indoor tiles, use Martin instead", body = String, content_type = "text/plain", example = "Indoor tiles are not served by this server."),
+    )
+)]
+#[get("/api/tiles/{layer}/{z}/{x}/{y}.mvt")]
+pub async fn tile_handler(
+    params: web::Path<TilePathParams>,
+    web::Query(args): web::Query<TileQueryArgs>,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let Some(tile_cache) = &data.tile_cache else {
+        return HttpResponse::ServiceUnavailable()
+            .content_type("text/plain")
+            .body("Indoor tiles are not served by this server.");
+    };
+    if !params.is_valid() {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid tile");
+    }
+    let Ok(level) = args.level() else {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body("Invalid level");
+    };
+    let layer = params.layer;
+    if params.z < layer.min_zoom() {
+        return empty_tile();
+    }
+    let function = layer.function();
+    // the level is ignored by layers without levels, so it must not split their cache
+    let level = level.filter(|_| function.has_levels);
+    let key = TileCacheKey {
+        layer,
+        z: params.z,
+        x: params.x,
+        y: params.y,
+        level: level.map(f32::to_bits),
+    };
+    // validated above, so `z <= 30` and `x, y < 2^z` fit
+    let coordinates = (
+        i32::try_from(params.z).unwrap_or(i32::MAX),
+        i32::try_from(params.x).unwrap_or(i32::MAX),
+        i32::try_from(params.y).unwrap_or(i32::MAX),
+    );
+    let tile = tile_cache
+        .try_get_with(key, async {
+            let tile = function.fetch_tile(&data.pool, coordinates, level).await?;
+            Ok::<_, sqlx::Error>(Bytes::from(tile.unwrap_or_default()))
+        })
+        .await;
+    match tile {
+        Ok(tile) if tile.is_empty() => empty_tile(),
+        Ok(tile) => HttpResponse::Ok()
+            .content_type("application/x-protobuf")
+            .insert_header(CacheControl(vec![
+                CacheDirective::MaxAge(60 * 60), // valid for 1h
+                CacheDirective::Public,
+            ]))
+            .body(tile),
+        Err(e) => {
+            error!(error = ?e, %layer, ?key, "Could not render the tile");
+            HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Could not render the tile")
+        }
+    }
+}
+
+fn empty_tile() -> HttpResponse {
+    HttpResponse::NoContent()
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(60 * 60), // valid for 1h
+            CacheDirective::Public,
+        ]))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_coordinates_are_validated() {
+        let params = |z, x, y| TilePathParams {
+            layer: TileLayer::IndoorRooms,
+            z,
+            x,
+            y,
+        };
+        assert!(params(0, 0, 0).is_valid());
+        assert!(params(17, 69_879, 45_437).is_valid());
+        assert!(!params(1, 2, 0).is_valid());
+        assert!(!params(31, 0, 0).is_valid());
+    }
+
+    #[test]
+    fn levels_are_rounded_and_limited() {
+        let level = |level| TileQueryArgs { level }.level();
+        assert_eq!(level(None), Ok(None));
+        assert_eq!(level(Some(1.0)), Ok(Some(1.0)));
+        assert_eq!(level(Some(0.5)), Ok(Some(0.5)));
+        assert_eq!(level(Some(-1.000_1)), Ok(Some(-1.0)));
+        assert_eq!(level(Some(2.3)), Ok(Some(2.5)));
+        assert_eq!(level(Some(101.0)), Err(()));
+        assert_eq!(level(Some(f32::NAN)), Err(()));
+    }
+}
@@ -3,4 +3,34 @@ pub mod feedback;
 pub mod locations;
 pub mod maps;
 pub mod mensa;
+pub mod oembed;
 pub mod search;
+pub mod sitemap;
+pub mod timetable;
+
+use std::future::{Ready, ready};
+
+use actix_web::dev::Payload;
+use actix_web::error::ErrorBadRequest;
+use actix_web::{FromRequest, HttpRequest};
+use serde::de::DeserializeOwned;
+
+/// Like [`actix_web::web::Query`], but allows repeated keys
+///
+/// `web::Query` uses `serde_urlencoded`, which cannot deserialise repeated keys
+/// (e.g. `?ids=5602.EG.001&ids=5602.EG.002`) into `Vec<String>`. `serde_html_form` does.
+#[derive(Debug)]
+pub struct HtmlQuery<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for HtmlQuery<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            serde_html_form::from_str::<T>(req.query_string())
+                .map(Self)
+                .map_err(|e| ErrorBadRequest(format!("Query deserialize error: {e}"))),
+        )
+    }
+}
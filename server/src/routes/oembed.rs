@@ -0,0 +1,240 @@
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{HttpResponse, get, web};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use url::Url;
+
+use crate::db::location::{Location, LocationKeyAlias};
+use crate::localisation::LanguageOptions;
+
+const WEBSITE: &str = "https://nav.tum.de";
+/// Dimensions of the `open_graph` preview image
+const PREVIEW_SIZE: (u32, u32) = (1200, 630);
+
+#[derive(Deserialize, Debug, utoipa::IntoParams)]
+struct OEmbedQueryArgs {
+    /// The URL of the location page to embed
+    #[param(example = "https://nav.tum.de/room/5606.EG.036")]
+    url: String,
+    /// The maximum width of the embedded image
+    maxwidth: Option<u32>,
+    /// The maximum height of the embedded image
+    maxheight: Option<u32>,
+    /// The response format. Only `json` is supported.
+    #[param(example = "json")]
+    format: Option<String>,
+}
+
+/// Get an oEmbed for a location page
+///
+/// Implements the [oEmbed](https://oembed.com) protocol for links to `https://nav.tum.de`.
+/// Returns the preview image of the location together with its name as a `photo`, so that
+/// news sites or learning-management systems can render a rich card for our links.
+///
+/// The image is delivered in a fixed resolution.
+/// If `maxwidth` or `maxheight` are smaller, the reported `width` and `height` are scaled down accordingly.
+#[utoipa::path(
+    tags=["locations"],
+    params(OEmbedQueryArgs),
+    responses(
+        (status = 200, description = "**oEmbed** of the location page", body = OEmbedResponse, content_type = "application/json"),
+        (status = 404, description = "**Not found.** The `url` is not a location page on `nav.tum.de`, or the location does not exist", body = String, content_type = "text/plain", example = "Not found"),
+        (status = 501, description = "**Not implemented.** Only `format=json` is supported", body = String, content_type = "text/plain", example = "Only json is supported"),
+    )
+)]
+#[get("/api/oembed", wrap = "actix_middleware_etag::Etag::default()")]
+pub async fn oembed_handler(
+    web::Query(args): web::Query<OEmbedQueryArgs>,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    if args
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "json")
+    {
+        return HttpResponse::NotImplemented()
+            .content_type("text/plain")
+            .body("Only json is supported");
+    }
+    let Some((id, lang)) = parse_location_url(&args.url) else {
+        return HttpResponse::NotFound()
+            .content_type("text/plain")
+            .body("Not found");
+    };
+    let key = match LocationKeyAlias::fetch_optional(&data.pool, &id).await {
+        Ok(Some(alias)) => alias.key,
+        Ok(None) => id,
+        Err(e) => {
+            error!(error = ?e, id, "error requesting alias");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    let location = match Location::fetch_optional(&data.pool, &key, &[lang.to_string()]).await {
+        Ok(Some(location)) => location,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .content_type("text/plain")
+                .body("Not found");
+        }
+        Err(e) => {
+            error!(error = ?e, key, "Could not get location");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error");
+        }
+    };
+    let (width, height) = fit_into(PREVIEW_SIZE, args.maxwidth, args.maxheight);
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(24 * 60 * 60), // valid for 1d
+            CacheDirective::Public,
+        ]))
+        .json(OEmbedResponse {
+            r#type: OEmbedTypeResponse::Photo,
+            version: "1.0",
+            title: location.name,
+            url: format!("{WEBSITE}/api/locations/{key}/preview?lang={lang}&format=open_graph"),
+            width,
+            height,
+            provider_name: "NavigaTUM",
+            provider_url: WEBSITE,
+            cache_age: 24 * 60 * 60,
+        })
+}
+
+/// The first path segment of the pages showing a location
+const LOCATION_PAGES: [&str; 7] = ["view", "embed", "campus", "site", "building", "room", "poi"];
+
+/// Extracts the location id and language from a link to a location page.
+///
+/// Pages are `/{type}/{id}`, `/view/{id}` or `/embed/{id}`, prefixed with `/en` for English.
+fn parse_location_url(url: &str) -> Option<(String, LanguageOptions)> {
+    let url = Url::parse(url).ok()?;
+    if !matches!(url.scheme(), "http" | "https")
+        || !matches!(url.host_str(), Some("nav.tum.de" | "www.nav.tum.de"))
+    {
+        return None;
+    }
+    let mut segments = url.path_segments()?.filter(|s| !s.is_empty()).peekable();
+    let lang = if segments.peek() == Some(&"en") {
+        segments.next();
+        LanguageOptions::En
+    } else {
+        LanguageOptions::De
+    };
+    let (Some(page), Some(id), None) = (segments.next(), segments.next(), segments.next()) else {
+        return None;
+    };
+    if !LOCATION_PAGES.contains(&page) {
+        return None;
+    }
+    let id = percent_encoding::percent_decode_str(id)
+        .decode_utf8()
+        .ok()?
+        .replace(|c: char| c.is_whitespace() || c.is_control(), "");
+    if id.is_empty() || id.len() > 255 {
+        return None;
+    }
+    Some((id, lang))
+}
+
+/// Scales `size` down, keeping its aspect ratio, until it fits into `max_width`×`max_height`.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "the scale is in [0, 1], so the results are non-negative and not larger than the input"
+)]
+fn fit_into(size: (u32, u32), max_width: Option<u32>, max_height: Option<u32>) -> (u32, u32) {
+    let (width, height) = (f64::from(size.0), f64::from(size.1));
+    let scale = [
+        max_width.map(|max| f64::from(max) / width),
+        max_height.map(|max| f64::from(max) / height),
+    ]
+    .into_iter()
+    .flatten()
+    .fold(1.0_f64, f64::min);
+    (
+        (width * scale).round() as u32,
+        (height * scale).round() as u32,
+    )
+}
+
+/// An oEmbed `photo` response, see <https://oembed.com/#section2.3>
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+struct OEmbedResponse {
+    r#type: OEmbedTypeResponse,
+    /// The oEmbed version
+    #[schema(examples("1.0"))]
+    version: &'static str,
+    /// Name of the location
+    #[schema(examples("5606.EG.036 (Büro Fachschaft Mathe Physik Informatik Chemie / MPIC)"))]
+    title: String,
+    /// URL of the preview image
+    #[schema(examples(
+        "https://nav.tum.de/api/locations/5606.EG.036/preview?lang=de&format=open_graph"
+    ))]
+    url: String,
+    /// Width at which the image should be displayed
+    #[schema(examples(1200))]
+    width: u32,
+    /// Height at which the image should be displayed
+    #[schema(examples(630))]
+    height: u32,
+    #[schema(examples("NavigaTUM"))]
+    provider_name: &'static str,
+    #[schema(examples("https://nav.tum.de"))]
+    provider_url: &'static str,
+    /// How long this response may be cached in seconds
+    #[schema(examples(86400))]
+    cache_age: u32,
+}
+
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum OEmbedTypeResponse {
+    Photo,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_urls_are_parsed() {
+        assert_eq!(
+            parse_location_url("https://nav.tum.de/room/5606.EG.036"),
+            Some(("5606.EG.036".to_string(), LanguageOptions::De))
+        );
+        assert_eq!(
+            parse_location_url("https://nav.tum.de/en/building/mi?foo=bar#x"),
+            Some(("mi".to_string(), LanguageOptions::En))
+        );
+        assert_eq!(
+            parse_location_url("https://nav.tum.de/view/5510.02.001%20"),
+            Some(("5510.02.001".to_string(), LanguageOptions::De))
+        );
+        assert_eq!(parse_location_url("https://example.com/room/mi"), None);
+        assert_eq!(
+            parse_location_url("https://nav.tum.de/about/privacy/x"),
+            None
+        );
+        assert_eq!(parse_location_url("https://nav.tum.de/search"), None);
+        assert_eq!(parse_location_url("https://nav.tum.de/about/mi"), None);
+        assert_eq!(parse_location_url("https://nav.tum.de/api/mi"), None);
+        assert_eq!(
+            parse_location_url("https://nav.tum.de/en/calendar/mi"),
+            None
+        );
+        assert_eq!(parse_location_url("not a url"), None);
+    }
+
+    #[test]
+    fn size_is_scaled_to_fit() {
+        assert_eq!(fit_into(PREVIEW_SIZE, None, None), (1200, 630));
+        assert_eq!(fit_into(PREVIEW_SIZE, Some(2000), None), (1200, 630));
+        assert_eq!(fit_into(PREVIEW_SIZE, Some(600), None), (600, 315));
+        assert_eq!(fit_into(PREVIEW_SIZE, Some(600), Some(100)), (190, 100));
+    }
+}
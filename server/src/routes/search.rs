@@ -4,14 +4,12 @@ use std::time::Instant;
 
 use crate::AppData;
 use crate::external::meilisearch::FacetFilter;
+use crate::routes::HtmlQuery;
 use crate::search_executor::{self, ResultFacet, ResultsSection};
-use actix_web::dev::Payload;
-use actix_web::error::ErrorBadRequest;
 use actix_web::http::header::{CacheControl, CacheDirective};
-use actix_web::{FromRequest, HttpRequest, HttpResponse, get, web};
+use actix_web::{HttpResponse, get, web};
 use meilisearch_sdk::client::Client;
 use serde::{Deserialize, Serialize};
-use std::future::{Ready, ready};
 use strum::EnumCount as _;
 use tokio::join;
 use tracing::{debug, error};
@@ -27,6 +25,7 @@ pub struct SearchCacheKey {
     pub filter_in: Vec<String>,
     pub filter_usage: Vec<String>,
     pub filter_type: Vec<FacetFilter>,
+    pub filter_semester: Vec<String>,
     pub near: Option<String>,
 }
 
@@ -90,6 +89,15 @@ pub struct SearchQueryArgs {
     #[schema(example = json!(["site", "room"]))]
     filter_type: Vec<FacetFilter>,
 
+    /// Filter lectures by the semester they take place in (e.g. `2026W`, `2027S`).
+    ///
+    /// Can be repeated for multiple values.
+    /// Only lectures have a semester, so other facets return no results when this is set.
+    /// Combine with `in` to filter by campus, e.g. `semester=2026W&in=garching`.
+    #[serde(rename = "semester", default)]
+    #[schema(example = json!(["2026W"]))]
+    filter_semester: Vec<String>,
+
     /// Sort results by distance to a coordinate (`lat,lon`).
     #[schema(example = "48.123,11.456")]
     near: Option<String>,
@@ -200,20 +208,6 @@ pub struct SearchQueryArgs {
     parsed_id: ParsedIdMode,
 }
 
-// `web::Query` uses `serde_urlencoded`, which cannot deserialise repeated keys
-// (e.g. `?in=garching&in=5304`) into `Vec<String>`. `serde_html_form` does.
-impl FromRequest for SearchQueryArgs {
-    type Error = actix_web::Error;
-    type Future = Ready<Result<Self, Self::Error>>;
-
-    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
-        ready(
-            serde_html_form::from_str::<Self>(req.query_string())
-                .map_err(|e| ErrorBadRequest(format!("Query deserialize error: {e}"))),
-        )
-    }
-}
-
 /// Returned search results by this
 #[derive(Serialize, utoipa::ToSchema)]
 pub struct SearchResponse {
@@ -486,6 +480,14 @@ fn build_meilisearch_filter(
     filters.join(" AND ")
 }
 
+/// Filter for the lecture-only fields, empty if none is requested
+pub(crate) fn build_lecture_filter(semester: &[String]) -> String {
+    if semester.is_empty() {
+        return String::new();
+    }
+    format!("(semester IN {semester:?})")
+}
+
 fn build_meilisearch_sorting(near: Option<&String>) -> Vec<String> {
     match near {
         Some(loc) => vec![format!("_geoPoint({loc}):asc")],
@@ -513,7 +515,10 @@ fn build_meilisearch_sorting(near: Option<&String>) -> Vec<String> {
     )
 )]
 #[get("/api/search", wrap = "actix_middleware_etag::Etag::default()")]
-pub async fn search_handler(data: web::Data<AppData>, args: SearchQueryArgs) -> HttpResponse {
+pub async fn search_handler(
+    data: web::Data<AppData>,
+    HtmlQuery(args): HtmlQuery<SearchQueryArgs>,
+) -> HttpResponse {
     if args.q.len() > 1000 {
         return HttpResponse::BadRequest()
             .content_type("text/plain")
@@ -532,6 +537,7 @@ pub async fn search_handler(data: web::Data<AppData>, args: SearchQueryArgs) ->
     let filter_in = args.filter_in;
     let filter_usage = args.usage;
     let filter_type = args.filter_type;
+    let filter_semester = args.filter_semester;
     let near = args.near;
 
     debug!(q, ?limits, ?formatting_config, "requested search");
@@ -544,10 +550,18 @@ pub async fn search_handler(data: web::Data<AppData>, args: SearchQueryArgs) ->
         filter_in: filter_in.clone(),
         filter_usage: filter_usage.clone(),
         filter_type: filter_type.clone(),
+        filter_semester: filter_semester.clone(),
         near: near.clone(),
     };
 
-    let ms_filter = build_meilisearch_filter(&filter_in, &filter_usage, &filter_type);
+    let ms_filter = [
+        build_meilisearch_filter(&filter_in, &filter_usage, &filter_type),
+        build_lecture_filter(&filter_semester),
+    ]
+    .into_iter()
+    .filter(|filter| !filter.is_empty())
+    .collect::<Vec<_>>()
+    .join(" AND ");
     let ms_sorting = build_meilisearch_sorting(near.as_ref());
 
     let results_sections = data
@@ -899,6 +913,13 @@ mod tests {
         assert!(!filter.contains("Garching"));
     }
 
+    #[test]
+    fn filter_lecture_fields() {
+        assert_eq!(build_lecture_filter(&[]), "");
+        let filter = build_lecture_filter(&["2026W".to_string(), "2027S".to_string()]);
+        insta::assert_snapshot!(filter, @r#"(semester IN ["2026W", "2027S"])"#);
+    }
+
     #[test]
     fn filter_type_serializes_all_known_facets() {
         let filter = build_meilisearch_filter(
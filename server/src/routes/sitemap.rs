@@ -0,0 +1,222 @@
+use std::fmt::Write as _;
+
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{HttpResponse, get, web};
+use chrono::{DateTime, SecondsFormat, Utc};
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::db::sitemap::{SitemapEntry, SitemapShard};
+use crate::xml;
+
+const WEBSITE: &str = "https://nav.tum.de";
+/// Languages the webclient has pages for, with the prefix of their paths.
+///
+/// Translations in further languages are only served via the API, so there are no pages to list for them.
+const WEBSITE_LANGUAGES: [(&str, &str); 2] = [("de", ""), ("en", "/en")];
+/// Sitemap of the static pages of the webclient.
+///
+/// Sitemap indexes cannot be nested, so it is listed in our index as well.
+const WEB_SITEMAP_URL: &str = "https://nav.tum.de/sitemap-webclient.xml";
+/// Locations per shard.
+///
+/// Each location is listed once per language, which keeps shards below the limit of 50 000 urls per sitemap.
+const SHARD_SIZE: i64 = 20_000;
+/// Characters which have to be escaped in a path segment
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Get the sitemap index
+///
+/// Lists the sitemap shards containing all locations, and the sitemap of the static pages.
+/// Intended for search engines.
+#[utoipa::path(
+    tags=["locations"],
+    responses(
+        (status = 200, description = "**Sitemap index**", body = String, content_type = "application/xml"),
+    )
+)]
+#[get("/api/sitemap.xml", wrap = "actix_middleware_etag::Etag::default()")]
+pub async fn sitemap_index_handler(data: web::Data<crate::AppData>) -> HttpResponse {
+    match SitemapShard::fetch_all(&data.pool, SHARD_SIZE).await {
+        Ok(shards) => xml_response(render_index(&shards)),
+        Err(e) => {
+            error!(error = ?e, "Could not get the sitemap shards");
+            HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error")
+        }
+    }
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct SitemapShardPathParams {
+    /// Number of the shard, as listed in the sitemap index
+    shard: u32,
+}
+
+/// Get a sitemap shard
+///
+/// Lists the canonical urls of a part of our locations in all languages.
+#[utoipa::path(
+    tags=["locations"],
+    params(SitemapShardPathParams),
+    responses(
+        (status = 200, description = "**Sitemap** of some locations", body = String, content_type = "application/xml"),
+        (status = 404, description = "**Not found.** The shard does not exist", body = String, content_type = "text/plain", example = "Not found"),
+    )
+)]
+#[get(
+    "/api/sitemap-{shard}.xml",
+    wrap = "actix_middleware_etag::Etag::default()"
+)]
+pub async fn sitemap_shard_handler(
+    params: web::Path<SitemapShardPathParams>,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    match SitemapEntry::fetch_shard(&data.pool, i64::from(params.shard), SHARD_SIZE).await {
+        Ok(entries) if entries.is_empty() => HttpResponse::NotFound()
+            .content_type("text/plain")
+            .body("Not found"),
+        Ok(entries) => xml_response(render_urlset(&entries)),
+        Err(e) => {
+            error!(error = ?e, shard = params.shard, "Could not get the sitemap shard");
+            HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("Internal Server Error")
+        }
+    }
+}
+
+fn xml_response(body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/xml")
+        .insert_header(CacheControl(vec![
+            CacheDirective::MaxAge(24 * 60 * 60), // valid for 1d
+            CacheDirective::Public,
+        ]))
+        .body(body)
+}
+
+fn format_lastmod(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn render_index(shards: &[SitemapShard]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+"#,
+    );
+    for shard in shards {
+        writeln!(
+            xml,
+            "<sitemap><loc>{WEBSITE}/api/sitemap-{shard}.xml</loc><lastmod>{lastmod}</lastmod></sitemap>",
+            shard = shard.shard,
+            lastmod = format_lastmod(shard.last_modified),
+        )
+        .expect("writing to a String is infallible");
+    }
+    writeln!(xml, "<sitemap><loc>{WEB_SITEMAP_URL}</loc></sitemap>")
+        .expect("writing to a String is infallible");
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+/// Renders one `<url>` per location and language, each referencing all translations via `hreflang`.
+fn render_urlset(entries: &[SitemapEntry]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:xhtml="http://www.w3.org/1999/xhtml">
+"#,
+    );
+    for entry in entries {
+        let path = canonical_path(entry);
+        let urls = WEBSITE_LANGUAGES
+            .map(|(lang, prefix)| (lang, xml::escape(&format!("{WEBSITE}{prefix}{path}"))));
+        let mut alternates = String::new();
+        for (lang, url) in &urls {
+            write!(
+                alternates,
+                r#"<xhtml:link rel="alternate" hreflang="{lang}" href="{url}"/>"#
+            )
+            .expect("writing to a String is infallible");
+        }
+        let default = &urls[0].1;
+        let lastmod = format_lastmod(entry.last_modified);
+        for (_, loc) in &urls {
+            writeln!(
+                xml,
+                r#"<url><loc>{loc}</loc><lastmod>{lastmod}</lastmod>{alternates}<xhtml:link rel="alternate" hreflang="x-default" href="{default}"/></url>"#
+            )
+            .expect("writing to a String is infallible");
+        }
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// The canonical path of a location, with its id percent-encoded.
+fn canonical_path(entry: &SitemapEntry) -> String {
+    let mut alias = entry.alias.clone();
+    alias.visible_id = utf8_percent_encode(&alias.visible_id, PATH_SEGMENT).to_string();
+    alias.redirect_exact_match()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(
+        clippy::unwrap_used,
+        reason = "the timestamps are hard-coded and valid"
+    )]
+    use super::*;
+    use crate::db::location::LocationKeyAlias;
+    use chrono::TimeZone as _;
+
+    #[test]
+    fn urlset_lists_all_languages() {
+        let entry = SitemapEntry {
+            alias: LocationKeyAlias {
+                key: "5606.EG.036".to_string(),
+                visible_id: "5606.EG.036".to_string(),
+                r#type: "room".to_string(),
+            },
+            last_modified: Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap(),
+        };
+        insta::assert_snapshot!(render_urlset(&[entry]));
+    }
+
+    #[test]
+    fn index_lists_shards_and_webclient() {
+        let shards = [SitemapShard {
+            shard: 0,
+            last_modified: Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap(),
+        }];
+        insta::assert_snapshot!(render_index(&shards));
+    }
+
+    #[test]
+    fn ids_are_escaped() {
+        let entry = SitemapEntry {
+            alias: LocationKeyAlias {
+                key: "a&b".to_string(),
+                visible_id: "a&b c".to_string(),
+                r#type: "poi".to_string(),
+            },
+            last_modified: Utc::now(),
+        };
+        assert_eq!(canonical_path(&entry), "/poi/a&b%20c");
+        assert!(render_urlset(&[entry]).contains("<loc>https://nav.tum.de/poi/a&amp;b%20c</loc>"));
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{HttpResponse, post, web};
+use chrono::{DateTime, Utc};
+use futures::{StreamExt as _, TryStreamExt as _, stream};
+use meilisearch_sdk::client::Client;
+use moka::future::Cache;
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::error;
+
+use crate::db::location::{LocationKeyAlias, LocationPin};
+use crate::localisation::{LangQueryArgs, LanguagePreferences};
+use crate::search_executor;
+
+mod parser;
+
+/// Each distinct location costs a lookup, a search at worst.
+/// A semester of lectures, exercises and labs takes place in far fewer rooms.
+const MAX_LOCATIONS: usize = 50;
+/// Locations resolved at the same time per upload
+const RESOLVE_CONCURRENCY: usize = 8;
+/// The same locations are uploaded by everyone attending the same lectures
+const RESOLUTION_CACHE_CAPACITY: u64 = 10_000;
+/// Resolutions only change when the data is updated
+const RESOLUTION_CACHE_TIME_TO_LIVE: Duration = Duration::from_hours(1);
+
+/// Resolved locations, shared between uploads
+#[derive(Clone, Debug)]
+pub struct ResolutionCache(Cache<String, Option<(String, ResolvedBy)>>);
+
+impl Default for ResolutionCache {
+    fn default() -> Self {
+        Self(
+            Cache::builder()
+                .max_capacity(RESOLUTION_CACHE_CAPACITY)
+                .time_to_live(RESOLUTION_CACHE_TIME_TO_LIVE)
+                .build(),
+        )
+    }
+}
+
+/// Resolve the locations of a timetable
+///
+/// Upload an iCalendar (`.ics`) export of your timetable, e.g. the personal calendar from `TUMonline`, to find out where your events are.
+/// The `LOCATION` of each event is resolved to a location on `NavigaTUM`:
+/// - `room_code`: the location contains a room code like `5510.02.001` or an arch name like `2001@5510`
+/// - `search`: otherwise, the best search result matching every word of the location is used
+/// - `unresolved`: nothing matched well enough (e.g. `Online` or `Raum folgt`), so the event should not be shown on a map
+/// - `no_location`: the event has no location
+///
+/// Resolving by search is a best guess, clients should show that the location might be wrong.
+/// At most 50 distinct locations can be resolved at once.
+#[utoipa::path(
+    tags=["calendar"],
+    params(LangQueryArgs),
+    request_body(content = String, description = "An iCalendar file", content_type = "text/calendar"),
+    responses(
+        (status = 200, description = "The **events of the timetable** with their locations", body = TimetableResponse, content_type = "application/json"),
+        (status = 400, description = "**Bad Request.** Make sure that the body is an iCalendar file with at most 50 distinct locations", body = String, content_type = "text/plain", example = "the upload is not an iCalendar file (no BEGIN:VCALENDAR)"),
+        (status = 413, description = "**Payload too large.** The iCalendar file may be at most 2MB"),
+        (status = 500, description = "**Internal server error**", body = String, content_type = "text/plain"),
+    )
+)]
+#[post("/api/timetable/resolve")]
+pub async fn resolve_handler(
+    languages: LanguagePreferences,
+    body: String,
+    data: web::Data<crate::AppData>,
+) -> HttpResponse {
+    let events = match parser::parse_calendar(&body) {
+        Ok(events) => events,
+        Err(e) => {
+            return HttpResponse::BadRequest()
+                .content_type("text/plain")
+                .body(e.to_string());
+        }
+    };
+    let locations = events
+        .iter()
+        .filter_map(|e| e.location.clone())
+        .filter(|l| !l.is_empty())
+        .collect::<HashSet<_>>();
+    if locations.len() > MAX_LOCATIONS {
+        return HttpResponse::BadRequest()
+            .content_type("text/plain")
+            .body(format!(
+                "the calendar has more than {MAX_LOCATIONS} distinct locations"
+            ));
+    }
+
+    let resolutions = match resolve_all(&data, locations).await {
+        Ok(resolutions) => resolutions,
+        Err(e) => {
+            error!(error = ?e, "could not resolve the locations");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not resolve the locations, please try again later");
+        }
+    };
+
+    let keys = resolutions
+        .values()
+        .map(|(key, _)| key.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let pins = match LocationPin::fetch_all(&data.pool, &keys, &languages.tags()).await {
+        Ok(pins) => pins
+            .into_iter()
+            .map(|p| (p.key.clone(), ResolvedLocationResponse::from(p)))
+            .collect::<HashMap<_, _>>(),
+        Err(e) => {
+            error!(error = ?e, "could not get the resolved locations");
+            return HttpResponse::InternalServerError()
+                .content_type("text/plain")
+                .body("could not resolve the locations, please try again later");
+        }
+    };
+
+    let events = events
+        .into_iter()
+        .map(|event| {
+            let resolution = event
+                .location
+                .as_deref()
+                .and_then(|l| resolutions.get(l))
+                .and_then(|(key, by)| Some((pins.get(key)?.clone(), *by)));
+            let resolved_by = match (&resolution, event.location.as_deref()) {
+                (Some((_, by)), _) => *by,
+                (None, None | Some("")) => ResolvedBy::NoLocation,
+                (None, Some(_)) => ResolvedBy::Unresolved,
+            };
+            TimetableEventResponse {
+                uid: event.uid,
+                summary: event.summary,
+                start_at: event.start_at,
+                end_at: event.end_at,
+                rrule: event.rrule,
+                location: event.location,
+                resolved_by,
+                resolved: resolution.map(|(location, _)| location),
+            }
+        })
+        .collect::<Vec<_>>();
+    let unresolved = events
+        .iter()
+        .filter(|e| e.resolved_by == ResolvedBy::Unresolved)
+        .count();
+    HttpResponse::Ok().json(TimetableResponse { unresolved, events })
+}
+
+/// The keys the `locations` resolve to, leaving out those which could not be resolved
+async fn resolve_all(
+    data: &crate::AppData,
+    locations: HashSet<String>,
+) -> Result<HashMap<String, (String, ResolvedBy)>, Arc<anyhow::Error>> {
+    let ms_url = env::var("MIELI_URL").unwrap_or_else(|_| "http://localhost:7700".to_string());
+    let client = Client::new(ms_url, env::var("MEILI_MASTER_KEY").ok())
+        .inspect_err(|e| error!(error = ?e, "Failed to create a meilisearch client"))
+        .ok();
+    let (cache, pool, client) = (&data.timetable_resolutions.0, &data.pool, client.as_ref());
+    stream::iter(locations)
+        .map(|location| async move {
+            let resolution = cache
+                .try_get_with_by_ref(&location, resolve(pool, client, &location))
+                .await?;
+            Ok((location, resolution))
+        })
+        .buffer_unordered(RESOLVE_CONCURRENCY)
+        .try_filter_map(|(location, resolution)| async move {
+            Ok(resolution.map(|resolution| (location, resolution)))
+        })
+        .try_collect()
+        .await
+}
+
+/// The key a location resolves to, trying the room codes in it before searching for it
+async fn resolve(
+    pool: &PgPool,
+    client: Option<&Client>,
+    location: &str,
+) -> anyhow::Result<Option<(String, ResolvedBy)>> {
+    for candidate in room_code_candidates(location) {
+        let aliases = LocationKeyAlias::fetch_all(pool, candidate).await?;
+        // an ambiguous code is no better than a search
+        if let [alias] = aliases.as_slice() {
+            return Ok(Some((alias.key.clone(), ResolvedBy::RoomCode)));
+        }
+    }
+    let Some(client) = client else {
+        return Ok(None);
+    };
+    let key = search_executor::best_location_match(client, location).await?;
+    Ok(key.map(|key| (key, ResolvedBy::Search)))
+}
+
+/// Parts of a location which look like a room code (`5510.02.001`) or arch name (`2001@5510`)
+fn room_code_candidates(location: &str) -> impl Iterator<Item = &str> {
+    location
+        .split(|c: char| !(c.is_alphanumeric() || matches!(c, '.' | '@' | '-')))
+        .map(|part| part.trim_matches(['.', '-']))
+        .filter(|part| part.contains(['.', '@']) && part.contains(|c: char| c.is_ascii_digit()))
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct TimetableResponse {
+    /// Number of events with a location which could not be resolved
+    #[schema(examples(1))]
+    unresolved: usize,
+    /// The events, in the order of the uploaded file
+    events: Vec<TimetableEventResponse>,
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct TimetableEventResponse {
+    /// `UID` of the event
+    #[schema(examples("950678223"))]
+    uid: Option<String>,
+    /// `SUMMARY` of the event
+    #[schema(examples("Analysis 1, Übung"))]
+    summary: Option<String>,
+    /// start of the (first occurrence of the) event
+    #[schema(examples("2039-01-19T03:14:07Z"))]
+    start_at: Option<DateTime<Utc>>,
+    /// end of the (first occurrence of the) event
+    #[schema(examples("2039-01-19T04:44:07Z"))]
+    end_at: Option<DateTime<Utc>>,
+    /// `RRULE` of the event, as uploaded
+    #[schema(examples("FREQ=WEEKLY;UNTIL=20390207T230000Z"))]
+    rrule: Option<String>,
+    /// `LOCATION` of the event, as uploaded
+    #[schema(examples("MW 2001, Rudolf-Diesel-Hörsaal (5510.02.001)"))]
+    location: Option<String>,
+    resolved_by: ResolvedBy,
+    /// Where the event is, `null` if `resolved_by` is `unresolved` or `no_location`
+    resolved: Option<ResolvedLocationResponse>,
+}
+
+/// How the location of an event was resolved
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolvedBy {
+    /// The location contains a room code or arch name
+    RoomCode,
+    /// The location is the best search result, which might be wrong
+    Search,
+    /// Nothing matched the location well enough
+    Unresolved,
+    /// The event has no location
+    NoLocation,
+}
+
+#[derive(Serialize, Clone, utoipa::ToSchema)]
+struct ResolvedLocationResponse {
+    /// ID of the location
+    #[schema(examples("5510.02.001"))]
+    id: String,
+    /// Name of the location
+    #[schema(examples("5510.02.001 (MW 2001, Rudolf-Diesel-Hörsaal)"))]
+    name: String,
+    /// Type of the location
+    #[schema(examples("room"))]
+    r#type: String,
+    #[schema(examples(48.265_831))]
+    lat: f64,
+    #[schema(examples(11.670_568))]
+    lon: f64,
+    /// Path of the details page of the location, relative to this server
+    #[schema(examples("/room/5510.02.001"))]
+    url: String,
+}
+
+impl From<LocationPin> for ResolvedLocationResponse {
+    fn from(pin: LocationPin) -> Self {
+        Self {
+            url: pin.details_path(),
+            id: pin.key,
+            name: pin.name,
+            r#type: pin.r#type,
+            lat: pin.lat,
+            lon: pin.lon,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn room_codes_are_extracted() {
+        let candidates = |location| room_code_candidates(location).collect::<Vec<_>>();
+        assert_eq!(
+            candidates("MW 2001, Rudolf-Diesel-Hörsaal (5510.02.001)"),
+            ["5510.02.001"]
+        );
+        assert_eq!(
+            candidates("Hörsaal 1 (0001@5602), Garching."),
+            ["0001@5602"]
+        );
+        assert_eq!(
+            candidates("5602.EG.001 / 5602.EG.002"),
+            ["5602.EG.001", "5602.EG.002"]
+        );
+        assert!(candidates("Interims-Hörsaal 1, Dr. Schmidt").is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone as _, Utc};
+use chrono_tz::Europe::Berlin;
+use chrono_tz::Tz;
+use thiserror::Error;
+
+/// More events are not a personal timetable anymore
+const MAX_EVENTS: usize = 5_000;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum IcsError {
+    #[error("the upload is not an iCalendar file (no BEGIN:VCALENDAR)")]
+    NotACalendar,
+    #[error("the calendar has more than {MAX_EVENTS} events")]
+    TooManyEvents,
+}
+
+/// The parts of a `VEVENT` we need to place it on the map
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IcsEvent {
+    pub uid: Option<String>,
+    pub summary: Option<String>,
+    pub location: Option<String>,
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    /// The recurrence rule, as exported
+    pub rrule: Option<String>,
+}
+
+/// Parses the `VEVENT`s of an iCalendar file (RFC 5545).
+///
+/// Only what is needed for resolving the locations is parsed, everything else is ignored.
+/// Malformed properties are skipped instead of rejecting the whole file, as exports are rarely perfect.
+pub fn parse_calendar(input: &str) -> Result<Vec<IcsEvent>, IcsError> {
+    let lines = unfold(input);
+    if !lines
+        .iter()
+        .any(|l| l.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err(IcsError::NotACalendar);
+    }
+    let mut events = Vec::new();
+    let mut current: Option<IcsEvent> = None;
+    // components nested in a VEVENT (e.g. VALARM) have their own properties
+    let mut nesting = 0_usize;
+    for line in &lines {
+        let Some(property) = Property::parse(line) else {
+            continue;
+        };
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(IcsEvent::default());
+                nesting = 0;
+            }
+            ("BEGIN", Some(_)) => nesting += 1,
+            ("END", Some(_)) if nesting > 0 => nesting -= 1,
+            ("END", Some(_)) => {
+                if events.len() >= MAX_EVENTS {
+                    return Err(IcsError::TooManyEvents);
+                }
+                events.extend(current.take());
+            }
+            (_, Some(event)) if nesting == 0 => event.apply(&property),
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+impl IcsEvent {
+    fn apply(&mut self, property: &Property) {
+        match property.name.as_str() {
+            "UID" => self.uid = Some(property.value.clone()),
+            "SUMMARY" => self.summary = Some(unescape(&property.value)),
+            "LOCATION" => self.location = Some(unescape(&property.value)),
+            "DTSTART" => self.start_at = property.date_time(),
+            "DTEND" => self.end_at = property.date_time(),
+            "RRULE" => self.rrule = Some(property.value.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// Joins folded lines, i.e. lines continued by starting the next one with a space or tab
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Property {
+    /// uppercased name
+    name: String,
+    /// uppercased parameter names and their unquoted values
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    /// Splits `NAME;PARAM=VALUE:value`, ignoring `:` and `;` in quoted parameter values
+    fn parse(line: &str) -> Option<Self> {
+        let mut in_quotes = false;
+        let mut separators = Vec::new();
+        let mut colon = None;
+        for (i, c) in line.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ';' if !in_quotes => separators.push(i),
+                ':' if !in_quotes => {
+                    colon = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let colon = colon?;
+        let (head, value) = (line.get(..colon)?, line.get(colon + 1..)?);
+        let mut parts = split_at_all(head, &separators).into_iter();
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((
+                    key.to_ascii_uppercase(),
+                    value.trim_matches('"').to_string(),
+                ))
+            })
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// `DTSTART`/`DTEND` in UTC.
+    ///
+    /// Floating times and unknown `TZID`s are assumed to be in Munich, all-day dates start at local midnight.
+    fn date_time(&self) -> Option<DateTime<Utc>> {
+        let value = self.value.trim();
+        if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+            let at = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            return Some(at.and_utc());
+        }
+        let tz = self
+            .param("TZID")
+            .and_then(|tz| tz.trim_start_matches('/').parse::<Tz>().ok())
+            .unwrap_or(Berlin);
+        let at = if self.param("VALUE") == Some("DATE") || value.len() == 8 {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)?
+        } else {
+            NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?
+        };
+        // for ambiguous times (DST ending), the earlier one is as good a guess as any
+        tz.from_local_datetime(&at)
+            .earliest()
+            .map(|at| at.with_timezone(&Utc))
+    }
+}
+
+fn split_at_all<'a>(text: &'a str, positions: &[usize]) -> Vec<&'a str> {
+    let mut parts = Vec::with_capacity(positions.len() + 1);
+    let mut start = 0;
+    for &end in positions {
+        parts.extend(text.get(start..end));
+        start = end + 1;
+    }
+    parts.extend(text.get(start..));
+    parts
+}
+
+/// Reverses the escaping of `TEXT` values
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped.trim().to_string()
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "test fixtures")]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//TUM//TUMonline//DE\r
+BEGIN:VEVENT\r
+UID:950678223\r
+SUMMARY:Analysis 1\\, Übung\r
+LOCATION:MW 2001\\, Rudolf-Diesel-Hörsaal (5510.02.001)\r
+DTSTART;TZID=Europe/Berlin:20391017T081500\r
+DTEND;TZID=\"Europe/Berlin\":20391017T094500\r
+RRULE:FREQ=WEEKLY;UNTIL=20400207T230000Z\r
+BEGIN:VALARM\r
+DESCRIPTION:not the summary\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:950678224\r
+SUMMARY:Sehr lange Vorlesung mit einem Titel\\, der gefaltet\r
+  werden muss\r
+DTSTART:20390101T120000Z\r
+DTEND;VALUE=DATE:20390102\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn events_are_parsed() {
+        let events = parse_calendar(CALENDAR).unwrap();
+        assert_eq!(
+            events,
+            [
+                IcsEvent {
+                    uid: Some("950678223".to_string()),
+                    summary: Some("Analysis 1, Übung".to_string()),
+                    location: Some("MW 2001, Rudolf-Diesel-Hörsaal (5510.02.001)".to_string()),
+                    start_at: Some(Utc.with_ymd_and_hms(2039, 10, 17, 6, 15, 0).unwrap()),
+                    end_at: Some(Utc.with_ymd_and_hms(2039, 10, 17, 7, 45, 0).unwrap()),
+                    rrule: Some("FREQ=WEEKLY;UNTIL=20400207T230000Z".to_string()),
+                },
+                IcsEvent {
+                    uid: Some("950678224".to_string()),
+                    summary: Some(
+                        "Sehr lange Vorlesung mit einem Titel, der gefaltet werden muss"
+                            .to_string()
+                    ),
+                    location: None,
+                    start_at: Some(Utc.with_ymd_and_hms(2039, 1, 1, 12, 0, 0).unwrap()),
+                    end_at: Some(Utc.with_ymd_and_hms(2039, 1, 1, 23, 0, 0).unwrap()),
+                    rrule: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert_eq!(
+            parse_calendar("BEGIN:VCARD\nEND:VCARD"),
+            Err(IcsError::NotACalendar)
+        );
+        assert_eq!(parse_calendar(""), Err(IcsError::NotACalendar));
+    }
+
+    #[test]
+    fn quoted_params_may_contain_separators() {
+        let property = Property::parse(
+            "LOCATION;ALTREP=\"https://nav.tum.de:443/room\";LANGUAGE=de:Hörsaal 1",
+        )
+        .unwrap();
+        assert_eq!(property.name, "LOCATION");
+        assert_eq!(
+            property.param("ALTREP"),
+            Some("https://nav.tum.de:443/room")
+        );
+        assert_eq!(property.param("LANGUAGE"), Some("de"));
+        assert_eq!(property.value, "Hörsaal 1");
+    }
+}
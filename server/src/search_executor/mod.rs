@@ -1,12 +1,15 @@
 use chrono::{DateTime, Utc};
 use meilisearch_sdk::client::Client;
+use meilisearch_sdk::errors::Error;
 use parser::TextToken;
 use serde::Serialize;
 use std::fmt::{self, Debug, Formatter};
 use strum::EnumCount as _;
 use tracing::error;
 
-use crate::external::meilisearch::{GeoEntryQuery, LocationEntryType, MSHit, UpcomingEvent};
+use crate::external::meilisearch::{
+    GeoEntryQuery, LocationEntryType, MSHit, UpcomingEvent, fetch_location_match,
+};
 use crate::external::nominatim::Nominatim;
 use crate::limited::vec::LimitedVec;
 use crate::routes::search::{FormattingConfig, Limits};
@@ -409,17 +412,7 @@ pub async fn do_geoentry_search(
     sorting: Vec<String>,
 ) -> LimitedVec<ResultsSection> {
     let parsed_input = ParsedQuery::from(q);
-
-    let meili_query = parsed_input
-        .tokens
-        .clone()
-        .into_iter()
-        .map(|s| match s {
-            TextToken::Text(t) => parser::strip_room_code_leading_zeros(&t),
-            TextToken::SplittableText((t1, t2)) => format!("{t1} {t2} {t1}{t2}"),
-        })
-        .collect::<Vec<String>>()
-        .join(" ");
+    let meili_query = meili_query(&parsed_input);
     let mut request =
         GeoEntryQuery::from((client, meili_query.clone(), &limits, &formatting_config));
     for sort in &sorting {
@@ -506,6 +499,38 @@ pub async fn do_geoentry_search(
     LimitedVec(sections)
 }
 
+/// The query sent to meilisearch, with room codes normalised and splittable tokens expanded
+fn meili_query(parsed_input: &ParsedQuery) -> String {
+    parsed_input
+        .tokens
+        .iter()
+        .map(|s| match s {
+            TextToken::Text(t) => parser::strip_room_code_leading_zeros(t),
+            TextToken::SplittableText((t1, t2)) => format!("{t1} {t2} {t1}{t2}"),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The id of the site, building, room or POI matching `q`, if one matches well enough.
+///
+/// Used where a free-text location has to be pinned to exactly one entry.
+/// Weak matches are `None`, as pinning e.g. `Online` to an arbitrary entry is worse than not pinning it.
+#[tracing::instrument(skip(client))]
+pub async fn best_location_match(client: &Client, q: &str) -> Result<Option<String>, Error> {
+    // every word has to match, so splittable tokens are kept as typed instead of being expanded
+    let query = ParsedQuery::from(q)
+        .tokens
+        .iter()
+        .map(|s| match s {
+            TextToken::Text(t) => parser::strip_room_code_leading_zeros(t),
+            TextToken::SplittableText((t1, t2)) => format!("{t1}{t2}"),
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    fetch_location_match(client, &query).await
+}
+
 #[cfg(test)]
 mod test {
     #![allow(
@@ -1659,10 +1684,10 @@ mod test {
         let ms = MeiliSearchTestContainer::new().await;
         let entries = ms.client.index("entries");
 
-        // `calendar.room_code` is a foreign key into `en` (which references `de`),
-        // so the hosting room must exist in both before any calendar row can.
+        // `calendar.room_code` is a foreign key into `locations`,
+        // so the hosting room must exist before any calendar row can.
         // `name`/`type`/`lat`/`lon` are columns generated from `data`, so only
-        // `key` and `data` are insertable - mirroring the real loader.
+        // `key`, `lang` and `data` are insertable - mirroring the real loader.
         let room_code = "5606.EG.011";
         let room_data = serde_json::json!({
             "name": "Testhörsaal",
@@ -1671,13 +1696,12 @@ mod test {
             "coords": { "lat": 48.0, "lon": 11.0, "source": "navigatum" },
         })
         .to_string();
-        sqlx::query("INSERT INTO de (key, data) VALUES ($1, $2::jsonb)")
+        sqlx::query("INSERT INTO locations (key) VALUES ($1)")
             .bind(room_code)
-            .bind(&room_data)
             .execute(&pg.pool)
             .await
             .unwrap();
-        sqlx::query("INSERT INTO en (key, data) VALUES ($1, $2::jsonb)")
+        sqlx::query("INSERT INTO location_data (key, lang, data) VALUES ($1, 'de', $2::jsonb), ($1, 'en', $2::jsonb)")
             .bind(room_code)
             .bind(&room_data)
             .execute(&pg.pool)
@@ -1815,6 +1839,113 @@ mod test {
         );
     }
 
+    /// Free-text locations are only pinned to an entry if all of their words match it
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_weak_location_matches_are_not_pinned() {
+        let ms = MeiliSearchTestContainer::new().await;
+        let room_doc = serde_json::json!({
+            "ms_id": "5510-02-001",
+            "facet": "room",
+            "type": "room",
+            "room_code": "5510.02.001",
+            "name": "5510.02.001 (MW 2001, Rudolf-Diesel-Hörsaal)",
+            "type_common_name": "Hörsaal",
+            "rank": 100,
+            "parent_building_names": ["Maschinenwesen (MW)"],
+            "parent_keywords": ["mw", "garching"],
+        });
+        ms.client
+            .index("entries")
+            .add_documents(&[room_doc], Some("ms_id"))
+            .await
+            .unwrap()
+            .wait_for_completion(&ms.client, None, Some(std::time::Duration::from_secs(30)))
+            .await
+            .unwrap();
+
+        for location in ["Rudolf-Diesel-Hörsaal", "MW 2001"] {
+            assert_eq!(
+                best_location_match(&ms.client, location).await.unwrap(),
+                Some("5510.02.001".to_string()),
+                "{location} should resolve"
+            );
+        }
+        for location in ["Online", "Zoom", "Raum folgt", "Hörsaal folgt"] {
+            assert_eq!(
+                best_location_match(&ms.client, location).await.unwrap(),
+                None,
+                "{location} should stay unresolved"
+            );
+        }
+    }
+
+    /// `semester=` narrows the lectures down to those taking place during the semester,
+    /// using the same filter as the search endpoint.
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_lectures_filtered_by_semester() {
+        let pg = PostgresTestContainer::new().await;
+        let ms = MeiliSearchTestContainer::new().await;
+        sqlx::raw_sql(
+            r#"
+            INSERT INTO semesters (key, start_date, end_date)
+            VALUES ('2039S', '2039-04-01', '2039-09-30'),
+                   ('2039W', '2039-10-01', '2040-03-31');
+            INSERT INTO locations (key) VALUES ('5606.EG.011'), ('5501.01.001');
+            INSERT INTO location_data (key, lang, data)
+            SELECT key, lang, jsonb_build_object('name', key, 'type', 'room', 'type_common_name', 'Hörsaal',
+                                                 'coords', '{"lat": 48.0, "lon": 11.0, "source": "navigatum"}'::jsonb)
+            FROM (VALUES ('5606.EG.011'), ('5501.01.001')) rooms(key)
+            CROSS JOIN (VALUES ('de'), ('en')) languages(lang);
+            INSERT INTO calendar (id, room_code, start_at, end_at, title_de, title_en, stp_type, entry_type, detailed_entry_type)
+            VALUES (1, '5606.EG.011', '2039-05-02 08:00Z', '2039-05-02 10:00Z', 'Filterkunde 1', 'Filtering 1', 'Vorlesung', 'lecture', 'Abhaltung'),
+                   (2, '5606.EG.011', '2039-10-10 08:00Z', '2039-10-10 10:00Z', 'Filterkunde 2', 'Filtering 2', 'Vorlesung', 'lecture', 'Abhaltung'),
+                   (3, '5501.01.001', '2039-10-11 08:00Z', '2039-10-11 10:00Z', 'Filterkunde 3', 'Filtering 3', 'Vorlesung', 'lecture', 'Abhaltung');
+            "#,
+        )
+        .execute(&pg.pool)
+        .await
+        .unwrap();
+        crate::refresh::lectures::refresh_once(&pg.pool, &ms.client)
+            .await
+            .unwrap();
+
+        let search = |semester: &[&str]| {
+            let filter = crate::routes::search::build_lecture_filter(
+                &semester.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            );
+            let client = ms.client.clone();
+            async move {
+                let results = do_geoentry_search(
+                    &client,
+                    "Filterkunde",
+                    Limits::default(),
+                    FormattingConfig::default(),
+                    filter,
+                    vec![],
+                )
+                .await;
+                let mut titles: Vec<String> = results
+                    .0
+                    .iter()
+                    .find_map(ResultsSection::lectures)
+                    .map_or_else(Vec::new, |s| {
+                        s.entries.iter().map(|e| e.title_de.clone()).collect()
+                    });
+                titles.sort();
+                titles
+            }
+        };
+        assert_eq!(
+            search(&[]).await,
+            ["Filterkunde 1", "Filterkunde 2", "Filterkunde 3"]
+        );
+        assert_eq!(search(&["2039S"]).await, ["Filterkunde 1"]);
+        assert_eq!(search(&["2039W"]).await, ["Filterkunde 2", "Filterkunde 3"]);
+        assert!(search(&["2040S"]).await.is_empty());
+    }
+
     /// The `upcoming` cap is `max(next 10 occurrences, 14-day window)`: a daily
     /// tutorial is bounded by the window (~14 entries), a weekly lecture by the
     /// count (10 entries). This seeds both, derives, and asserts each arm of the
@@ -1847,14 +1978,13 @@ mod test {
             "coords": { "lat": 48.0, "lon": 11.0, "source": "navigatum" },
         })
         .to_string();
-        // `calendar.room_code` is a foreign key into `en` (which references `de`).
-        sqlx::query("INSERT INTO de (key, data) VALUES ($1, $2::jsonb)")
+        // `calendar.room_code` is a foreign key into `locations`.
+        sqlx::query("INSERT INTO locations (key) VALUES ($1)")
             .bind(room_code)
-            .bind(&room_data)
             .execute(&pg.pool)
             .await
             .unwrap();
-        sqlx::query("INSERT INTO en (key, data) VALUES ($1, $2::jsonb)")
+        sqlx::query("INSERT INTO location_data (key, lang, data) VALUES ($1, 'de', $2::jsonb), ($1, 'en', $2::jsonb)")
             .bind(room_code)
             .bind(&room_data)
             .execute(&pg.pool)
@@ -1,32 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::env;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
 use crate::limited::vec::LimitedVec;
+use crate::localisation::Language;
 use crate::setup::file_loader;
 use bytes::Bytes;
 use parquet::file::reader::{FileReader as _, SerializedFileReader};
 use parquet::record::Field;
-use serde_json::Value;
+use serde_json::{Map, Value};
 use sqlx::{Postgres, Transaction};
 
 #[derive(Clone)]
 pub(super) struct DelocalisedValues {
     key: String,
     hash: i64,
-    de: Value,
-    en: Value,
+    /// The data in every language present in the export, `de` and `en` first.
+    ///
+    /// Untranslated strings of further languages are filled in from `en` and `de`.
+    localised: Vec<(String, Value)>,
 }
-#[expect(
-    clippy::missing_fields_in_debug,
-    reason = "Debug intentionally elides the de/en JSON payloads for log readability"
-)]
 impl fmt::Debug for DelocalisedValues {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("DelocalisedValues")
             .field("key", &self.key)
             .field("hash", &self.hash)
+            // the JSON payloads are elided for log readability
+            .field(
+                "localised",
+                &self
+                    .localised
+                    .iter()
+                    .map(|(lang, _)| lang)
+                    .collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
@@ -57,39 +65,78 @@ impl From<HashMap<String, Value>> for DelocalisedValues {
             .expect("a hash should always exist")
             .as_i64()
             .expect("a hash should be a valid i64");
+        let mut languages = BTreeSet::new();
+        for v in value.values() {
+            Self::find_languages(v, &mut languages);
+        }
+        let delocalised = |fallback_chain: &[&str]| -> Value {
+            value
+                .iter()
+                .map(|(k, v)| (k.clone(), Self::delocalise(v.clone(), fallback_chain)))
+                .collect()
+        };
+        let base = [
+            ("de".to_string(), delocalised(&["de"])),
+            ("en".to_string(), delocalised(&["en"])),
+        ];
+        let further = languages.into_iter().map(|lang| {
+            let data = delocalised(&[lang.as_str(), "en", "de"]);
+            (lang, data)
+        });
         Self {
             key,
             hash,
-            de: value
-                .clone()
-                .into_iter()
-                .map(|(k, v)| (k, Self::delocalise(v.clone(), "de")))
-                .collect(),
-            en: value
-                .clone()
-                .into_iter()
-                .map(|(k, v)| (k, Self::delocalise(v.clone(), "en")))
-                .collect(),
+            localised: base.into_iter().chain(further).collect(),
         }
     }
 }
 impl DelocalisedValues {
-    fn delocalise(value: Value, language: &'static str) -> Value {
+    /// Whether `obj` is a localised string like `{"de": "Hörsaal", "en": "lecture hall"}`
+    fn is_localised(obj: &Map<String, Value>) -> bool {
+        obj.contains_key("de") || obj.contains_key("en")
+    }
+    /// Collects the languages beyond `de` and `en` which strings are translated to
+    fn find_languages(value: &Value, languages: &mut BTreeSet<String>) {
+        match value {
+            Value::Array(arr) => {
+                for value in arr {
+                    Self::find_languages(value, languages);
+                }
+            }
+            Value::Object(obj) if Self::is_localised(obj) => {
+                let translations = obj
+                    .iter()
+                    .filter(|(lang, text)| *lang != "de" && *lang != "en" && text.is_string())
+                    .filter_map(|(lang, _)| Language::try_from(lang.clone()).ok());
+                languages.extend(translations.map(String::from));
+            }
+            Value::Object(obj) => {
+                for value in obj.values() {
+                    Self::find_languages(value, languages);
+                }
+            }
+            _ => {}
+        }
+    }
+    /// Replaces localised strings by their translation into the first language of `fallback_chain` they have
+    fn delocalise(value: Value, fallback_chain: &[&str]) -> Value {
         match value {
             Value::Array(arr) => Value::Array(
                 arr.into_iter()
-                    .map(|value| Self::delocalise(value, language))
+                    .map(|value| Self::delocalise(value, fallback_chain))
                     .collect(),
             ),
             Value::Object(obj) => {
-                if obj.contains_key("de") || obj.contains_key("en") {
-                    obj.get(language)
+                if Self::is_localised(&obj) {
+                    fallback_chain
+                        .iter()
+                        .find_map(|language| obj.get(*language))
                         .cloned()
                         .unwrap_or(Value::String(String::new()))
                 } else {
                     Value::Object(
                         obj.into_iter()
-                            .map(|(key, value)| (key, Self::delocalise(value, language)))
+                            .map(|(key, value)| (key, Self::delocalise(value, fallback_chain)))
                             .filter(|(key, _)| key != "de" && key != "en")
                             .collect(),
                     )
@@ -101,29 +148,43 @@ impl DelocalisedValues {
     async fn store(self, tx: &mut Transaction<'_, Postgres>) -> Result<(), sqlx::Error> {
         sqlx::query!(
             r#"
-            INSERT INTO de(key,data,hash)
-            VALUES ($1,$2,$3)
+            INSERT INTO locations(key,hash)
+            VALUES ($1,$2)
             ON CONFLICT (key) DO UPDATE
-            SET data = EXCLUDED.data,
-                hash = EXCLUDED.hash"#,
+            SET hash = EXCLUDED.hash,
+                hash_updated_at = NOW()"#,
             self.key,
-            self.de,
             self.hash,
         )
         .execute(&mut **tx)
         .await?;
 
+        let languages: Vec<String> = self
+            .localised
+            .iter()
+            .map(|(lang, _)| lang.clone())
+            .collect();
         sqlx::query!(
-            r#"
-            INSERT INTO en(key,data)
-            VALUES ($1,$2)
-            ON CONFLICT (key) DO UPDATE
-            SET data = EXCLUDED.data"#,
+            "DELETE FROM location_data WHERE key = $1 AND lang <> ALL($2::text[])",
             self.key,
-            self.en,
+            &languages,
         )
         .execute(&mut **tx)
         .await?;
+        for (lang, data) in self.localised {
+            sqlx::query!(
+                r#"
+                INSERT INTO location_data(key,lang,data)
+                VALUES ($1,$2,$3)
+                ON CONFLICT (key,lang) DO UPDATE
+                SET data = EXCLUDED.data"#,
+                self.key,
+                lang,
+                data,
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
 
         Ok(())
     }
@@ -173,3 +234,48 @@ pub async fn download_status() -> anyhow::Result<(LimitedVec<String>, LimitedVec
     }
     Ok((LimitedVec(id_col), LimitedVec(hash_col)))
 }
+
+#[cfg(test)]
+#[expect(
+    clippy::unwrap_used,
+    clippy::indexing_slicing,
+    reason = "test fixtures and JSON assertions, consistent with the other setup tests"
+)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn further_languages_fall_back_to_en_and_de() {
+        let entry: HashMap<String, Value> = serde_json::from_value(json!({
+            "id": "mi",
+            "hash": 42,
+            "name": {"de": "Fakultät Mathematik & Informatik", "en": "Faculty of Mathematics & Informatics", "zh": "数学与信息学院"},
+            "type_common_name": {"de": "Gebäudekomplex", "en": "Building complex", "es": "Complejo de edificios"},
+            "props": {"comment": {"de": "nur auf Deutsch"}},
+        }))
+        .unwrap();
+        let values = DelocalisedValues::from(entry);
+        let languages: Vec<_> = values
+            .localised
+            .iter()
+            .map(|(lang, _)| lang.as_str())
+            .collect();
+        assert_eq!(languages, ["de", "en", "es", "zh"]);
+        let data = |language: &str| {
+            let (_, data) = values
+                .localised
+                .iter()
+                .find(|(lang, _)| lang == language)
+                .unwrap();
+            data
+        };
+        assert_eq!(data("de")["name"], "Fakultät Mathematik & Informatik");
+        assert_eq!(data("en")["props"]["comment"], "");
+        let zh = data("zh");
+        assert_eq!(zh["name"], "数学与信息学院");
+        assert_eq!(zh["type_common_name"], "Building complex");
+        assert_eq!(zh["props"]["comment"], "nur auf Deutsch");
+    }
+}
@@ -61,8 +61,8 @@ pub(crate) async fn find_keys_which_need_updating(
         r#"
 SELECT expected.key AS "key!"
 FROM (SELECT * FROM UNNEST($1::text[], $2::int8[])) as expected(key,hash)
-LEFT JOIN de ON de.key = expected.key
-WHERE de.key IS NULL OR de.hash != expected.hash
+LEFT JOIN locations ON locations.key = expected.key
+WHERE locations.key IS NULL OR locations.hash != expected.hash
 "#,
         keys.as_ref(),
         hashes.as_ref(),
@@ -79,14 +79,30 @@ async fn cleanup_deleted(
     tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
 ) -> anyhow::Result<()> {
     let keys = &keys.0;
+    // keys which are back (e.g. a removal was reverted) are no longer gone.
+    sqlx::query!("DELETE FROM tombstones WHERE key = ANY($1::text[])", keys)
+        .execute(&mut **tx)
+        .await?;
+    // remember what is about to be removed, so that old links can be answered with 410 Gone or a redirect to a successor.
     sqlx::query!(
-        "DELETE FROM aliases WHERE NOT EXISTS (SELECT * FROM UNNEST($1::text[]) AS expected(key) WHERE aliases.key = expected.key)",
+        r#"
+INSERT INTO tombstones (key, visible_id, type, name)
+SELECT d.key, COALESCE(aliases.visible_id, d.key), d.type, d.name
+FROM location_data d
+LEFT JOIN aliases ON aliases.alias = d.key AND aliases.key = d.key
+WHERE d.lang = 'de' AND NOT EXISTS (SELECT * FROM UNNEST($1::text[]) AS expected(key) WHERE d.key = expected.key)
+ON CONFLICT (key) DO UPDATE SET
+ visible_id = EXCLUDED.visible_id,
+ type = EXCLUDED.type,
+ name = EXCLUDED.name,
+ removed_at = EXCLUDED.removed_at
+"#,
         keys
     )
     .execute(&mut **tx)
     .await?;
     sqlx::query!(
-        "DELETE FROM en WHERE NOT EXISTS (SELECT * FROM UNNEST($1::text[]) AS expected(key) WHERE en.key = expected.key)",
+        "DELETE FROM aliases WHERE NOT EXISTS (SELECT * FROM UNNEST($1::text[]) AS expected(key) WHERE aliases.key = expected.key)",
         keys
     )
     .execute(&mut **tx)
@@ -98,7 +114,7 @@ async fn cleanup_deleted(
     .execute(&mut **tx)
     .await?;
     sqlx::query!(
-        "DELETE FROM de WHERE NOT EXISTS (SELECT * FROM UNNEST($1::text[]) AS expected(key) WHERE de.key = expected.key)",
+        "DELETE FROM locations WHERE NOT EXISTS (SELECT * FROM UNNEST($1::text[]) AS expected(key) WHERE locations.key = expected.key)",
         keys
     )
     .execute(&mut **tx)
@@ -7,14 +7,14 @@
 
 use tracing::{debug, info};
 
-/// Per-language override; `{TABLE}` is substituted with a trusted, hard-coded table name.
+/// Rewrites the coordinates of the tagged entries in every language.
 ///
 /// We rewrite `data->'coords'` rather than the columns directly, because `lat`, `lon`,
 /// `coordinate_source`, and `coordinate_accuracy` are `GENERATED` from it. The point lands
 /// on the polygon (`ST_PointOnSurface`, inside even concave rooms), reprojected from web
 /// mercator to WGS84; `accuracy` is dropped because this is a precise room-level coordinate.
-const OVERRIDE_TEMPLATE: &str = r#"
-UPDATE {TABLE} AS t
+const OVERRIDE_SQL: &str = r#"
+UPDATE location_data AS t
 SET data = jsonb_set(jsonb_set(jsonb_set(
         t.data #- '{coords,accuracy}',
         '{coords,lat}',    to_jsonb(c.lat), true),
@@ -32,9 +32,6 @@ FROM (
 WHERE c.key = t.key
 "#;
 
-/// The per-language location tables `OVERRIDE_TEMPLATE` is applied to.
-const LOCALISED_TABLES: [&str; 2] = ["de", "en"];
-
 /// Rewrites `coords` for every location whose key matches a `ref:tum` tagged room.
 ///
 /// `rooms` is owned by osm2pgsql and absent in migration-only setups (local dev, tests), so
@@ -52,13 +49,12 @@ pub(super) async fn override_room_coords(pool: &sqlx::PgPool) -> anyhow::Result<
         return Ok(());
     }
 
-    // If a ref:tum is mapped to more than one polygon, the template prefers the larger one.
-    for table in LOCALISED_TABLES {
-        // `table` is a hard-coded literal, never user input, so the interpolation is safe.
-        let sql = sqlx::AssertSqlSafe(OVERRIDE_TEMPLATE.replace("{TABLE}", table));
-        let updated = sqlx::query(sql).execute(pool).await?.rows_affected();
-        info!(table, updated, "applied ref:tum coordinate override");
-    }
+    // If a ref:tum is mapped to more than one polygon, the override prefers the larger one.
+    let updated = sqlx::query(OVERRIDE_SQL)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    info!(updated, "applied ref:tum coordinate override");
     Ok(())
 }
 
@@ -70,35 +66,6 @@ pub(super) async fn override_room_coords(pool: &sqlx::PgPool) -> anyhow::Result<
 )]
 mod tests {
     use crate::setup::tests::PostgresTestContainer;
-    use rstest::rstest;
-
-    /// `replace` must substitute the `{TABLE}` token only, leaving the JSON-path braces
-    /// (`{coords,lat}` …) intact - otherwise the generated SQL is silently corrupted.
-    /// The cases mirror `LOCALISED_TABLES`, so every table the override runs against is covered.
-    #[rstest]
-    #[case::de("de")]
-    #[case::en("en")]
-    fn substitutes_placeholders_only(#[case] table: &str) {
-        assert!(
-            super::LOCALISED_TABLES.contains(&table),
-            "case `{table}` is not a table the override actually runs against"
-        );
-        let sql = super::OVERRIDE_TEMPLATE.replace("{TABLE}", table);
-
-        assert!(
-            sql.contains(&format!("UPDATE {table} AS t")),
-            "table not substituted:{sql}"
-        );
-        assert!(!sql.contains("{TABLE}"), "placeholder left behind:{sql}");
-        for path in [
-            "{coords,accuracy}",
-            "{coords,lat}",
-            "{coords,lon}",
-            "{coords,source}",
-        ] {
-            assert!(sql.contains(path), "json path `{path}` was mangled:{sql}");
-        }
-    }
 
     /// Mirrors the osm2pgsql-owned table: generic geometry in web mercator plus `ref:tum`.
     async fn create_rooms_table(pool: &sqlx::Pool<sqlx::Postgres>) {
@@ -117,18 +84,19 @@ mod tests {
             "type_common_name": "room",
             "coords": { "lat": 0.0, "lon": 0.0, "source": "inferred", "accuracy": "building" },
         });
-        sqlx::query("INSERT INTO de(key, data, hash) VALUES ($1, $2, 0)")
+        sqlx::query("INSERT INTO locations(key) VALUES ($1)")
             .bind(key)
-            .bind(&data)
-            .execute(pool)
-            .await
-            .unwrap();
-        sqlx::query("INSERT INTO en(key, data) VALUES ($1, $2)")
-            .bind(key)
-            .bind(&data)
             .execute(pool)
             .await
             .unwrap();
+        sqlx::query(
+            "INSERT INTO location_data(key, lang, data) VALUES ($1, 'de', $2), ($1, 'en', $2)",
+        )
+        .bind(key)
+        .bind(&data)
+        .execute(pool)
+        .await
+        .unwrap();
     }
 
     /// Inserts a square `ref:tum` room of half-width `half` degrees centred on (`lon`, `lat`),
@@ -169,8 +137,8 @@ mod tests {
             .await
             .unwrap()
     }
-    const DE_COORDS: &str = "SELECT data->'coords' FROM de WHERE key=$1";
-    const EN_COORDS: &str = "SELECT data->'coords' FROM en WHERE key=$1";
+    const DE_COORDS: &str = "SELECT data->'coords' FROM location_data WHERE key=$1 AND lang='de'";
+    const EN_COORDS: &str = "SELECT data->'coords' FROM location_data WHERE key=$1 AND lang='en'";
 
     #[tokio::test]
     #[tracing_test::traced_test]
@@ -0,0 +1,42 @@
+use parquet::record::Field;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use super::Loader;
+
+#[derive(Debug, Default)]
+pub struct RawKeySuccessor {
+    key: String,
+    successor: String,
+}
+
+pub struct KeySuccessors;
+
+impl Loader for KeySuccessors {
+    const FILENAME: &'static str = "key_successors.parquet";
+    const TRUNCATE_SQL: &'static str = "TRUNCATE TABLE key_successors";
+    const ANALYZE_SQL: &'static str = "ANALYZE key_successors";
+    type Row = RawKeySuccessor;
+
+    fn parse_field(col: &str, field: &Field, r: &mut Self::Row) {
+        match (col, field) {
+            ("key", Field::Str(v)) => r.key.clone_from(v),
+            ("successor", Field::Str(v)) => r.successor.clone_from(v),
+            _ => {}
+        }
+    }
+
+    async fn insert(tx: &mut Transaction<'_, Postgres>, r: &Self::Row) -> anyhow::Result<()> {
+        sqlx::query!(
+            "INSERT INTO key_successors (key, successor) VALUES ($1, $2)",
+            r.key,
+            r.successor,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+}
+
+pub async fn setup(pool: PgPool) -> anyhow::Result<()> {
+    super::run::<KeySuccessors>(pool).await
+}
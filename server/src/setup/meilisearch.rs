@@ -71,6 +71,7 @@ pub async fn setup(client: &Client) -> anyhow::Result<()> {
             "type",
             "usage",
             "next_occurrence_at",
+            "semester",
         ])
         .with_ranking_rules([
             "words",
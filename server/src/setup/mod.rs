@@ -5,12 +5,14 @@ mod loader;
 pub(crate) use loader::{Loader, run};
 
 pub(crate) mod events;
+pub(crate) mod key_successors;
 pub(crate) mod location_images;
 pub mod meilisearch;
 pub(crate) mod operators_de;
 pub(crate) mod operators_en;
 pub(crate) mod parents;
 pub(crate) mod ranking_factors;
+pub(crate) mod semesters;
 pub(crate) mod sources;
 #[cfg(test)]
 pub mod tests;
@@ -0,0 +1,48 @@
+use chrono::NaiveDate;
+use parquet::record::Field;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use super::Loader;
+
+#[derive(Debug, Default)]
+pub struct RawSemester {
+    key: String,
+    start: String,
+    end: String,
+}
+
+pub struct Semesters;
+
+impl Loader for Semesters {
+    const FILENAME: &'static str = "semesters.parquet";
+    const TRUNCATE_SQL: &'static str = "TRUNCATE TABLE semesters";
+    const ANALYZE_SQL: &'static str = "ANALYZE semesters";
+    type Row = RawSemester;
+
+    fn parse_field(col: &str, field: &Field, r: &mut Self::Row) {
+        match (col, field) {
+            ("key", Field::Str(v)) => r.key.clone_from(v),
+            ("start", Field::Str(v)) => r.start.clone_from(v),
+            ("end", Field::Str(v)) => r.end.clone_from(v),
+            _ => {}
+        }
+    }
+
+    async fn insert(tx: &mut Transaction<'_, Postgres>, r: &Self::Row) -> anyhow::Result<()> {
+        let start_date = NaiveDate::parse_from_str(&r.start, "%Y-%m-%d")?;
+        let end_date = NaiveDate::parse_from_str(&r.end, "%Y-%m-%d")?;
+        sqlx::query!(
+            "INSERT INTO semesters (key, start_date, end_date) VALUES ($1, $2, $3)",
+            r.key,
+            start_date,
+            end_date,
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+}
+
+pub async fn setup(pool: PgPool) -> anyhow::Result<()> {
+    super::run::<Semesters>(pool).await
+}
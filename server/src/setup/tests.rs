@@ -118,19 +118,11 @@ async fn test_meilisearch_setup() {
     ms.load_data_retrying().await;
 }
 
-/// Inserts a row into `de` with the minimal JSON needed to satisfy the
-/// generated NOT NULL columns (`name`, `type`, `type_common_name`, `coords.lat`, `coords.lon`).
-async fn seed_de(pool: &sqlx::Pool<sqlx::Postgres>, key: &str, hash: i64) {
-    let data = serde_json::json!({
-        "name": key,
-        "type": "room",
-        "type_common_name": "room",
-        "coords": { "lat": 0.0, "lon": 0.0, "source": "test" },
-    });
+/// Inserts an entry into `locations`, which is all that the hashes are compared against
+async fn seed_location(pool: &sqlx::Pool<sqlx::Postgres>, key: &str, hash: i64) {
     sqlx::query!(
-        "INSERT INTO de(key, data, hash) VALUES ($1, $2, $3)",
+        "INSERT INTO locations(key, hash) VALUES ($1, $2)",
         key,
-        data,
         hash,
     )
     .execute(pool)
@@ -144,8 +136,8 @@ async fn find_keys_returns_missing_and_changed_but_not_unchanged() {
     use crate::limited::vec::LimitedVec;
 
     let pg = PostgresTestContainer::new().await;
-    seed_de(&pg.pool, "A", 1).await;
-    seed_de(&pg.pool, "B", 2).await;
+    seed_location(&pg.pool, "A", 1).await;
+    seed_location(&pg.pool, "B", 2).await;
 
     let keys = LimitedVec(vec!["A".to_string(), "B".to_string(), "C".to_string()]);
     let hashes = LimitedVec(vec![1, 99, 7]);
@@ -0,0 +1,24 @@
+//! Helpers for the XML the server writes by hand, i.e. sitemaps, Atom feeds and the SVGs of door signs.
+
+/// Escapes `text` for use in XML text and attribute values
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_is_escaped() {
+        assert_eq!(
+            escape(r#"<a href="?x=1&y='2'">"#),
+            "&lt;a href=&quot;?x=1&amp;y=&apos;2&apos;&quot;&gt;"
+        );
+        assert_eq!(escape("Hörsaal 1"), "Hörsaal 1");
+    }
+}
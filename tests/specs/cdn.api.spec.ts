@@ -55,19 +55,6 @@ test.describe("CDN Endpoints - Parquet Files", () => {
   });
 });
 
-test.describe("CDN Endpoints - XML Files", () => {
-  test("should serve sitemap.xml with valid XML structure", async ({ request }) => {
-    const response = await request.get("/cdn/sitemap.xml");
-
-    expect(response.status()).toBe(200);
-    expect(response.headers()["content-type"]).toMatch(/xml/);
-
-    const body = await response.text();
-    expect(body).toContain("<?xml");
-    expect(body.includes("<sitemapindex") || body.includes("<urlset")).toBe(true);
-  });
-});
-
 test.describe("CDN Endpoints - Large Images", () => {
   test("should serve large webp images with correct content type", async ({ request }) => {
     const response = await request.get("/cdn/lg/0101_0.webp");
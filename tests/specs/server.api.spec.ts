@@ -382,3 +382,16 @@ test.describe("API Endpoints - Content Negotiation", () => {
     expect(response.headers()["content-type"]).toContain("text/plain");
   });
 });
+
+test.describe("API Endpoints - Sitemap", () => {
+  test("should serve sitemap.xml as a sitemap index", async ({ request }) => {
+    const response = await request.get("/api/sitemap.xml");
+
+    expect(response.status()).toBe(200);
+    expect(response.headers()["content-type"]).toMatch(/xml/);
+
+    const body = await response.text();
+    expect(body).toContain("<?xml");
+    expect(body).toContain("<sitemapindex");
+  });
+});